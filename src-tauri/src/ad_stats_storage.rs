@@ -0,0 +1,56 @@
+// ad_stats_storage.rs
+// Persist lifetime post statistics per ad so they survive app restarts.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdStats {
+    pub total_posts: u64,
+    // unix timestamp (secs) of the last successful post
+    pub last_post_unix: Option<u64>,
+}
+
+// serializes read-modify-write cycles between concurrently running ads
+static STATS_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_stats_file_path() -> Result<PathBuf> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Failed to get config directory"))?;
+    let app_dir = config_dir.join("roli-trade-ad-automation");
+    fs::create_dir_all(&app_dir)?;
+    Ok(app_dir.join("ad_stats.json"))
+}
+
+/// Stats for every ad that has posted at least once, keyed by ad id.
+pub fn load_all_stats() -> Result<HashMap<String, AdStats>> {
+    let path = get_stats_file_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    crate::atomic_file::read_with_backup(&path, |raw| Ok(serde_json::from_str(raw)?))
+}
+
+/// Stats for one ad (all zero if it never posted).
+pub fn get_stats(id: &str) -> Result<AdStats> {
+    let _guard = STATS_LOCK.lock().unwrap();
+    Ok(load_all_stats()?.remove(id).unwrap_or_default())
+}
+
+/// Count a successful post made at `at_unix`. Returns the updated stats.
+pub fn record_post(id: &str, at_unix: u64) -> Result<AdStats> {
+    let _guard = STATS_LOCK.lock().unwrap();
+    let mut all = load_all_stats()?;
+    let entry = all.entry(id.to_string()).or_default();
+    entry.total_posts += 1;
+    entry.last_post_unix = Some(at_unix);
+    let updated = entry.clone();
+
+    let raw = serde_json::to_string_pretty(&all)?;
+    crate::atomic_file::write_atomic(&get_stats_file_path()?, raw)?;
+    Ok(updated)
+}
@@ -1,37 +1,864 @@
 // ads_runner.rs
 // Manage background ad posting tasks (start/stop/list running ads).
 
-use anyhow::Result;
+use crate::api_error::ApiError;
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDateTime, Timelike};
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicU64, AtomicUsize, Ordering},
     Mutex,
 };
-use tauri::{Emitter, Window};
+use tauri::{AppHandle, Emitter, Manager, Window};
 use tokio::sync::oneshot;
 
-// map: ad_id -> (cancellation sender, runner_unique_id)
-static RUNNERS: Lazy<Mutex<HashMap<String, (oneshot::Sender<()>, u64)>>> =
+struct RunnerHandle {
+    cancel: oneshot::Sender<()>,
+    // unique id of the spawned task, so an exiting task never removes a newer runner
+    runner_id: u64,
+    interval_override: Option<u64>,
+    // unix time of the single post for a one-shot scheduled runner
+    scheduled_at: Option<u64>,
+}
+
+// map: ad_id -> handle of its running task
+static RUNNERS: Lazy<Mutex<HashMap<String, RunnerHandle>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
 // global counter for assigning unique ids to spawned runners
 static RUNNER_COUNTER: Lazy<AtomicU64> = Lazy::new(|| AtomicU64::new(1));
 
-// track successful post counts per ad id
+// track successful post counts per ad id (seeded from the persisted lifetime stats)
 static POST_COUNTS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
+// successful posts per ad id in its current run, reset whenever a runner starts; `max_posts`
+// is checked against this rather than the lifetime count above
+static RUN_POSTS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// unix timestamp (secs) of the next scheduled post per ad id, updated before each sleep
+static NEXT_POST: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// consecutive non-verification post failures per ad id, reset on success
+static CONSECUTIVE_FAILURES: Lazy<Mutex<HashMap<String, u32>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// longest wait between retries while backing off after failures
+const MAX_BACKOFF_MINS: u64 = 120;
+// stop the runner after this many consecutive failures unless the ad overrides it
+const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 10;
+// random +/- offset applied to each sleep unless the ad sets its own jitter_seconds
+const DEFAULT_JITTER_SECS: u64 = 90;
+// jitter never pulls a wait below this (or below the wait itself when it is shorter),
+// so a 15 minute interval or a server cooldown is never cut short
+const MIN_JITTERED_SECS: u64 = 15 * 60;
+
+/// How many ads may run (or wait scheduled) at once unless the user changes it.
+pub const DEFAULT_MAX_CONCURRENT_ADS: usize = 20;
+
+// cap on RUNNERS entries; 0 = unlimited. Loaded from runner_settings.json on first use.
+static MAX_CONCURRENT_ADS: Lazy<AtomicUsize> = Lazy::new(|| {
+    let saved = crate::running_ads_storage::load_runner_settings()
+        .map_err(|e| log::warn!("ads_runner: failed to load runner settings: {}", e))
+        .ok()
+        .and_then(|s| s.max_concurrent_ads);
+    AtomicUsize::new(saved.unwrap_or(DEFAULT_MAX_CONCURRENT_ADS))
+});
+
+pub fn max_concurrent_ads() -> usize {
+    MAX_CONCURRENT_ADS.load(Ordering::Relaxed)
+}
+
+/// Change the cap on simultaneously running ads (0 = unlimited) and remember it across
+/// restarts. Ads already running are left alone even if there are more than `limit`.
+pub fn set_max_concurrent_ads(limit: usize) -> Result<()> {
+    let mut settings = crate::running_ads_storage::load_runner_settings().unwrap_or_default();
+    settings.max_concurrent_ads = Some(limit);
+    crate::running_ads_storage::save_runner_settings(&settings)?;
+    MAX_CONCURRENT_ADS.store(limit, Ordering::Relaxed);
+    log::info!("ads_runner: max concurrent ads set to {}", limit);
+    Ok(())
+}
+
+fn at_capacity(running: usize, limit: usize) -> bool {
+    limit > 0 && running >= limit
+}
+
+/// Refuse another runner when the cap is reached. Call with RUNNERS locked.
+fn check_capacity(runners: &HashMap<String, RunnerHandle>) -> Result<()> {
+    let limit = max_concurrent_ads();
+    if at_capacity(runners.len(), limit) {
+        return Err(anyhow!(
+            "Max concurrent ads reached ({}). Stop an ad or raise the limit first.",
+            limit
+        ));
+    }
+    Ok(())
+}
+
+/// Random wait range in seconds between ads restarted at launch, so their first posts spread out.
+pub const DEFAULT_RESTORE_STAGGER_SECS: (u64, u64) = (5, 30);
+// longest wait between two restarted ads the user can configure
+const MAX_RESTORE_STAGGER_SECS: u64 = 600;
+
+/// Wait range (min, max) in seconds between restarted ads; (0, 0) restarts them back to back.
+pub fn restore_stagger_secs() -> (u64, u64) {
+    crate::running_ads_storage::load_runner_settings()
+        .map_err(|e| log::warn!("ads_runner: failed to load runner settings: {}", e))
+        .ok()
+        .and_then(|s| s.restore_stagger_secs)
+        .unwrap_or(DEFAULT_RESTORE_STAGGER_SECS)
+}
+
+pub fn set_restore_stagger_secs(min_secs: u64, max_secs: u64) -> Result<()> {
+    if min_secs > max_secs {
+        return Err(anyhow!("Minimum stagger must not exceed the maximum"));
+    }
+    if max_secs > MAX_RESTORE_STAGGER_SECS {
+        return Err(anyhow!(
+            "Stagger can be at most {} seconds",
+            MAX_RESTORE_STAGGER_SECS
+        ));
+    }
+    let mut settings = crate::running_ads_storage::load_runner_settings().unwrap_or_default();
+    settings.restore_stagger_secs = Some((min_secs, max_secs));
+    crate::running_ads_storage::save_runner_settings(&settings)?;
+    log::info!(
+        "ads_runner: restore stagger set to {}-{}s",
+        min_secs,
+        max_secs
+    );
+    Ok(())
+}
+
+/// Random wait before restarting the next persisted ad, within `range`.
+pub fn restore_stagger_delay(range: (u64, u64)) -> std::time::Duration {
+    let (min, max) = range;
+    let secs = if max <= min {
+        min
+    } else {
+        rand::thread_rng().gen_range(min..=max)
+    };
+    std::time::Duration::from_secs(secs)
+}
+
+// ad ids whose runner task is alive but skipping posts until resumed
+static PAUSED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunnerStatus {
+    Running,
+    Paused,
+    /// Waiting to post once at a set time (see `schedule_ad_once`).
+    Scheduled,
+}
+
+/// What `start_ad` did: spawned a runner, or found one already in place for the ad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartOutcome {
+    Started,
+    AlreadyRunning,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunningAd {
+    pub id: String,
+    pub status: RunnerStatus,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AdStatus {
+    pub running: bool,
+    pub paused: bool,
+    pub post_count: u64,
+    pub next_post_unix: Option<u64>,
+    pub consecutive_failures: u32,
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Pick a random offset in [-jitter, +jitter] seconds.
+fn random_jitter_offset(jitter: u64) -> i64 {
+    if jitter == 0 {
+        return 0;
+    }
+    let jitter = jitter.min(i64::MAX as u64) as i64;
+    rand::thread_rng().gen_range(-jitter..=jitter)
+}
+
+/// Apply a jitter offset to a wait, never going below min(base, MIN_JITTERED_SECS).
+fn jittered_secs(base_secs: u64, offset: i64) -> u64 {
+    let floor = base_secs.min(MIN_JITTERED_SECS);
+    base_secs.saturating_add_signed(offset).max(floor)
+}
+
+/// Wait before the next attempt after `failures` consecutive failures:
+/// base, 2x, 4x, ... capped at MAX_BACKOFF_MINS (never below the base interval).
+fn backoff_wait_mins(base: u64, failures: u32) -> u64 {
+    let exp = failures.saturating_sub(1).min(16);
+    base.saturating_mul(1u64 << exp)
+        .min(MAX_BACKOFF_MINS)
+        .max(base)
+}
+
+/// Whether `hour` falls inside the window [start, end). A window with start > end wraps
+/// past midnight (e.g. 22-4), and start == end is treated as the whole day.
+fn hour_in_window(hour: u32, start: u32, end: u32) -> bool {
+    if start == end {
+        true
+    } else if start < end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+/// Minutes from `now` until the clock next reads `start_hour:00` (rounded up, at least 1).
+fn minutes_until_hour(now: NaiveDateTime, start_hour: u32) -> u64 {
+    let mut target = now.date().and_hms_opt(start_hour, 0, 0).unwrap_or(now);
+    if target <= now {
+        target += chrono::Duration::days(1);
+    }
+    let secs = (target - now).num_seconds().max(0) as u64;
+    secs.div_ceil(60).max(1)
+}
+
+/// If the ad has a posting window and the local time is outside it, return how many
+/// minutes to wait until the window opens again.
+fn outside_window_wait(ad: &crate::ads_storage::AdData) -> Option<u64> {
+    let (start, end) = (ad.active_start_hour?, ad.active_end_hour?);
+    let now = Local::now().naive_local();
+    if hour_in_window(now.hour(), start, end) {
+        None
+    } else {
+        Some(minutes_until_hour(now, start))
+    }
+}
+
+/// Push a post-success event to the owner's Discord webhook, if one is configured.
+/// Delivery happens in the background and failures are only logged.
+fn notify_post_webhook(ad: &crate::ads_storage::AdData, count: u64) {
+    let user_id = ad.player_id.to_string();
+    let url = match crate::notification_settings::get_discord_webhook_url(&user_id) {
+        Ok(Some(url)) => url,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Failed to read Discord webhook URL: {}", e);
+            return;
+        }
+    };
+    let ad_name = ad.name.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::webhook::send_post_success(&url, &ad_name, count).await {
+            log::warn!("Failed to send Discord webhook for ad {}: {}", ad_name, e);
+        }
+    });
+}
+
+fn is_paused(id: &str) -> bool {
+    PAUSED.lock().unwrap().contains(id)
+}
+
 pub fn list_running_ads() -> Result<Vec<String>> {
     let guard = RUNNERS.lock().unwrap();
     Ok(guard.keys().cloned().collect())
 }
 
+/// Like `list_running_ads`, but reports whether each runner is posting or paused.
+pub fn list_running_ads_detailed() -> Result<Vec<RunningAd>> {
+    let guard = RUNNERS.lock().unwrap();
+    let paused = PAUSED.lock().unwrap();
+    Ok(guard
+        .iter()
+        .map(|(id, h)| RunningAd {
+            id: id.clone(),
+            status: if h.scheduled_at.is_some() {
+                RunnerStatus::Scheduled
+            } else if paused.contains(id) {
+                RunnerStatus::Paused
+            } else {
+                RunnerStatus::Running
+            },
+        })
+        .collect())
+}
+
+/// Snapshot of a single ad's runner state so the UI can render an absolute countdown.
+pub fn get_ad_status(id: &str) -> Result<AdStatus> {
+    let running = RUNNERS.lock().unwrap().contains_key(id);
+    let post_count = POST_COUNTS.lock().unwrap().get(id).copied().unwrap_or(0);
+    let next_post_unix = if running {
+        NEXT_POST.lock().unwrap().get(id).copied()
+    } else {
+        None
+    };
+    let consecutive_failures = CONSECUTIVE_FAILURES
+        .lock()
+        .unwrap()
+        .get(id)
+        .copied()
+        .unwrap_or(0);
+    Ok(AdStatus {
+        running,
+        paused: running && is_paused(id),
+        post_count,
+        next_post_unix,
+        consecutive_failures,
+    })
+}
+
+/// Load the persisted lifetime post counts into the session counters. Call once on startup.
+/// These are for display only; `max_posts` is checked against the per-run count.
+pub fn seed_post_counts() {
+    match crate::ad_stats_storage::load_all_stats() {
+        Ok(stats) => {
+            let mut pc = POST_COUNTS.lock().unwrap();
+            for (id, s) in stats {
+                pc.insert(id, s.total_posts);
+            }
+        }
+        Err(e) => log::warn!("ads_runner: failed to load ad stats: {}", e),
+    }
+}
+
+/// Write the current set of runners to disk so they can be restored on the next launch.
+pub fn persist_running() {
+    let entries: Vec<crate::running_ads_storage::RunningAdEntry> = {
+        let guard = RUNNERS.lock().unwrap();
+        // one-shot schedules aren't restored; they would come back as recurring runners
+        guard
+            .iter()
+            .filter(|(_, h)| h.scheduled_at.is_none())
+            .map(|(id, h)| crate::running_ads_storage::RunningAdEntry {
+                id: id.clone(),
+                interval_override: h.interval_override,
+            })
+            .collect()
+    };
+    if let Err(e) = crate::running_ads_storage::save_running_ads(&entries) {
+        log::error!("ads_runner: failed to persist running ads: {}", e);
+    }
+}
+
 pub fn stop_ad(id: &str) -> Result<()> {
-    let mut guard = RUNNERS.lock().unwrap();
-    if let Some((tx, _)) = guard.remove(id) {
-        // send cancellation; ignore send errors
-        let _ = tx.send(());
+    {
+        let mut guard = RUNNERS.lock().unwrap();
+        if let Some(handle) = guard.remove(id) {
+            // send cancellation; ignore send errors
+            let _ = handle.cancel.send(());
+        }
     }
+    PAUSED.lock().unwrap().remove(id);
+    persist_running();
+    Ok(())
+}
+
+/// Escape hatch for a wedged runner: free its slot right away so `list_running_ads` is accurate
+/// and `start_ad` can spawn a new one, and still send the cancellation. A task stuck in a
+/// request keeps running until that request returns, then finds its slot gone and exits
+/// without touching the new runner's state. Returns whether the ad had a slot.
+pub fn force_remove_ad(id: &str) -> Result<bool> {
+    let handle = RUNNERS.lock().unwrap().remove(id);
+    let removed = handle.is_some();
+    if let Some(handle) = handle {
+        let _ = handle.cancel.send(());
+        log::warn!("ads_runner: force-removed runner for ad {}", id);
+    }
+    PAUSED.lock().unwrap().remove(id);
+    NEXT_POST.lock().unwrap().remove(id);
+    CONSECUTIVE_FAILURES.lock().unwrap().remove(id);
+    RUN_POSTS.lock().unwrap().remove(id);
+    persist_running();
+    Ok(removed)
+}
+
+/// Whether the slot for `id` still belongs to the task with `runner_id`.
+fn owns_slot(id: &str, runner_id: u64) -> bool {
+    RUNNERS
+        .lock()
+        .unwrap()
+        .get(id)
+        .is_some_and(|h| h.runner_id == runner_id)
+}
+
+/// Stop every running ad and return how many were stopped. Safe to call when none are running.
+pub fn stop_all_ads() -> Result<usize> {
+    // drain the map under the lock, then signal outside it
+    let handles: Vec<RunnerHandle> = {
+        let mut guard = RUNNERS.lock().unwrap();
+        guard.drain().map(|(_, h)| h).collect()
+    };
+    let count = handles.len();
+    for handle in handles {
+        let _ = handle.cancel.send(());
+    }
+    PAUSED.lock().unwrap().clear();
+    persist_running();
+    Ok(count)
+}
+
+/// Keep the runner (and its schedule) alive but skip posting until `resume_ad` is called.
+pub fn pause_ad(id: &str) -> Result<()> {
+    let guard = RUNNERS.lock().unwrap();
+    if !guard.contains_key(id) {
+        return Err(anyhow!("Ad {} is not running", id));
+    }
+    PAUSED.lock().unwrap().insert(id.to_string());
+    Ok(())
+}
+
+/// Resume a paused runner. The current sleep is left untouched, so the next post
+/// happens when the existing interval elapses rather than immediately.
+pub fn resume_ad(id: &str) -> Result<()> {
+    let guard = RUNNERS.lock().unwrap();
+    if !guard.contains_key(id) {
+        return Err(anyhow!("Ad {} is not running", id));
+    }
+    PAUSED.lock().unwrap().remove(id);
+    Ok(())
+}
+
+/// Dedicated client for an ad with its own proxy. `None` means the ad posts through the
+/// shared client (and so follows the global proxy setting).
+pub fn ad_post_client(ad: &crate::ads_storage::AdData) -> Result<Option<reqwest::Client>> {
+    match ad.proxy_url.as_deref().filter(|u| !u.trim().is_empty()) {
+        Some(url) => crate::http::build_client(Some(url))
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid proxy for ad {}: {}", ad.id, e)),
+        None => Ok(None),
+    }
+}
+
+/// Scheduled posts can afford a longer timeout and a couple of retries: a transient failure
+/// would otherwise cost a whole interval.
+const RUNNER_POST_OPTIONS: crate::trade_ad::PostOptions = crate::trade_ad::PostOptions {
+    timeout: std::time::Duration::from_secs(20),
+    max_retries: 2,
+};
+
+/// How an ad appears in runner log lines: id, name, player and the stored account's username
+/// when there is one, so ads posting around the same time can be told apart in app.log.
+fn ad_log_label(ad: &crate::ads_storage::AdData) -> String {
+    let account_id = ad.account_id.unwrap_or(ad.player_id);
+    let username = match crate::auth_storage::get_account(account_id) {
+        Ok(account) => account.map(|a| a.username),
+        Err(e) => {
+            log::debug!("ads_runner: no account lookup for ad {}: {}", ad.id, e);
+            None
+        }
+    };
+    format_ad_label(&ad.id, &ad.name, ad.player_id, username.as_deref())
+}
+
+fn format_ad_label(id: &str, name: &str, player_id: u64, username: Option<&str>) -> String {
+    match username.filter(|u| !u.is_empty()) {
+        Some(user) => format!("ad {} \"{}\" (player {}, @{})", id, name, player_id, user),
+        None => format!("ad {} \"{}\" (player {})", id, name, player_id),
+    }
+}
+
+/// Items to offer in this post: the auto-offer pick from the player's current inventory when
+/// the ad has auto-offer set, otherwise (or when that pick fails) its stored offer list.
+pub async fn offer_ids_for(app: &AppHandle, ad: &crate::ads_storage::AdData) -> Vec<u64> {
+    let Some(config) = &ad.auto_offer else {
+        return ad.offer_item_ids.clone();
+    };
+    match crate::select_auto_offer(app, ad.player_id, config).await {
+        Ok(ids) if !ids.is_empty() => {
+            log::info!("ads_runner: ad {} auto-offering {:?}", ad.id, ids);
+            ids
+        }
+        Ok(_) => {
+            log::warn!(
+                "ads_runner: ad {} found no valued items to auto-offer, using its offer list",
+                ad.id
+            );
+            ad.offer_item_ids.clone()
+        }
+        Err(e) => {
+            log::warn!(
+                "ads_runner: ad {} auto-offer inventory fetch failed, using its offer list: {}",
+                ad.id,
+                e
+            );
+            ad.offer_item_ids.clone()
+        }
+    }
+}
+
+/// Send one post for `ad` with `token`, through its own proxy client when it has one.
+async fn post_ad(
+    app: &AppHandle,
+    ad: &crate::ads_storage::AdData,
+    client: Option<&reqwest::Client>,
+    token: &str,
+) -> std::result::Result<crate::trade_ad::PostedTradeAd, ApiError> {
+    let offer_item_ids = offer_ids_for(app, ad).await;
+    let client = client.cloned().unwrap_or_else(crate::http::client);
+    crate::trade_ad::post_trade_ad_with_client(
+        &client,
+        token,
+        ad.player_id,
+        offer_item_ids,
+        ad.request_item_ids.clone(),
+        ad.request_tags.clone(),
+        RUNNER_POST_OPTIONS,
+    )
+    .await
+}
+
+/// The ad as posted for one of its extra targets: the same preset under that player's id and
+/// token. Auto-offer then picks from that player's inventory.
+fn target_ad(
+    ad: &crate::ads_storage::AdData,
+    target: &crate::ads_storage::AdTarget,
+) -> crate::ads_storage::AdData {
+    crate::ads_storage::AdData {
+        player_id: target.player_id,
+        roli_verification: target.roli_verification.clone(),
+        account_id: None,
+        targets: Vec::new(),
+        ..ad.clone()
+    }
+}
+
+/// `error_kind` reported for a failed target post.
+fn target_error_kind(err: &ApiError) -> &'static str {
+    if matches!(err, ApiError::Unauthorized) {
+        "verification"
+    } else if err.retry_after_secs().is_some() {
+        "cooldown"
+    } else if matches!(err, ApiError::BlockedByChallenge) {
+        "challenge"
+    } else {
+        "other"
+    }
+}
+
+/// Post `ad` for each of its extra targets, emitting one `ad:target_posted` event per target.
+/// Targets succeed or fail on their own: a missing token, cooldown or failed post for one is
+/// reported and the rest are still posted. They don't count towards the ad's post count,
+/// failure streak or schedule, which follow the ad's own player.
+async fn post_to_targets(
+    window: &Window,
+    ad: &crate::ads_storage::AdData,
+    client: Option<&reqwest::Client>,
+) {
+    for target in &ad.targets {
+        let ad_for = target_ad(ad, target);
+        let label = ad_log_label(&ad_for);
+        let token = ad_for
+            .roli_verification
+            .clone()
+            .filter(|t| !t.trim().is_empty());
+        let cooldown = crate::trade_ad::post_cooldown_remaining(target.player_id);
+        let payload = match token {
+            None => {
+                log::warn!(
+                    "ads_runner: {} missing roli_verification, skipping post",
+                    label
+                );
+                serde_json::json!({
+                    "id": ad.id,
+                    "name": ad.name,
+                    "player_id": target.player_id,
+                    "ok": false,
+                    "message": "trade ad post skipped (no roli_verification)",
+                    "error_kind": "verification",
+                })
+            }
+            Some(_) if cooldown > 0 => {
+                log::info!(
+                    "ads_runner: {} on cooldown ({}s), skipping post",
+                    label,
+                    cooldown
+                );
+                serde_json::json!({
+                    "id": ad.id,
+                    "name": ad.name,
+                    "player_id": target.player_id,
+                    "ok": false,
+                    "message": format!("trade ad post delayed (cooldown {}s)", cooldown),
+                    "error_kind": "cooldown",
+                    "cooldown_secs": cooldown,
+                })
+            }
+            Some(token) => match post_ad(window.app_handle(), &ad_for, client, &token).await {
+                Ok(posted) => {
+                    log::info!("ads_runner: {} posted ({:?})", label, posted.ad_id);
+                    serde_json::json!({
+                        "id": ad.id,
+                        "name": ad.name,
+                        "player_id": target.player_id,
+                        "ok": true,
+                        "message": "trade ad post success",
+                        "ad_id": posted.ad_id,
+                        "created_unix": posted.created_unix,
+                    })
+                }
+                Err(err) => {
+                    log::warn!("ads_runner: {} failed to post: {}", label, err);
+                    serde_json::json!({
+                        "id": ad.id,
+                        "name": ad.name,
+                        "player_id": target.player_id,
+                        "ok": false,
+                        "message": format!("trade ad post error: {}", err),
+                        "error_kind": target_error_kind(&err),
+                        "reason": err.to_string(),
+                        "api_error": err,
+                    })
+                }
+            },
+        };
+        let _ = window.emit("ad:target_posted", payload);
+    }
+}
+
+/// Check each extra target's token before a runner starts and leave out the ones Rolimons
+/// rejects, reporting each with an `ad:target_posted` verification event. The ad itself and
+/// the remaining targets still start. Targets without a token, or whose check fails for
+/// another reason (e.g. network), are kept and reported by the runner as usual.
+pub async fn drop_invalid_targets(window: &Window, ad: &mut crate::ads_storage::AdData) {
+    let mut kept = Vec::with_capacity(ad.targets.len());
+    for target in std::mem::take(&mut ad.targets) {
+        let Some(token) = target
+            .roli_verification
+            .as_deref()
+            .filter(|t| !t.trim().is_empty())
+        else {
+            kept.push(target);
+            continue;
+        };
+        match crate::trade_ad::validate_roli_verification(token).await {
+            Ok(false) => {
+                log::warn!(
+                    "ads_runner: ad {} target player {} has an invalid roli_verification, leaving it out",
+                    ad.id, target.player_id
+                );
+                let _ = window.emit(
+                    "ad:target_posted",
+                    serde_json::json!({
+                        "id": ad.id,
+                        "name": ad.name,
+                        "player_id": target.player_id,
+                        "ok": false,
+                        "message": "trade ad post failed (verification_required)",
+                        "error_kind": "verification",
+                    }),
+                );
+            }
+            Ok(true) => kept.push(target),
+            Err(e) => {
+                log::warn!(
+                    "ads_runner: could not validate roli_verification for ad {} target player {}: {}",
+                    ad.id, target.player_id, e
+                );
+                kept.push(target);
+            }
+        }
+    }
+    ad.targets = kept;
+}
+
+/// Count a successful post in the session counter and the persisted stats, notify the
+/// webhook, and return the new (lifetime, this run) counts.
+fn record_success(ad: &crate::ads_storage::AdData) -> (u64, u64) {
+    CONSECUTIVE_FAILURES.lock().unwrap().remove(&ad.id);
+    let (cnt, run_posts) = bump_post_counts(&ad.id);
+    if let Err(e) = crate::ad_stats_storage::record_post(&ad.id, unix_now()) {
+        log::error!(
+            "ads_runner: failed to persist stats for ad {}: {}",
+            ad.id,
+            e
+        );
+    }
+    notify_post_webhook(ad, cnt);
+    (cnt, run_posts)
+}
+
+/// Emit an `ad:posted` event for `ad`: the id, name, post count and message every payload
+/// carries, plus the fields in `extra`.
+fn emit_posted(
+    window: &Window,
+    ad: &crate::ads_storage::AdData,
+    count: u64,
+    message: &str,
+    extra: serde_json::Value,
+) {
+    let mut payload = serde_json::json!({
+        "id": ad.id,
+        "name": ad.name,
+        "count": count,
+        "message": message,
+    });
+    if let (Some(payload), serde_json::Value::Object(extra)) = (payload.as_object_mut(), extra) {
+        payload.extend(extra);
+    }
+    let _ = window.emit("ad:posted", payload);
+}
+
+/// Add one post to the ad's lifetime and current-run counters and return both.
+fn bump_post_counts(id: &str) -> (u64, u64) {
+    let bump = |counts: &Mutex<HashMap<String, u64>>| {
+        let mut counts = counts.lock().unwrap();
+        let entry = counts.entry(id.to_string()).or_insert(0);
+        *entry += 1;
+        *entry
+    };
+    (bump(&POST_COUNTS), bump(&RUN_POSTS))
+}
+
+/// Hand a finished post's result back to the task that sent it, or `None` when the task no
+/// longer owns the ad's slot (force-removed or replaced while the request was in flight). Call
+/// before recording anything about the post, so a stale task never touches the new runner's
+/// counters, stats or webhook.
+fn owned_result<T>(id: &str, runner_id: u64, result: T) -> Option<T> {
+    owns_slot(id, runner_id).then_some(result)
+}
+
+/// Remove a finished task's runner entry, but only if it is still ours (a newer runner
+/// for the same ad may have replaced it).
+fn release_runner(id: &str, runner_id: u64) {
+    let removed = {
+        let mut guard = RUNNERS.lock().unwrap();
+        let ours = guard
+            .get(id)
+            .map(|h| h.runner_id == runner_id)
+            .unwrap_or(false);
+        if ours {
+            guard.remove(id);
+            PAUSED.lock().unwrap().remove(id);
+            NEXT_POST.lock().unwrap().remove(id);
+            CONSECUTIVE_FAILURES.lock().unwrap().remove(id);
+            RUN_POSTS.lock().unwrap().remove(id);
+        }
+        ours
+    };
+    if removed {
+        persist_running();
+    }
+}
+
+/// Post `ad` exactly once at `unix_time`, then exit. Shows up as a `Scheduled` runner until
+/// it fires and can be cancelled with `stop_ad`. The ad must already carry its token.
+pub fn schedule_ad_once(
+    ad: crate::ads_storage::AdData,
+    window: Window,
+    unix_time: u64,
+) -> Result<()> {
+    let now = unix_now();
+    if unix_time <= now {
+        return Err(anyhow!("Scheduled time must be in the future"));
+    }
+    let token = ad
+        .roli_verification
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| anyhow!("Ad {} has no roli_verification token", ad.id))?;
+    let ad_client = ad_post_client(&ad)?;
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let my_id = RUNNER_COUNTER.fetch_add(1, Ordering::SeqCst);
+    {
+        let mut guard = RUNNERS.lock().unwrap();
+        if guard.contains_key(&ad.id) {
+            return Err(anyhow!("Ad {} is already running or scheduled", ad.id));
+        }
+        check_capacity(&guard)?;
+        guard.insert(
+            ad.id.clone(),
+            RunnerHandle {
+                cancel: tx,
+                runner_id: my_id,
+                interval_override: None,
+                scheduled_at: Some(unix_time),
+            },
+        );
+    }
+    NEXT_POST.lock().unwrap().insert(ad.id.clone(), unix_time);
+    let label = ad_log_label(&ad);
+    log::info!("ads_runner: {} scheduled to post at {}", label, unix_time);
+
+    tauri::async_runtime::spawn(async move {
+        let sleep = tokio::time::sleep(std::time::Duration::from_secs(unix_time - now));
+        let fired = tokio::select! {
+            _ = rx => false,
+            _ = sleep => true,
+        };
+        if fired {
+            let result = post_ad(window.app_handle(), &ad, ad_client.as_ref(), &token).await;
+            match owned_result(&ad.id, my_id, result) {
+                None => {
+                    log::warn!(
+                        "ads_runner: {} lost its slot while posting, discarding the result",
+                        label
+                    )
+                }
+                Some(result) => {
+                    match result {
+                        Ok(posted) => {
+                            log::info!(
+                                "ads_runner: {} posted scheduled ad ({:?})",
+                                label,
+                                posted.ad_id
+                            );
+                            let (cnt, _) = record_success(&ad);
+                            emit_posted(
+                                &window,
+                                &ad,
+                                cnt,
+                                "trade ad post success",
+                                serde_json::json!({
+                                    "ad_id": posted.ad_id,
+                                    "created_unix": posted.created_unix,
+                                    "scheduled": true,
+                                }),
+                            );
+                        }
+                        Err(err) => {
+                            log::warn!("ads_runner: scheduled post for {} failed: {}", label, err);
+                            let (message, error_kind) = if matches!(err, ApiError::Unauthorized) {
+                                (
+                                    "trade ad post failed (verification_required)".to_string(),
+                                    "verification",
+                                )
+                            } else if matches!(err, ApiError::BlockedByChallenge) {
+                                (format!("trade ad post error: {}", err), "challenge")
+                            } else {
+                                (format!("trade ad post error: {}", err), "other")
+                            };
+                            emit_posted(
+                                &window,
+                                &ad,
+                                0,
+                                &message,
+                                serde_json::json!({
+                                    "error_kind": error_kind,
+                                    "reason": err.to_string(),
+                                    "api_error": err,
+                                    "scheduled": true,
+                                }),
+                            );
+                        }
+                    }
+                    post_to_targets(&window, &ad, ad_client.as_ref()).await;
+                }
+            }
+        }
+        release_runner(&ad.id, my_id);
+        log::debug!("ads_runner: scheduled task for {} exiting", label);
+    });
+
     Ok(())
 }
 
@@ -39,7 +866,10 @@ pub fn start_ad(
     ad: crate::ads_storage::AdData,
     window: Window,
     interval_override: Option<u64>,
-) -> Result<()> {
+) -> Result<StartOutcome> {
+    // fail before reserving a slot if the ad's proxy can't even be configured
+    let ad_client = ad_post_client(&ad)?;
+
     // Reserve and check under lock to avoid races where two callers both spawn runners
     let (tx, rx) = oneshot::channel::<()>();
     let my_id = RUNNER_COUNTER.fetch_add(1, Ordering::SeqCst);
@@ -48,11 +878,24 @@ pub fn start_ad(
         let mut guard = RUNNERS.lock().unwrap();
         if guard.contains_key(&ad.id) {
             // another runner already present for this ad
-            return Ok(());
+            return Ok(StartOutcome::AlreadyRunning);
         }
+        check_capacity(&guard)?;
         // reserve the slot with our sender and unique id before spawning
-        guard.insert(ad.id.clone(), (tx, my_id));
+        guard.insert(
+            ad.id.clone(),
+            RunnerHandle {
+                cancel: tx,
+                runner_id: my_id,
+                interval_override,
+                scheduled_at: None,
+            },
+        );
     }
+    // a fresh runner starts with a clean failure streak and its own post count
+    CONSECUTIVE_FAILURES.lock().unwrap().remove(&ad.id);
+    RUN_POSTS.lock().unwrap().remove(&ad.id);
+    persist_running();
 
     // Determine effective interval (in minutes): prefer the override, then the ad's stored value (if non-zero).
     // If neither is set, we'll stop the runner when that is detected in the loop (rather than silently defaulting).
@@ -69,6 +912,8 @@ pub fn start_ad(
 
     // spawn a tokio task to post immediately and then sleep repeatedly until cancelled
     let ad_clone = ad.clone();
+    let label = ad_log_label(&ad);
+    log::info!("ads_runner: starting runner for {}", label);
     let win = window.clone();
     tauri::async_runtime::spawn(async move {
         // rx receives cancellation signal
@@ -76,59 +921,148 @@ pub fn start_ad(
         loop {
             // perform post now and choose next wait time based on success
             let next_wait_mins: u64;
-            if let Some(roli) = ad_clone.roli_verification.clone() {
+            // random offset for this round's sleep so posts don't land on a fixed cadence
+            let jitter_offset =
+                random_jitter_offset(ad_clone.jitter_seconds.unwrap_or(DEFAULT_JITTER_SECS));
+            let wait_secs = |mins: u64| jittered_secs(mins * 60, jitter_offset);
+            // extra targets post whenever the ad itself would, even if its own post is skipped
+            let post_targets = !ad_clone.targets.is_empty()
+                && !is_paused(&ad_clone.id)
+                && outside_window_wait(&ad_clone).is_none();
+            let cooldown = crate::trade_ad::post_cooldown_remaining(ad_clone.player_id);
+            if is_paused(&ad_clone.id) {
+                next_wait_mins = effective_interval.unwrap_or(20);
+                let cnt = POST_COUNTS
+                    .lock()
+                    .unwrap()
+                    .get(&ad_clone.id)
+                    .copied()
+                    .unwrap_or(0);
+                emit_posted(
+                    &win,
+                    &ad_clone,
+                    cnt,
+                    "trade ad post skipped (paused)",
+                    serde_json::json!({
+                        "paused": true,
+                        "next_wait_mins": next_wait_mins,
+                        "next_wait_secs": wait_secs(next_wait_mins),
+                    }),
+                );
+            } else if let Some(wait) = outside_window_wait(&ad_clone) {
+                next_wait_mins = wait;
+                emit_posted(
+                    &win,
+                    &ad_clone,
+                    0,
+                    "outside posting window, waiting",
+                    serde_json::json!({
+                        "next_wait_mins": next_wait_mins,
+                        "next_wait_secs": wait_secs(next_wait_mins),
+                    }),
+                );
+            } else if cooldown > 0 {
+                // another ad for this player just posted (or was told to wait); wait it out
+                // instead of sending a post Rolimons is going to reject
+                next_wait_mins = cooldown.div_ceil(60).max(1);
+                emit_posted(
+                    &win,
+                    &ad_clone,
+                    0,
+                    &format!("trade ad post delayed (cooldown {}s)", cooldown),
+                    serde_json::json!({
+                        "error_kind": "cooldown",
+                        "cooldown_secs": cooldown,
+                        "next_wait_mins": next_wait_mins,
+                        "next_wait_secs": wait_secs(next_wait_mins),
+                    }),
+                );
+            } else if let Some(roli) = ad_clone.roli_verification.clone() {
                 if roli.trim().is_empty() {
-                    eprintln!(
-                        "ads_runner: ad {} has empty roli_verification, skipping post",
-                        ad_clone.id
+                    log::warn!(
+                        "ads_runner: {} has empty roli_verification, skipping post",
+                        label
                     );
                     next_wait_mins = effective_interval.unwrap_or(20);
-                    let _ = win.emit("ad:posted", serde_json::json!({ "id": ad_clone.id, "count": 0, "message": "trade ad post skipped (no roli_verification)", "next_wait_mins": next_wait_mins }));
+                    emit_posted(
+                        &win,
+                        &ad_clone,
+                        0,
+                        "trade ad post skipped (no roli_verification)",
+                        serde_json::json!({
+                            "next_wait_mins": next_wait_mins,
+                            "next_wait_secs": wait_secs(next_wait_mins),
+                        }),
+                    );
                 } else {
-                    match crate::trade_ad::post_trade_ad_direct(
-                        &roli,
-                        ad_clone.player_id,
-                        ad_clone
-                            .offer_item_ids
-                            .clone()
-                            .into_iter()
-                            .map(|v| v as u64)
-                            .collect(),
-                        ad_clone
-                            .request_item_ids
-                            .clone()
-                            .into_iter()
-                            .map(|v| v as u64)
-                            .collect(),
-                        ad_clone.request_tags.clone(),
-                    )
-                    .await
-                    {
-                        Ok(_msg) => {
+                    let result =
+                        post_ad(win.app_handle(), &ad_clone, ad_client.as_ref(), &roli).await;
+                    // a force-removed (or replaced) runner must not count this post against
+                    // the ad's new runner, so it leaves before touching any counters or stats
+                    let Some(result) = owned_result(&ad_clone.id, my_id, result) else {
+                        log::warn!(
+                            "ads_runner: {} runner lost its slot while posting, exiting",
+                            label
+                        );
+                        break;
+                    };
+                    match result {
+                        Ok(posted) => {
+                            log::info!("ads_runner: {} posted ({:?})", label, posted.ad_id);
                             // increment count and emit an event to the frontend with the count
-                            let mut pc = POST_COUNTS.lock().unwrap();
-                            let entry = pc.entry(ad_clone.id.clone()).or_insert(0);
-                            *entry += 1;
-                            let cnt = *entry;
+                            let (cnt, run_posts) = record_success(&ad_clone);
                             // build a clean message as requested by UI (lowercase, short)
                             let user_msg = if cnt <= 1 {
                                 "trade ad post success".to_string()
                             } else {
                                 format!("trade ad post success ({})", cnt)
                             };
+                            // Stop once this run reaches the optional post limit (None or 0 means unlimited)
+                            if let Some(max) = ad_clone.max_posts.filter(|m| *m > 0) {
+                                if run_posts >= max {
+                                    log::info!(
+                                        "ads_runner: {} reached max posts ({}), stopping runner",
+                                        label,
+                                        max
+                                    );
+                                    emit_posted(
+                                        &win,
+                                        &ad_clone,
+                                        cnt,
+                                        "ad completed (max posts reached)",
+                                        serde_json::json!({ "ad_id": posted.ad_id }),
+                                    );
+                                    break;
+                                }
+                            }
                             // Use the effective_interval directly - it's been validated by lib.rs before reaching here.
                             // If for some reason it's None, emit an error and stop the runner.
                             match effective_interval {
                                 Some(v) => {
                                     next_wait_mins = v;
-                                    let _ = win.emit("ad:posted", serde_json::json!({ "id": ad_clone.id, "count": cnt, "message": user_msg, "next_wait_mins": next_wait_mins }));
+                                    emit_posted(
+                                        &win,
+                                        &ad_clone,
+                                        cnt,
+                                        &user_msg,
+                                        serde_json::json!({
+                                            "ad_id": posted.ad_id,
+                                            "created_unix": posted.created_unix,
+                                            "next_wait_mins": next_wait_mins,
+                                            "next_wait_secs": wait_secs(next_wait_mins),
+                                        }),
+                                    );
                                 }
                                 None => {
-                                    eprintln!("ads_runner: ad {} has no valid interval set, stopping runner", ad_clone.id);
+                                    log::error!(
+                                        "ads_runner: {} has no valid interval set, stopping runner",
+                                        label
+                                    );
                                     let _ = win.emit(
                                         "ad:posted",
                                         serde_json::json!({
                                             "id": ad_clone.id,
+                                            "name": ad_clone.name,
                                             "count": 0,
                                             "message": "ad stopped (no valid interval configured)",
                                             "error_kind": "config"
@@ -140,49 +1074,204 @@ pub fn start_ad(
                         }
                         Err(err) => {
                             let err_str = err.to_string();
-                            eprintln!("ads_runner: ad {} failed to post: {}", ad_clone.id, err_str);
+                            log::warn!("ads_runner: {} failed to post: {}", label, err_str);
                             // classify verification-related failures so UI only prompts when appropriate
-                            let is_verification = err_str.starts_with("verification_required")
-                                || err_str.to_lowercase().contains("verification");
+                            let is_verification = matches!(err, ApiError::Unauthorized);
 
                             // Attempt to parse a JSON error payload to extract any API error code for richer events
                             let mut error_code: Option<u64> = None;
-                            if let Ok(v) = serde_json::from_str::<serde_json::Value>(&err_str) {
-                                if let Some(code_val) = v.get("code") {
-                                    if code_val.is_u64() {
-                                        error_code = code_val.as_u64();
-                                    } else if code_val.is_i64() {
-                                        error_code = Some(code_val.as_i64().unwrap() as u64);
+                            if let ApiError::Rejected { message, .. } = &err {
+                                if let Ok(v) = serde_json::from_str::<serde_json::Value>(message) {
+                                    if let Some(code_val) = v.get("code") {
+                                        if code_val.is_u64() {
+                                            error_code = code_val.as_u64();
+                                        } else if code_val.is_i64() {
+                                            error_code = Some(code_val.as_i64().unwrap() as u64);
+                                        }
                                     }
                                 }
                             }
 
-                            // Use effective_interval instead of hardcoded 20 minutes for retry
-                            next_wait_mins = effective_interval.unwrap_or(20);
+                            // Prefer the server-provided cooldown; otherwise retry after the normal interval
+                            let cooldown_secs = err.retry_after_secs();
+                            // Only generic failures (outages etc.) count towards backoff; cooldowns carry
+                            // their own wait and verification failures are surfaced to the user instead.
+                            let is_other = cooldown_secs.is_none() && !is_verification;
+                            let failures = if is_other {
+                                let mut cf = CONSECUTIVE_FAILURES.lock().unwrap();
+                                let entry = cf.entry(ad_clone.id.clone()).or_insert(0);
+                                *entry += 1;
+                                *entry
+                            } else {
+                                0
+                            };
+                            // a challenge page won't clear by itself soon, so back off one step further
+                            let is_challenge = matches!(err, ApiError::BlockedByChallenge);
+                            next_wait_mins = match cooldown_secs {
+                                Some(secs) => secs.div_ceil(60).max(1),
+                                None if is_challenge => backoff_wait_mins(
+                                    effective_interval.unwrap_or(20),
+                                    failures + 1,
+                                ),
+                                None if is_other => {
+                                    backoff_wait_mins(effective_interval.unwrap_or(20), failures)
+                                }
+                                None => effective_interval.unwrap_or(20),
+                            };
+                            let max_failures = ad_clone
+                                .max_consecutive_failures
+                                .filter(|m| *m > 0)
+                                .unwrap_or(DEFAULT_MAX_CONSECUTIVE_FAILURES);
+                            if is_other && failures >= max_failures {
+                                log::error!(
+                                    "ads_runner: {} failed {} times in a row, giving up",
+                                    label,
+                                    failures
+                                );
+                                emit_posted(
+                                    &win,
+                                    &ad_clone,
+                                    0,
+                                    &format!("ad stopped after {} consecutive failures", failures),
+                                    serde_json::json!({
+                                        "error_kind": "gave_up",
+                                        "reason": err_str,
+                                        "api_error": err,
+                                        "error_code": error_code,
+                                        "consecutive_failures": failures,
+                                    }),
+                                );
+                                break;
+                            }
+
+                            // with a per-ad proxy a request that never got a response almost
+                            // always means the proxy itself is down or refusing us
+                            let proxy_failed =
+                                ad_client.is_some() && matches!(err, ApiError::Network(_));
 
-                            if is_verification {
-                                let _ = win.emit("ad:posted", serde_json::json!({ "id": ad_clone.id, "count": 0, "message": "trade ad post failed (verification_required)", "error_kind": "verification", "reason": err_str, "error_code": error_code, "next_wait_mins": next_wait_mins }));
+                            if let Some(secs) = cooldown_secs {
+                                emit_posted(
+                                    &win,
+                                    &ad_clone,
+                                    0,
+                                    &format!("trade ad post delayed (cooldown {}s)", secs),
+                                    serde_json::json!({
+                                        "error_kind": "cooldown",
+                                        "reason": err_str,
+                                        "api_error": err,
+                                        "cooldown_secs": secs,
+                                        "next_wait_mins": next_wait_mins,
+                                        "next_wait_secs": wait_secs(next_wait_mins),
+                                    }),
+                                );
+                            } else if is_verification {
+                                emit_posted(
+                                    &win,
+                                    &ad_clone,
+                                    0,
+                                    "trade ad post failed (verification_required)",
+                                    serde_json::json!({
+                                        "error_kind": "verification",
+                                        "reason": err_str,
+                                        "api_error": err,
+                                        "error_code": error_code,
+                                        "next_wait_mins": next_wait_mins,
+                                        "next_wait_secs": wait_secs(next_wait_mins),
+                                    }),
+                                );
+                            } else if is_challenge {
+                                emit_posted(
+                                    &win,
+                                    &ad_clone,
+                                    0,
+                                    &format!("trade ad post error: {}", err_str),
+                                    serde_json::json!({
+                                        "error_kind": "challenge",
+                                        "reason": err_str,
+                                        "api_error": err,
+                                        "consecutive_failures": failures,
+                                        "next_wait_mins": next_wait_mins,
+                                        "next_wait_secs": wait_secs(next_wait_mins),
+                                    }),
+                                );
+                            } else if proxy_failed {
+                                let proxy = crate::http::mask_proxy_url(
+                                    ad_clone.proxy_url.as_deref().unwrap_or_default(),
+                                );
+                                emit_posted(
+                                    &win,
+                                    &ad_clone,
+                                    0,
+                                    &format!("trade ad post error: proxy {} unreachable", proxy),
+                                    serde_json::json!({
+                                        "error_kind": "proxy",
+                                        "reason": err_str,
+                                        "api_error": err,
+                                        "proxy": proxy,
+                                        "consecutive_failures": failures,
+                                        "next_wait_mins": next_wait_mins,
+                                        "next_wait_secs": wait_secs(next_wait_mins),
+                                    }),
+                                );
                             } else {
                                 // Use a different message prefix for non-verification failures so older frontends
                                 // that look for messages starting with "trade ad post failed" don't treat these
                                 // as verification prompts. Include structured fields for diagnostics.
-                                let _ = win.emit("ad:posted", serde_json::json!({ "id": ad_clone.id, "count": 0, "message": format!("trade ad post error: {}", err_str), "error_kind": "other", "reason": err_str, "error_code": error_code, "next_wait_mins": next_wait_mins }));
+                                emit_posted(
+                                    &win,
+                                    &ad_clone,
+                                    0,
+                                    &format!("trade ad post error: {}", err_str),
+                                    serde_json::json!({
+                                        "error_kind": "other",
+                                        "reason": err_str,
+                                        "api_error": err,
+                                        "error_code": error_code,
+                                        "consecutive_failures": failures,
+                                        "next_wait_mins": next_wait_mins,
+                                        "next_wait_secs": wait_secs(next_wait_mins),
+                                    }),
+                                );
                             }
                         }
                     }
                 }
             } else {
-                eprintln!(
-                    "ads_runner: ad {} missing roli_verification, skipping post",
-                    ad_clone.id
+                log::warn!(
+                    "ads_runner: {} missing roli_verification, skipping post",
+                    label
                 );
                 // Use effective_interval instead of hardcoded 20 minutes
                 next_wait_mins = effective_interval.unwrap_or(20);
-                let _ = win.emit("ad:posted", serde_json::json!({ "id": ad_clone.id, "count": 0, "message": "trade ad post skipped (no roli_verification)", "next_wait_mins": next_wait_mins }));
+                emit_posted(
+                    &win,
+                    &ad_clone,
+                    0,
+                    "trade ad post skipped (no roli_verification)",
+                    serde_json::json!({
+                        "next_wait_mins": next_wait_mins,
+                        "next_wait_secs": wait_secs(next_wait_mins),
+                    }),
+                );
+            }
+            if post_targets {
+                post_to_targets(&win, &ad_clone, ad_client.as_ref()).await;
+            }
+
+            // a runner force-removed during the rest of this round (e.g. while posting for its
+            // extra targets) notices here
+            if !owns_slot(&ad_clone.id, my_id) {
+                log::warn!("ads_runner: {} runner lost its slot, exiting", label);
+                break;
             }
 
-            // wait for next_wait_mins or cancellation
-            let sleep = tokio::time::sleep(std::time::Duration::from_secs(next_wait_mins * 60));
+            // wait for next_wait_mins (plus jitter) or cancellation
+            let sleep_secs = wait_secs(next_wait_mins);
+            NEXT_POST
+                .lock()
+                .unwrap()
+                .insert(ad_clone.id.clone(), unix_now() + sleep_secs);
+            let sleep = tokio::time::sleep(std::time::Duration::from_secs(sleep_secs));
             tokio::select! {
                 _ = &mut cancel_rx => break,
                 _ = sleep => continue,
@@ -190,17 +1279,167 @@ pub fn start_ad(
         }
 
         // task is exiting — remove our runner entry only if it's still our id (avoid removing a newer runner)
-        {
-            let mut guard = RUNNERS.lock().unwrap();
-            if let Some((_, id)) = guard.get(&ad_clone.id) {
-                if *id == my_id {
-                    guard.remove(&ad_clone.id);
-                }
-            }
-        }
+        release_runner(&ad_clone.id, my_id);
 
-        eprintln!("ads_runner: task for ad {} exiting", ad_clone.id);
+        log::debug!("ads_runner: task for {} exiting", label);
     });
 
-    Ok(())
+    Ok(StartOutcome::Started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_at_capacity() {
+        assert!(!at_capacity(19, 20));
+        assert!(at_capacity(20, 20));
+        // 0 means unlimited
+        assert!(!at_capacity(500, 0));
+    }
+
+    #[test]
+    fn test_seeded_lifetime_count_does_not_end_new_run() {
+        let id = "test-seeded-run-posts";
+        // what seed_post_counts loads from a previous session
+        POST_COUNTS.lock().unwrap().insert(id.to_string(), 50);
+        // what start_ad does on reserving the slot
+        RUN_POSTS.lock().unwrap().remove(id);
+        let max = 3;
+        let (total, run) = bump_post_counts(id);
+        assert_eq!((total, run), (51, 1));
+        assert!(run < max);
+        assert_eq!(bump_post_counts(id), (52, 2));
+        assert_eq!(bump_post_counts(id), (53, 3));
+        POST_COUNTS.lock().unwrap().remove(id);
+        RUN_POSTS.lock().unwrap().remove(id);
+    }
+
+    #[test]
+    fn test_stale_runner_discards_post_result() {
+        let id = "test-stale-runner";
+        let slot = |runner_id: u64| RunnerHandle {
+            cancel: oneshot::channel::<()>().0,
+            runner_id,
+            interval_override: None,
+            scheduled_at: None,
+        };
+        RUNNERS.lock().unwrap().insert(id.to_string(), slot(1));
+        assert_eq!(owned_result(id, 1, "posted"), Some("posted"));
+
+        // runner 1 is force-removed mid-post and runner 2 takes the slot
+        RUNNERS.lock().unwrap().insert(id.to_string(), slot(2));
+        POST_COUNTS.lock().unwrap().insert(id.to_string(), 7);
+        assert_eq!(owned_result(id, 1, "posted"), None);
+        assert_eq!(POST_COUNTS.lock().unwrap().get(id), Some(&7));
+        assert!(!CONSECUTIVE_FAILURES.lock().unwrap().contains_key(id));
+
+        RUNNERS.lock().unwrap().remove(id);
+        POST_COUNTS.lock().unwrap().remove(id);
+        assert_eq!(owned_result(id, 2, "posted"), None);
+    }
+
+    #[test]
+    fn test_restore_stagger_delay() {
+        for _ in 0..50 {
+            let d = restore_stagger_delay((5, 30)).as_secs();
+            assert!((5..=30).contains(&d), "{}", d);
+        }
+        assert_eq!(restore_stagger_delay((0, 0)).as_secs(), 0);
+        assert_eq!(restore_stagger_delay((7, 7)).as_secs(), 7);
+    }
+
+    #[test]
+    fn test_target_ad() {
+        let ad: crate::ads_storage::AdData = serde_json::from_value(serde_json::json!({
+            "id": "multi", "name": "Main", "player_id": 1, "roli_verification": "tok-1",
+            "offer_item_ids": [1028606], "request_item_ids": [], "request_tags": ["any"],
+            "interval_minutes": 20, "account_id": 1,
+            "targets": [{ "player_id": 2, "roli_verification": "tok-2" }],
+        }))
+        .unwrap();
+        let alt = target_ad(&ad, &ad.targets[0]);
+        assert_eq!(alt.id, "multi");
+        assert_eq!(alt.player_id, 2);
+        assert_eq!(alt.roli_verification.as_deref(), Some("tok-2"));
+        assert_eq!(alt.account_id, None);
+        assert!(alt.targets.is_empty());
+        assert_eq!(alt.offer_item_ids, ad.offer_item_ids);
+    }
+
+    #[test]
+    fn test_format_ad_label() {
+        assert_eq!(
+            format_ad_label("3", "Main", 123, Some("someone")),
+            "ad 3 \"Main\" (player 123, @someone)"
+        );
+        assert_eq!(
+            format_ad_label("3", "Main", 123, None),
+            "ad 3 \"Main\" (player 123)"
+        );
+        assert_eq!(
+            format_ad_label("3", "Main", 123, Some("")),
+            "ad 3 \"Main\" (player 123)"
+        );
+    }
+
+    #[test]
+    fn test_backoff_wait_mins() {
+        assert_eq!(backoff_wait_mins(15, 1), 15);
+        assert_eq!(backoff_wait_mins(15, 2), 30);
+        assert_eq!(backoff_wait_mins(15, 3), 60);
+        assert_eq!(backoff_wait_mins(15, 4), 120);
+        // capped
+        assert_eq!(backoff_wait_mins(15, 9), 120);
+        // never shorter than the base interval itself
+        assert_eq!(backoff_wait_mins(180, 3), 180);
+    }
+
+    #[test]
+    fn test_jittered_secs() {
+        // 20 minute interval can move either way
+        assert_eq!(jittered_secs(1200, 90), 1290);
+        assert_eq!(jittered_secs(1200, -90), 1110);
+        // never below the 15 minute floor
+        assert_eq!(jittered_secs(900, -90), 900);
+        assert_eq!(jittered_secs(900, 60), 960);
+        // short waits (cooldowns, posting windows) are only ever lengthened
+        assert_eq!(jittered_secs(180, -90), 180);
+        assert_eq!(jittered_secs(180, 30), 210);
+        assert_eq!(random_jitter_offset(0), 0);
+        for _ in 0..100 {
+            assert!(random_jitter_offset(90).abs() <= 90);
+        }
+    }
+
+    #[test]
+    fn test_hour_in_window_same_day() {
+        assert!(hour_in_window(15, 15, 23));
+        assert!(hour_in_window(22, 15, 23));
+        assert!(!hour_in_window(23, 15, 23));
+        assert!(!hour_in_window(9, 15, 23));
+    }
+
+    #[test]
+    fn test_hour_in_window_wraps_midnight() {
+        assert!(hour_in_window(22, 22, 4));
+        assert!(hour_in_window(0, 22, 4));
+        assert!(hour_in_window(3, 22, 4));
+        assert!(!hour_in_window(4, 22, 4));
+        assert!(!hour_in_window(12, 22, 4));
+    }
+
+    #[test]
+    fn test_minutes_until_hour() {
+        let now = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(10, 30, 0)
+            .unwrap();
+        // later today
+        assert_eq!(minutes_until_hour(now, 15), 270);
+        // already past today's start -> tomorrow
+        assert_eq!(minutes_until_hour(now, 2), 930);
+    }
 }
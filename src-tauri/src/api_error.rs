@@ -0,0 +1,205 @@
+// api_error.rs
+// Responsibility: Shared error type for the Rolimons HTTP calls
+
+use serde::Serialize;
+
+/// A failed API call, classified so the UI can react (wait, re-authenticate, retry later...).
+/// Display output starts with the variant name (e.g. `rate_limited: ...`) so it survives being
+/// flattened into a string by the Tauri commands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ApiError {
+    /// HTTP 429 or an explicit cooldown; `retry_after` is in seconds when the server said.
+    RateLimited { retry_after: Option<u64> },
+    /// 5xx from the server.
+    ServerError(u16),
+    /// 401/403, or the _RoliVerification cookie was rejected.
+    Unauthorized,
+    /// An HTML page (usually a Cloudflare challenge) came back instead of JSON.
+    BlockedByChallenge,
+    /// Any other non-success status, with the response body.
+    Rejected { status: u16, message: String },
+    /// The response could not be parsed.
+    Parse(String),
+    /// The request never got a response (DNS, TLS, timeout...).
+    Network(String),
+}
+
+impl ApiError {
+    /// Classify a non-success HTTP status.
+    pub fn from_status(status: u16, retry_after: Option<u64>, body: &str) -> Self {
+        match status {
+            429 => ApiError::RateLimited { retry_after },
+            401 | 403 => ApiError::Unauthorized,
+            500..=599 => ApiError::ServerError(status),
+            _ => ApiError::Rejected {
+                status,
+                message: body.to_string(),
+            },
+        }
+    }
+
+    /// Short machine-readable name of the variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::ServerError(_) => "server_error",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::BlockedByChallenge => "blocked_by_challenge",
+            ApiError::Rejected { .. } => "rejected",
+            ApiError::Parse(_) => "parse",
+            ApiError::Network(_) => "network",
+        }
+    }
+
+    /// Seconds to wait before retrying, if the server told us.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            ApiError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = self.kind();
+        match self {
+            ApiError::RateLimited {
+                retry_after: Some(secs),
+            } => write!(f, "{}: retry after {}s", kind, secs),
+            ApiError::RateLimited { retry_after: None } | ApiError::Unauthorized => {
+                write!(f, "{}", kind)
+            }
+            ApiError::ServerError(status) => write!(f, "{}: HTTP {}", kind, status),
+            ApiError::BlockedByChallenge => write!(
+                f,
+                "{}: Rolimons returned a browser challenge page; open rolimons.com in your browser and refresh your session",
+                kind
+            ),
+            ApiError::Rejected { status, message } => {
+                write!(f, "{}: HTTP {} - {}", kind, status, message)
+            }
+            ApiError::Parse(msg) | ApiError::Network(msg) => write!(f, "{}: {}", kind, msg),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            ApiError::Parse(e.to_string())
+        } else {
+            ApiError::Network(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::Parse(e.to_string())
+    }
+}
+
+/// Seconds from a numeric Retry-After header, if present.
+pub fn retry_after_header(resp: &reqwest::Response) -> Option<u64> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+/// Whether a response is an HTML page (e.g. a Cloudflare challenge) rather than an API answer,
+/// judged by its Content-Type or the start of the body.
+pub fn is_challenge_page(content_type: Option<&str>, body: &str) -> bool {
+    if content_type.is_some_and(|ct| ct.to_ascii_lowercase().contains("text/html")) {
+        return true;
+    }
+    let start: String = body.trim_start().chars().take(16).collect();
+    let start = start.to_ascii_lowercase();
+    start.starts_with("<!doctype") || start.starts_with("<html")
+}
+
+/// Pass successful responses through; turn anything else into an ApiError.
+pub async fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, ApiError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let retry_after = retry_after_header(&resp);
+    let body = resp.text().await.unwrap_or_default();
+    Err(ApiError::from_status(status.as_u16(), retry_after, &body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert_eq!(
+            ApiError::from_status(429, Some(30), ""),
+            ApiError::RateLimited {
+                retry_after: Some(30)
+            }
+        );
+        assert_eq!(ApiError::from_status(403, None, ""), ApiError::Unauthorized);
+        assert_eq!(
+            ApiError::from_status(502, None, ""),
+            ApiError::ServerError(502)
+        );
+        assert_eq!(
+            ApiError::from_status(400, None, "bad"),
+            ApiError::Rejected {
+                status: 400,
+                message: "bad".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_display_starts_with_kind() {
+        let errors = [
+            ApiError::RateLimited {
+                retry_after: Some(5),
+            },
+            ApiError::ServerError(500),
+            ApiError::Unauthorized,
+            ApiError::BlockedByChallenge,
+            ApiError::Parse("eof".to_string()),
+            ApiError::Network("timeout".to_string()),
+        ];
+        for e in errors {
+            assert!(e.to_string().starts_with(e.kind()), "{}", e);
+        }
+    }
+
+    #[test]
+    fn test_is_challenge_page() {
+        assert!(is_challenge_page(Some("text/html; charset=UTF-8"), "{}"));
+        assert!(is_challenge_page(
+            None,
+            "\n  <!DOCTYPE html><html><title>Just a moment...</title>"
+        ));
+        assert!(is_challenge_page(
+            Some("application/octet-stream"),
+            "<HTML><body></body></HTML>"
+        ));
+        assert!(!is_challenge_page(
+            Some("application/json"),
+            r#"{"success":false}"#
+        ));
+        assert!(!is_challenge_page(None, "invalid token"));
+    }
+
+    #[test]
+    fn test_serializes_with_kind_tag() {
+        let v = serde_json::to_value(ApiError::ServerError(503)).unwrap();
+        assert_eq!(
+            v,
+            serde_json::json!({ "kind": "server_error", "detail": 503 })
+        );
+    }
+}
@@ -0,0 +1,145 @@
+// app_log.rs
+// Responsibility: Backend for the `log` macros: timestamped, level-prefixed lines written to
+// stderr and to app.log in the app-local data dir, with size-based rotation and tail reads for the UI.
+
+use chrono::Local;
+use dirs::data_local_dir;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Rotate app.log to app.log.1 once it grows past this many bytes.
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+fn log_path() -> Option<PathBuf> {
+    let mut dir = data_local_dir()?;
+    dir.push("roli-trade-ad-automation");
+    let _ = fs::create_dir_all(&dir);
+    dir.push("app.log");
+    Some(dir)
+}
+
+/// The single rotated copy kept next to `path`.
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("log.1")
+}
+
+/// Move `path` aside once it exceeds `max_bytes`, replacing any older backup.
+fn rotate_if_needed(path: &Path, max_bytes: u64) {
+    let Ok(meta) = fs::metadata(path) else {
+        return;
+    };
+    if meta.len() > max_bytes {
+        let _ = fs::rename(path, rotated_path(path));
+    }
+}
+
+fn append_to(path: &Path, msg: &str, max_bytes: u64) {
+    rotate_if_needed(path, max_bytes);
+    if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(f, "{}: {}", Local::now().to_rfc3339(), msg);
+    }
+}
+
+/// Write a timestamped line to app.log.
+pub fn append(msg: &str) {
+    if let Some(path) = log_path() {
+        append_to(&path, msg, MAX_LOG_BYTES);
+    }
+}
+
+struct AppLogger;
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("[{}] {}", record.level(), record.args());
+        eprintln!("{}: {}", Local::now().to_rfc3339(), line);
+        append(&line);
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: AppLogger = AppLogger;
+
+/// Install the logger. The level defaults to INFO and can be changed with the
+/// ROLI_LOG_LEVEL environment variable (error, warn, info, debug, trace, off).
+pub fn init() {
+    let level = std::env::var("ROLI_LOG_LEVEL")
+        .ok()
+        .and_then(|v| v.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::Info);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+fn read_tail_from(path: &Path, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    // right after a rotation the current file is short, so fill up from the backup
+    if lines.len() < max_lines {
+        let older = fs::read_to_string(rotated_path(path)).unwrap_or_default();
+        let mut combined: Vec<String> = older.lines().map(str::to_string).collect();
+        combined.append(&mut lines);
+        lines = combined;
+    }
+    let start = lines.len().saturating_sub(max_lines);
+    lines.split_off(start)
+}
+
+/// The last `max_lines` lines of the log, oldest first.
+pub fn read_tail(max_lines: usize) -> Vec<String> {
+    match log_path() {
+        Some(path) => read_tail_from(&path, max_lines),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_log(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("roli-app-log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("app.log")
+    }
+
+    #[test]
+    fn test_rotation_keeps_one_backup() {
+        let path = test_log("rotate");
+        for i in 0..20 {
+            append_to(&path, &format!("line {}", i), 200);
+        }
+        assert!(fs::metadata(&path).unwrap().len() <= 200 + 64);
+        assert!(rotated_path(&path).exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_read_tail_spans_rotation() {
+        let path = test_log("tail");
+        fs::write(rotated_path(&path), "a\nb\nc\n").unwrap();
+        fs::write(&path, "d\ne\n").unwrap();
+
+        assert_eq!(read_tail_from(&path, 2), vec!["d", "e"]);
+        assert_eq!(read_tail_from(&path, 4), vec!["b", "c", "d", "e"]);
+        assert_eq!(read_tail_from(&path, 10).len(), 5);
+        assert!(read_tail_from(&path, 0).is_empty());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
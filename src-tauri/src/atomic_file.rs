@@ -0,0 +1,130 @@
+// atomic_file.rs
+// Responsibility: Crash-safe writes for the JSON storage files, keeping a .bak of the
+// previous good version.
+
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `<file>.<suffix>` next to `path`.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Where the previous version of `path` is kept.
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, "bak")
+}
+
+/// Write `contents` to `path` without ever leaving a half-written file behind.
+/// The data goes to a temp file in the same directory which is then renamed over
+/// the target (atomic on the same filesystem). The old file is copied to `.bak` first.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let tmp = sibling_path(path, "tmp");
+    {
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(contents.as_ref())?;
+        f.sync_all()?;
+    }
+    if path.exists() {
+        if let Err(e) = fs::copy(path, backup_path(path)) {
+            log::warn!("atomic_file: could not back up {}: {}", path.display(), e);
+        }
+    }
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Read and parse `path`, falling back to its `.bak` when the file is unreadable or
+/// doesn't parse. A recovered backup is written back over the damaged file.
+pub fn read_with_backup<T>(path: &Path, parse: impl Fn(&str) -> Result<T>) -> Result<T> {
+    let primary_err = match fs::read_to_string(path) {
+        Ok(raw) => match parse(&raw) {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        },
+        Err(e) => e.into(),
+    };
+
+    let bak = backup_path(path);
+    let raw = fs::read_to_string(&bak).map_err(|_| {
+        anyhow!(
+            "{} is unreadable ({}) and has no backup",
+            path.display(),
+            primary_err
+        )
+    })?;
+    let value = parse(&raw).map_err(|e| {
+        anyhow!(
+            "{} and its backup are both unreadable: {}; {}",
+            path.display(),
+            primary_err,
+            e
+        )
+    })?;
+    log::warn!(
+        "atomic_file: {} was damaged ({}), restored from backup",
+        path.display(),
+        primary_err
+    );
+    if let Err(e) = fs::copy(&bak, path) {
+        log::error!("atomic_file: could not restore {}: {}", path.display(), e);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("roli-atomic-file-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn parse_list(raw: &str) -> Result<Vec<u32>> {
+        Ok(serde_json::from_str(raw)?)
+    }
+
+    #[test]
+    fn test_write_keeps_backup_of_previous_version() {
+        let dir = test_dir("backup");
+        let path = dir.join("ads.json");
+        write_atomic(&path, "[1]").unwrap();
+        write_atomic(&path, "[1,2]").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1,2]");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "[1]");
+        assert!(!sibling_path(&path, "tmp").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_partial_write_is_recoverable() {
+        let dir = test_dir("partial");
+        let path = dir.join("ads.json");
+        write_atomic(&path, "[1]").unwrap();
+        write_atomic(&path, "[1,2]").unwrap();
+
+        // a crash before the rename only leaves a stray temp file; the target is untouched
+        fs::write(sibling_path(&path, "tmp"), "[1,2,").unwrap();
+        assert_eq!(read_with_backup(&path, parse_list).unwrap(), vec![1, 2]);
+
+        // a truncated target (e.g. written by an older version) falls back to the backup
+        fs::write(&path, "[1,2,").unwrap();
+        assert_eq!(read_with_backup(&path, parse_list).unwrap(), vec![1]);
+        // and the damaged file is repaired
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1]");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
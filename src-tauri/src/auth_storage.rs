@@ -1,5 +1,6 @@
 // auth_storage.rs
-// Responsibility: Persist and load authentication data (user_id and roli_verification).
+// Responsibility: Persist and load authentication data (user_id and roli_verification)
+// for one or more accounts, plus which account is currently active.
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -14,37 +15,142 @@ pub struct AuthData {
     pub roli_verification: Option<String>,
 }
 
-/// Get the path to the auth storage file.
-fn get_auth_file_path() -> Result<PathBuf> {
+/// Get the app config directory (created if missing).
+fn get_app_dir() -> Result<PathBuf> {
     let config_dir =
         dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Failed to get config directory"))?;
     let app_dir = config_dir.join("roli-trade-ad-automation");
     fs::create_dir_all(&app_dir)?;
-    Ok(app_dir.join("auth.json"))
+    Ok(app_dir)
 }
 
-/// Save authentication data to disk.
-pub fn save_auth(auth: &AuthData) -> Result<()> {
-    let path = get_auth_file_path()?;
-    let json = serde_json::to_string_pretty(auth)?;
-    fs::write(path, json)?;
-    eprintln!("auth_storage: saved auth for user_id={}", auth.user_id);
+/// Get the path to the auth storage file.
+fn get_auth_file_path() -> Result<PathBuf> {
+    Ok(get_app_dir()?.join("auth.json"))
+}
+
+/// Get the path to the file holding the active account's user_id.
+fn get_active_account_file_path() -> Result<PathBuf> {
+    Ok(get_app_dir()?.join("active_account.json"))
+}
+
+/// Write all accounts to disk. roli_verification tokens are encrypted at rest.
+fn write_accounts(accounts: &[AuthData]) -> Result<()> {
+    let mut stored = accounts.to_vec();
+    for auth in stored.iter_mut() {
+        if let Some(token) = &auth.roli_verification {
+            auth.roli_verification = Some(crate::secret_store::encrypt_if_plain(token)?);
+        }
+    }
+    let json = serde_json::to_string_pretty(&stored)?;
+    crate::atomic_file::write_atomic(&get_auth_file_path()?, json)?;
     Ok(())
 }
 
-/// Load authentication data from disk. Returns None if no auth file exists.
-pub fn load_auth() -> Result<Option<AuthData>> {
+fn read_active_account_id() -> Result<Option<u64>> {
+    let path = get_active_account_file_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    crate::atomic_file::read_with_backup(&path, |contents| Ok(serde_json::from_str(contents)?))
+}
+
+fn write_active_account_id(user_id: Option<u64>) -> Result<()> {
+    let json = serde_json::to_string(&user_id)?;
+    crate::atomic_file::write_atomic(&get_active_account_file_path()?, json)?;
+    Ok(())
+}
+
+/// List all stored accounts.
+pub fn list_accounts() -> Result<Vec<AuthData>> {
     let path = get_auth_file_path()?;
 
     if !path.exists() {
-        eprintln!("auth_storage: no auth file found");
+        return Ok(Vec::new());
+    }
+
+    // Older versions stored a single AuthData object rather than a list.
+    let (mut accounts, legacy_single) = crate::atomic_file::read_with_backup(&path, |contents| {
+        match serde_json::from_str::<Vec<AuthData>>(contents) {
+            Ok(list) => Ok((list, false)),
+            Err(_) => Ok((vec![serde_json::from_str::<AuthData>(contents)?], true)),
+        }
+    })?;
+
+    let mut needs_migration = legacy_single;
+    for auth in accounts.iter_mut() {
+        if let Some(stored) = auth.roli_verification.as_mut() {
+            needs_migration |= !crate::secret_store::is_encrypted(stored);
+            match crate::secret_store::decrypt(stored) {
+                Ok(token) => *stored = token,
+                // keep the ciphertext so the next save doesn't lose the token for good
+                Err(e) => log::error!(
+                    "auth_storage: failed to decrypt roli_verification for user_id={}: {}",
+                    auth.user_id,
+                    e
+                ),
+            }
+        }
+    }
+    if needs_migration {
+        // older versions stored a single plaintext account; re-save as an encrypted list
+        write_accounts(&accounts)?;
+        if legacy_single {
+            write_active_account_id(Some(accounts[0].user_id))?;
+        }
+        log::info!("auth_storage: migrated auth file to encrypted account list");
+    }
+    Ok(accounts)
+}
+
+/// Save authentication data to disk, replacing any account with the same user_id,
+/// and make it the active account.
+pub fn save_auth(auth: &AuthData) -> Result<()> {
+    let mut accounts = list_accounts()?;
+    if let Some(idx) = accounts.iter().position(|a| a.user_id == auth.user_id) {
+        accounts[idx] = auth.clone();
+    } else {
+        accounts.push(auth.clone());
+    }
+    write_accounts(&accounts)?;
+    write_active_account_id(Some(auth.user_id))?;
+    log::info!("auth_storage: saved auth for user_id={}", auth.user_id);
+    Ok(())
+}
+
+/// Load the active account's authentication data. Returns None if no account is active.
+pub fn load_auth() -> Result<Option<AuthData>> {
+    let accounts = list_accounts()?;
+    if accounts.is_empty() {
+        log::debug!("auth_storage: no auth file found");
         return Ok(None);
     }
+    let active = match read_active_account_id()? {
+        Some(id) => accounts.into_iter().find(|a| a.user_id == id),
+        None => None,
+    };
+    if let Some(auth) = &active {
+        log::debug!("auth_storage: loaded auth for user_id={}", auth.user_id);
+    }
+    Ok(active)
+}
 
-    let contents = fs::read_to_string(path)?;
-    let auth: AuthData = serde_json::from_str(&contents)?;
-    eprintln!("auth_storage: loaded auth for user_id={}", auth.user_id);
-    Ok(Some(auth))
+/// Look up a stored account by user_id.
+pub fn get_account(user_id: u64) -> Result<Option<AuthData>> {
+    Ok(list_accounts()?.into_iter().find(|a| a.user_id == user_id))
+}
+
+/// Switch the active account. The account must already be stored.
+pub fn set_active_account(user_id: u64) -> Result<()> {
+    if get_account(user_id)?.is_none() {
+        return Err(anyhow::anyhow!(
+            "No stored account with user_id={}",
+            user_id
+        ));
+    }
+    write_active_account_id(Some(user_id))?;
+    log::info!("auth_storage: active account set to user_id={}", user_id);
+    Ok(())
 }
 
 /// Update the roli_verification for the current user.
@@ -57,16 +163,26 @@ pub fn update_roli_verification(roli_verification: String) -> Result<()> {
         Some(roli_verification)
     };
     save_auth(&auth)?;
-    eprintln!("auth_storage: updated roli_verification");
+    log::info!("auth_storage: updated roli_verification");
     Ok(())
 }
 
-/// Clear authentication data (logout).
+/// Clear the active account's authentication data (logout). Other accounts are kept.
 pub fn clear_auth() -> Result<()> {
-    let path = get_auth_file_path()?;
-    if path.exists() {
-        fs::remove_file(path)?;
-        eprintln!("auth_storage: cleared auth");
+    let active = read_active_account_id()?;
+    let mut accounts = list_accounts()?;
+    if let Some(id) = active {
+        accounts.retain(|a| a.user_id != id);
+    }
+    if accounts.is_empty() {
+        let path = get_auth_file_path()?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    } else {
+        write_accounts(&accounts)?;
     }
+    write_active_account_id(None)?;
+    log::info!("auth_storage: cleared auth");
     Ok(())
 }
@@ -1,38 +1,103 @@
-use reqwest::header::USER_AGENT;
+use crate::http;
+use crate::rate_limit;
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::future::Future;
 
-/// Tauri command: fetch avatar bust thumbnails from Rolimons for a list of user IDs.
-/// Returns a mapping from user id string -> thumbnail URL (only entries with a URL are returned).
-#[tauri::command]
-pub async fn fetch_avatar_thumbnails(
-    user_ids: Vec<u64>,
-) -> Result<HashMap<String, String>, String> {
-    if user_ids.is_empty() {
-        return Ok(HashMap::new());
+/// Ids per request, to avoid extremely long URLs.
+const CHUNK_SIZE: usize = 50;
+/// Chunks in flight at once.
+const MAX_CONCURRENT_CHUNKS: usize = 8;
+
+/// User id string -> thumbnail URL.
+type ThumbMap = HashMap<String, String>;
+const DEFAULT_SIZE: &str = "150x150";
+
+/// Which avatar render to fetch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarKind {
+    /// Head and shoulders, served by Rolimons.
+    #[default]
+    Bust,
+    /// Square face crop, served by Roblox.
+    Headshot,
+    /// Whole avatar, served by Roblox.
+    Fullbody,
+}
+
+/// `size` must look like `150x150`; which sizes a kind supports is up to the server.
+fn is_valid_size(size: &str) -> bool {
+    match size.split_once('x') {
+        Some((w, h)) => {
+            !w.is_empty()
+                && !h.is_empty()
+                && w.chars().all(|c| c.is_ascii_digit())
+                && h.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
     }
+}
 
-    // Only fetch up to 50 ids in one request to avoid extremely long URLs.
-    let chunk: Vec<String> = user_ids.iter().take(50).map(|id| id.to_string()).collect();
-    let url = format!(
-        "https://thumbnails.rolimons.com/avatarbust?userIds={}&size=150x150",
-        chunk.join(",")
-    );
+fn thumbnail_url(kind: AvatarKind, ids: &[u64], size: &str) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    let ids = ids.join(",");
+    match kind {
+        AvatarKind::Bust => format!(
+            "https://thumbnails.rolimons.com/avatarbust?userIds={}&size={}",
+            ids, size
+        ),
+        AvatarKind::Headshot => format!(
+            "https://thumbnails.roblox.com/v1/users/avatar-headshot?userIds={}&size={}&format=Png",
+            ids, size
+        ),
+        AvatarKind::Fullbody => format!(
+            "https://thumbnails.roblox.com/v1/users/avatar?userIds={}&size={}&format=Png",
+            ids, size
+        ),
+    }
+}
 
-    let start = std::time::Instant::now();
-    eprintln!(
-        "avatar_thumbnails: fetching for ids={} url={}",
-        chunk.join(","),
-        url
-    );
+/// User id string -> URL from either response shape: Rolimons' `thumbnails` object or
+/// Roblox's `data` array. Entries without a URL are skipped.
+fn parse_thumbnails(json: &Value) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(8))
-        .build()
-        .map_err(|e| e.to_string())?;
-    let resp = client
+    if let Some(thumbs) = json.get("thumbnails").and_then(|v| v.as_object()) {
+        for (k, v) in thumbs.iter() {
+            if let Some(url_val) = v.get("url").and_then(|s| s.as_str()) {
+                map.insert(k.clone(), url_val.to_string());
+            }
+        }
+    }
+    if let Some(data) = json.get("data").and_then(|v| v.as_array()) {
+        for entry in data {
+            let id = entry.get("targetId").and_then(|v| v.as_u64());
+            let url_val = entry.get("imageUrl").and_then(|v| v.as_str());
+            if let (Some(id), Some(url_val)) = (id, url_val.filter(|u| !u.is_empty())) {
+                map.insert(id.to_string(), url_val.to_string());
+            }
+        }
+    }
+    map
+}
+
+async fn fetch_chunk(
+    kind: AvatarKind,
+    ids: &[u64],
+    size: &str,
+) -> Result<HashMap<String, String>, String> {
+    let url = thumbnail_url(kind, ids, size);
+    log::debug!("avatar_thumbnails: fetching {} ids url={}", ids.len(), url);
+
+    if kind == AvatarKind::Bust {
+        rate_limit::acquire().await;
+    }
+    let resp = http::client()
         .get(&url)
-        .header(USER_AGENT, "rolimons-avatar-fetcher/1.0")
+        .timeout(std::time::Duration::from_secs(8))
         .send()
         .await
         .map_err(|e| e.to_string())?;
@@ -42,16 +107,159 @@ pub async fn fetch_avatar_thumbnails(
     }
 
     let json: Value = resp.json().await.map_err(|e| e.to_string())?;
-    let mut map: HashMap<String, String> = HashMap::new();
+    Ok(parse_thumbnails(&json))
+}
 
-    if let Some(thumbs) = json.get("thumbnails").and_then(|v| v.as_object()) {
-        for (k, v) in thumbs.iter() {
-            if let Some(url_val) = v.get("url").and_then(|s| s.as_str()) {
-                map.insert(k.clone(), url_val.to_string());
+/// Run `fetch` over `ids` in chunks of CHUNK_SIZE, up to MAX_CONCURRENT_CHUNKS at a time, and
+/// merge the results in chunk order. A failing chunk is logged and skipped so the others still
+/// get thumbnails; the call only fails when every chunk did.
+async fn fetch_in_chunks<F, Fut>(ids: &[u64], fetch: F) -> Result<HashMap<String, String>, String>
+where
+    F: Fn(Vec<u64>) -> Fut,
+    Fut: Future<Output = Result<HashMap<String, String>, String>>,
+{
+    let chunks: Vec<Vec<u64>> = ids.chunks(CHUNK_SIZE).map(<[u64]>::to_vec).collect();
+    let mut results: Vec<(usize, usize, Result<ThumbMap, String>)> =
+        stream::iter(chunks.into_iter().enumerate())
+            .map(|(i, chunk)| {
+                let len = chunk.len();
+                let fut = fetch(chunk);
+                async move { (i, len, fut.await) }
+            })
+            .buffer_unordered(MAX_CONCURRENT_CHUNKS)
+            .collect()
+            .await;
+    results.sort_by_key(|(i, _, _)| *i);
+
+    let mut map: HashMap<String, String> = HashMap::new();
+    let mut last_err = None;
+    let mut succeeded = 0;
+    for (_, len, result) in results {
+        match result {
+            Ok(part) => {
+                succeeded += 1;
+                map.extend(part);
+            }
+            Err(e) => {
+                log::warn!("avatar_thumbnails: chunk of {} ids failed: {}", len, e);
+                last_err = Some(e);
             }
         }
     }
+    match last_err {
+        Some(e) if succeeded == 0 => Err(e),
+        _ => Ok(map),
+    }
+}
 
-    eprintln!("avatar_thumbnails: fetched {} thumbnails in {:?}", map.len(), start.elapsed());
+/// Tauri command: fetch avatar thumbnails for a list of user IDs. `kind` is "bust" (default),
+/// "headshot" or "fullbody" and `size` e.g. "150x150" (the default).
+/// Returns a mapping from user id string -> thumbnail URL (only entries with a URL are returned).
+#[tauri::command]
+pub async fn fetch_avatar_thumbnails(
+    user_ids: Vec<u64>,
+    kind: Option<AvatarKind>,
+    size: Option<String>,
+) -> Result<HashMap<String, String>, String> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let kind = kind.unwrap_or_default();
+    let size = size.unwrap_or_else(|| DEFAULT_SIZE.to_string());
+    if !is_valid_size(&size) {
+        return Err(format!(
+            "Invalid thumbnail size '{}' (expected e.g. 150x150)",
+            size
+        ));
+    }
+
+    let start = std::time::Instant::now();
+    let map = fetch_in_chunks(&user_ids, |chunk| {
+        let size = size.clone();
+        async move { fetch_chunk(kind, &chunk, &size).await }
+    })
+    .await?;
+
+    log::debug!(
+        "avatar_thumbnails: fetched {} thumbnails in {:?}",
+        map.len(),
+        start.elapsed()
+    );
     Ok(map)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_url_by_kind() {
+        assert_eq!(
+            thumbnail_url(AvatarKind::Bust, &[1, 2], DEFAULT_SIZE),
+            "https://thumbnails.rolimons.com/avatarbust?userIds=1,2&size=150x150"
+        );
+        assert!(
+            thumbnail_url(AvatarKind::Headshot, &[1], "48x48").starts_with(
+                "https://thumbnails.roblox.com/v1/users/avatar-headshot?userIds=1&size=48x48"
+            )
+        );
+        assert!(thumbnail_url(AvatarKind::Fullbody, &[1], "420x420")
+            .starts_with("https://thumbnails.roblox.com/v1/users/avatar?userIds=1&size=420x420"));
+        assert!(is_valid_size("150x150"));
+        assert!(!is_valid_size("150"));
+        assert!(!is_valid_size("x150"));
+        assert!(!is_valid_size("150x150&format=Jpeg"));
+    }
+
+    #[tokio::test]
+    async fn test_all_chunks_attempted_despite_failure() {
+        let ids: Vec<u64> = (1..=120).collect();
+        let attempted = std::sync::Mutex::new(Vec::new());
+        let map = fetch_in_chunks(&ids, |chunk| {
+            attempted.lock().unwrap().push(chunk.clone());
+            async move {
+                if chunk.contains(&60) {
+                    return Err("thumbnail HTTP error: 500".to_string());
+                }
+                Ok(chunk
+                    .iter()
+                    .map(|id| (id.to_string(), format!("https://a/{}.png", id)))
+                    .collect())
+            }
+        })
+        .await
+        .unwrap();
+
+        let attempted = attempted.into_inner().unwrap();
+        let sizes: Vec<usize> = attempted.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![50, 50, 20]);
+        assert_eq!(attempted.concat(), ids);
+        // the failed middle chunk is skipped, the rest are merged
+        assert_eq!(map.len(), 70);
+        assert!(map.contains_key("120"));
+        assert!(!map.contains_key("60"));
+
+        let all_failed = fetch_in_chunks(&ids, |_| async { Err("down".to_string()) }).await;
+        assert_eq!(all_failed.unwrap_err(), "down");
+    }
+
+    #[test]
+    fn test_parse_both_response_shapes() {
+        let rolimons = serde_json::json!({
+            "thumbnails": { "1": { "url": "https://a/1.png" }, "2": { "state": "Pending" } }
+        });
+        let map = parse_thumbnails(&rolimons);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["1"], "https://a/1.png");
+
+        let roblox = serde_json::json!({
+            "data": [
+                { "targetId": 3, "state": "Completed", "imageUrl": "https://b/3.png" },
+                { "targetId": 4, "state": "Blocked", "imageUrl": "" }
+            ]
+        });
+        let map = parse_thumbnails(&roblox);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map["3"], "https://b/3.png");
+    }
+}
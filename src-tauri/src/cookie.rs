@@ -0,0 +1,394 @@
+// cookie.rs
+// Responsibility: Extract the _RoliVerification cookie from a local browser profile
+// so users don't have to copy it out of devtools by hand.
+
+use anyhow::{anyhow, Result};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const COOKIE_NAME: &str = "_RoliVerification";
+// how many times to try copying a locked browser DB before giving up
+const COPY_ATTEMPTS: u32 = 5;
+// plausible length range for a _RoliVerification value
+const MIN_TOKEN_LEN: usize = 16;
+const MAX_TOKEN_LEN: usize = 4096;
+
+/// Characters RFC 6265 allows in a cookie value.
+fn is_cookie_value_char(c: char) -> bool {
+    c.is_ascii_graphic() && !matches!(c, '"' | ',' | ';' | '\\')
+}
+
+/// Clean up a pasted _RoliVerification: accepts the bare value, `_RoliVerification=value;`,
+/// or a whole `Cookie:` header, with surrounding whitespace and quotes. Returns the bare
+/// value, or an error saying why it can't be a token.
+pub fn normalize_roli_verification(raw: &str) -> Result<String> {
+    let mut text = raw.trim();
+    if text
+        .get(..7)
+        .is_some_and(|p| p.eq_ignore_ascii_case("cookie:"))
+    {
+        text = text[7..].trim();
+    }
+
+    let mut value = text;
+    if text.contains('=') {
+        // the value itself never contains '=', so this is a name=value pair or a header
+        value = text
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(COOKIE_NAME))
+            .map(|(_, v)| v)
+            .ok_or_else(|| anyhow!("No {} cookie found in the pasted text", COOKIE_NAME))?;
+    }
+    let value = value
+        .trim()
+        .trim_end_matches(';')
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .trim();
+
+    if value.is_empty() {
+        return Err(anyhow!("{} is empty", COOKIE_NAME));
+    }
+    if let Some(c) = value.chars().find(|c| !is_cookie_value_char(*c)) {
+        return Err(anyhow!(
+            "{} contains an unexpected character {:?}; paste only the cookie value",
+            COOKIE_NAME,
+            c
+        ));
+    }
+    if !(MIN_TOKEN_LEN..=MAX_TOKEN_LEN).contains(&value.len()) {
+        return Err(anyhow!(
+            "{} should be {}-{} characters long, got {}",
+            COOKIE_NAME,
+            MIN_TOKEN_LEN,
+            MAX_TOKEN_LEN,
+            value.len()
+        ));
+    }
+    Ok(value.to_string())
+}
+
+/// Locate the Firefox profiles directory for the current user.
+pub fn get_firefox_profiles_dir() -> Result<PathBuf> {
+    #[cfg(windows)]
+    let dir = dirs::config_dir().map(|d| d.join("Mozilla").join("Firefox").join("Profiles"));
+    #[cfg(target_os = "macos")]
+    let dir = dirs::config_dir().map(|d| d.join("Firefox").join("Profiles"));
+    #[cfg(all(not(windows), not(target_os = "macos")))]
+    let dir = dirs::home_dir().map(|d| d.join(".mozilla").join("firefox"));
+
+    let dir = dir.ok_or_else(|| anyhow!("Failed to determine Firefox profile directory"))?;
+    if !dir.exists() {
+        return Err(anyhow!(
+            "Firefox profile directory not found at {}",
+            dir.display()
+        ));
+    }
+    Ok(dir)
+}
+
+/// List `cookies.sqlite` files across all Firefox profiles, most recently modified first.
+fn firefox_cookie_dbs(profiles_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dbs: Vec<(std::time::SystemTime, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(profiles_dir)?.flatten() {
+        let db = entry.path().join("cookies.sqlite");
+        if let Ok(meta) = fs::metadata(&db) {
+            let modified = meta.modified().unwrap_or(std::time::UNIX_EPOCH);
+            dbs.push((modified, db));
+        }
+    }
+    dbs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(dbs.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Copy a SQLite DB (and its WAL, if present) to the temp dir so a running browser's lock
+/// doesn't block us. Retries briefly since browsers hold the file open while writing.
+/// Returns the path of the copy.
+fn copy_db_to_temp(db: &Path, label: &str) -> Result<PathBuf> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let tmp = std::env::temp_dir().join(format!(
+        "roli-{}-cookies-{}-{}.sqlite",
+        label,
+        std::process::id(),
+        stamp
+    ));
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match fs::copy(db, &tmp) {
+            Ok(_) => break,
+            Err(e) if attempt < COPY_ATTEMPTS => {
+                log::warn!(
+                    "cookie: copy of {} failed (attempt {}/{}): {}",
+                    db.display(),
+                    attempt,
+                    COPY_ATTEMPTS,
+                    e
+                );
+                std::thread::sleep(std::time::Duration::from_millis(250));
+            }
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to copy {} ({}). The browser is probably running and has the cookie database locked; close it and try again.",
+                    db.display(),
+                    e
+                ));
+            }
+        }
+    }
+    // recent cookie writes may still live in the write-ahead log
+    let wal = PathBuf::from(format!("{}-wal", db.display()));
+    if wal.exists() {
+        let _ = fs::copy(&wal, format!("{}-wal", tmp.display()));
+    }
+    Ok(tmp)
+}
+
+fn remove_temp_db(tmp: &Path) {
+    let _ = fs::remove_file(tmp);
+    let _ = fs::remove_file(format!("{}-wal", tmp.display()));
+    let _ = fs::remove_file(format!("{}-shm", tmp.display()));
+}
+
+/// Read _RoliVerification from Firefox. Firefox stores cookie values unencrypted.
+pub fn extract_roli_verification_from_firefox() -> Result<String> {
+    let profiles_dir = get_firefox_profiles_dir()?;
+    let dbs = firefox_cookie_dbs(&profiles_dir)?;
+    if dbs.is_empty() {
+        return Err(anyhow!(
+            "No Firefox cookies.sqlite found under {}",
+            profiles_dir.display()
+        ));
+    }
+
+    for db in dbs {
+        let tmp = copy_db_to_temp(&db, "firefox")?;
+        let result = (|| -> Result<Option<String>> {
+            let conn = Connection::open_with_flags(&tmp, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            let value = conn
+                .query_row(
+                    "SELECT value FROM moz_cookies WHERE host LIKE '%rolimons%' AND name = ?1 ORDER BY expiry DESC LIMIT 1",
+                    [COOKIE_NAME],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?;
+            Ok(value)
+        })();
+        remove_temp_db(&tmp);
+        match result {
+            Ok(Some(v)) if !v.trim().is_empty() => return Ok(v),
+            Ok(_) => continue,
+            Err(e) => log::warn!("cookie: failed to read {}: {}", db.display(), e),
+        }
+    }
+
+    Err(anyhow!(
+        "No {} cookie found in Firefox. Log in to rolimons.com in Firefox first.",
+        COOKIE_NAME
+    ))
+}
+
+/// Locate Chrome's "User Data" directory for the current user.
+pub fn get_chrome_user_data_dir() -> Result<PathBuf> {
+    #[cfg(windows)]
+    let dir = dirs::data_local_dir().map(|d| d.join("Google").join("Chrome").join("User Data"));
+    #[cfg(target_os = "macos")]
+    let dir = dirs::config_dir().map(|d| d.join("Google").join("Chrome"));
+    #[cfg(all(not(windows), not(target_os = "macos")))]
+    let dir = dirs::config_dir().map(|d| d.join("google-chrome"));
+
+    let dir = dir.ok_or_else(|| anyhow!("Failed to determine Chrome user data directory"))?;
+    if !dir.exists() {
+        return Err(anyhow!(
+            "Chrome user data directory not found at {}",
+            dir.display()
+        ));
+    }
+    Ok(dir)
+}
+
+/// Find the Cookies DB for a Chrome profile (default "Default"). Newer Chrome versions keep it
+/// under `Network/`, older ones at the profile root.
+pub fn resolve_cookies_db(user_data_dir: &Path, profile: Option<&str>) -> Result<PathBuf> {
+    let profile_dir = user_data_dir.join(profile.unwrap_or("Default"));
+    let candidates = [
+        profile_dir.join("Network").join("Cookies"),
+        profile_dir.join("Cookies"),
+    ];
+    candidates.into_iter().find(|p| p.exists()).ok_or_else(|| {
+        anyhow!(
+            "No Chrome Cookies database found in {}",
+            profile_dir.display()
+        )
+    })
+}
+
+/// Read the AES key Chrome uses for cookie values from `Local State` (DPAPI-protected).
+#[cfg(windows)]
+fn chrome_master_key(user_data_dir: &Path) -> Result<Vec<u8>> {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    let raw = fs::read_to_string(user_data_dir.join("Local State"))?;
+    let state: serde_json::Value = serde_json::from_str(&raw)?;
+    let b64 = state
+        .get("os_crypt")
+        .and_then(|v| v.get("encrypted_key"))
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Chrome Local State has no os_crypt.encrypted_key"))?;
+    let blob = STANDARD.decode(b64)?;
+    let blob = blob
+        .strip_prefix(b"DPAPI")
+        .ok_or_else(|| anyhow!("Unexpected Chrome key format"))?;
+    crate::secret_store::dpapi_unprotect(blob)
+}
+
+/// Decrypt a Chrome `encrypted_value` ("v10"/"v11" AES-256-GCM). Newer cookie DBs prefix the
+/// plaintext with SHA-256(host_key), which is stripped when present.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn decrypt_chrome_value(key: &[u8], encrypted: &[u8], host_key: &str) -> Result<String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+    use sha2::{Digest, Sha256};
+
+    if encrypted.starts_with(b"v20") {
+        return Err(anyhow!(
+            "This Chrome version uses app-bound cookie encryption, which can't be read by other apps. Copy the cookie manually instead."
+        ));
+    }
+    if !(encrypted.starts_with(b"v10") || encrypted.starts_with(b"v11")) || encrypted.len() < 15 {
+        return Err(anyhow!("Unrecognized Chrome cookie encryption format"));
+    }
+    let nonce: [u8; 12] = encrypted[3..15].try_into()?;
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| anyhow!("{}", e))?;
+    let plain = cipher
+        .decrypt(&Nonce::from(nonce), &encrypted[15..])
+        .map_err(|_| anyhow!("Failed to decrypt Chrome cookie value"))?;
+    let host_hash = Sha256::digest(host_key.as_bytes());
+    let plain = match plain.strip_prefix(&host_hash[..]) {
+        Some(rest) => rest.to_vec(),
+        None => plain,
+    };
+    Ok(String::from_utf8(plain)?)
+}
+
+/// Read _RoliVerification from a copied Chrome Cookies DB and decrypt it.
+pub fn extract_roli_verification_from_chrome(
+    user_data_dir: &Path,
+    cookies_db: &Path,
+) -> Result<String> {
+    let tmp = copy_db_to_temp(cookies_db, "chrome")?;
+    let row = (|| -> Result<Option<(String, String, Vec<u8>)>> {
+        let conn = Connection::open_with_flags(&tmp, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let row = conn
+            .query_row(
+                "SELECT host_key, value, encrypted_value FROM cookies WHERE host_key LIKE '%rolimons%' AND name = ?1 ORDER BY expires_utc DESC LIMIT 1",
+                [COOKIE_NAME],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        Ok(row)
+    })();
+    remove_temp_db(&tmp);
+
+    let (host_key, value, encrypted) = row?.ok_or_else(|| {
+        anyhow!(
+            "No {} cookie found in Chrome. Log in to rolimons.com in Chrome first.",
+            COOKIE_NAME
+        )
+    })?;
+    if !value.is_empty() {
+        return Ok(value);
+    }
+
+    #[cfg(windows)]
+    {
+        let key = chrome_master_key(user_data_dir)?;
+        decrypt_chrome_value(&key, &encrypted, &host_key)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (user_data_dir, encrypted, host_key);
+        Err(anyhow!(
+            "Decrypting Chrome cookies is only supported on Windows"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+    use sha2::{Digest, Sha256};
+
+    fn encrypt_v10(key: &[u8], plain: &[u8]) -> Vec<u8> {
+        let nonce = [7u8; 12];
+        let cipher = Aes256Gcm::new_from_slice(key).unwrap();
+        let mut out = b"v10".to_vec();
+        out.extend_from_slice(&nonce);
+        out.extend(cipher.encrypt(&Nonce::from(nonce), plain).unwrap());
+        out
+    }
+
+    #[test]
+    fn test_decrypt_chrome_value_v10() {
+        let key = [3u8; 32];
+        let encrypted = encrypt_v10(&key, b"token-value");
+        let out = decrypt_chrome_value(&key, &encrypted, ".rolimons.com").unwrap();
+        assert_eq!(out, "token-value");
+    }
+
+    #[test]
+    fn test_decrypt_chrome_value_strips_host_hash() {
+        let key = [3u8; 32];
+        let mut plain = Sha256::digest(b".rolimons.com").to_vec();
+        plain.extend_from_slice(b"token-value");
+        let encrypted = encrypt_v10(&key, &plain);
+        let out = decrypt_chrome_value(&key, &encrypted, ".rolimons.com").unwrap();
+        assert_eq!(out, "token-value");
+    }
+
+    #[test]
+    fn test_decrypt_chrome_value_rejects_app_bound() {
+        let err = decrypt_chrome_value(&[0u8; 32], b"v20abcdefghijklmnop", "x").unwrap_err();
+        assert!(err.to_string().contains("app-bound"));
+    }
+
+    #[test]
+    fn test_normalize_roli_verification_paste_variants() {
+        let token = "abcDEF0123456789_-tokenvalue";
+        let variants = [
+            token.to_string(),
+            format!("  {}\n", token),
+            format!("\"{}\"", token),
+            format!("_RoliVerification={}", token),
+            format!("_RoliVerification={};", token),
+            format!("_roliverification = '{}' ;", token),
+            format!("Cookie: _ga=GA1.2.3; _RoliVerification={}; other=1", token),
+        ];
+        for v in &variants {
+            assert_eq!(
+                normalize_roli_verification(v).unwrap(),
+                token,
+                "input {:?}",
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_roli_verification_rejects() {
+        assert!(normalize_roli_verification("   ").is_err());
+        assert!(normalize_roli_verification("_RoliVerification=;").is_err());
+        assert!(normalize_roli_verification("short").is_err());
+        assert!(normalize_roli_verification("has space in the middle of it").is_err());
+        assert!(normalize_roli_verification("cookieé_RoliVerification").is_err());
+        let err = normalize_roli_verification("_ga=GA1.2.3; other=1").unwrap_err();
+        assert!(err.to_string().contains("No _RoliVerification cookie"));
+    }
+}
@@ -1,36 +1,39 @@
 // lib.rs: Tauri commands for Rolimons trade ad automation GUI application.
 
+mod ad_stats_storage;
 mod ads_runner;
 mod ads_storage;
+mod api_error;
+mod app_log;
+mod atomic_file;
 mod auth_storage;
 mod avatar_thumbnails;
+mod cookie;
+mod http;
 mod notification_settings;
 mod player_assets;
+mod rate_limit;
 mod roblox_user;
 mod rolimons_players;
-mod thumbnails;
+mod running_ads_storage;
+mod secret_store;
 mod trade_ad;
 mod value_change_detector;
+mod value_watch;
 mod verification;
+mod webhook;
 
-use chrono::Local;
-use dirs::data_local_dir;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
-use std::fs::OpenOptions;
-use std::io::Write;
-
-// Top-level helper: write a timestamped line to the app-local log so release runs can be diagnosed.
-fn append_app_log(msg: &str) {
-    if let Some(mut dir) = data_local_dir() {
-        dir.push("roli-trade-ad-automation");
-        let _ = std::fs::create_dir_all(&dir);
-        dir.push("app.log");
-        if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(&dir) {
-            let _ = writeln!(f, "{}: {}", Local::now().to_rfc3339(), msg);
-        }
-    }
+use tauri::Manager;
+// Shared with the catalog code so there is a single thumbnail cache
+use trade_ad::thumbnails;
+
+/// Tail of app.log (oldest first), including the rotated copy when the current file is short.
+#[tauri::command]
+fn read_app_log(max_lines: usize) -> Vec<String> {
+    app_log::read_tail(max_lines)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,48 +43,47 @@ pub struct TradeAdRequest {
     request_item_ids: Vec<u64>,
     request_tags: Vec<String>,
     roli_verification: String,
+    /// Check the offered items against the player's inventory before posting.
+    #[serde(default)]
+    verify_ownership: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TradeAdResponse {
     success: bool,
     logs: Vec<String>,
+    // id of the created ad, when Rolimons returned one
+    ad_id: Option<String>,
 }
 
 /// Tauri command to post a trade ad to Rolimons
 #[tauri::command]
-async fn post_trade_ad(request: TradeAdRequest) -> Result<TradeAdResponse, String> {
+async fn post_trade_ad(mut request: TradeAdRequest) -> Result<TradeAdResponse, String> {
     // Validate inputs
     let mut logs = Vec::new();
     logs.push("Connecting to Rolimons API...".to_string());
 
-    if request.offer_item_ids.is_empty() {
-        logs.push("You must offer at least one item".to_string());
-        return Ok(TradeAdResponse {
-            success: false,
-            logs,
-        });
-    }
-    if request.offer_item_ids.len() > 4 {
-        logs.push("You can only offer up to 4 items".to_string());
-        return Ok(TradeAdResponse {
-            success: false,
-            logs,
-        });
-    }
-    let total_requests = request.request_item_ids.len() + request.request_tags.len();
-    if total_requests == 0 {
-        logs.push("You must request at least one item or tag".to_string());
-        return Ok(TradeAdResponse {
-            success: false,
-            logs,
-        });
-    }
-    if total_requests > 4 {
-        logs.push("You can only request up to 4 items (combined item IDs and tags)".to_string());
+    request.request_tags = match ads_storage::normalize_request_tags(&request.request_tags) {
+        Ok(tags) => tags,
+        Err(e) => {
+            logs.push(e);
+            return Ok(TradeAdResponse {
+                success: false,
+                logs,
+                ad_id: None,
+            });
+        }
+    };
+    if let Err(e) = ads_storage::validate_item_counts(
+        &request.offer_item_ids,
+        &request.request_item_ids,
+        &request.request_tags,
+    ) {
+        logs.push(e);
         return Ok(TradeAdResponse {
             success: false,
             logs,
+            ad_id: None,
         });
     }
     if request.roli_verification.trim().is_empty() {
@@ -89,8 +91,22 @@ async fn post_trade_ad(request: TradeAdRequest) -> Result<TradeAdResponse, Strin
         return Ok(TradeAdResponse {
             success: false,
             logs,
+            ad_id: None,
         });
     }
+    if request.verify_ownership {
+        logs.push("Checking offered items against inventory...".to_string());
+        if let Err(e) =
+            player_assets::verify_offer_ownership(request.player_id, &request.offer_item_ids).await
+        {
+            logs.push(e);
+            return Ok(TradeAdResponse {
+                success: false,
+                logs,
+                ad_id: None,
+            });
+        }
+    }
     logs.push("Posting trade ad...".to_string());
     match trade_ad::post_trade_ad_direct(
         &request.roli_verification,
@@ -98,14 +114,16 @@ async fn post_trade_ad(request: TradeAdRequest) -> Result<TradeAdResponse, Strin
         request.offer_item_ids,
         request.request_item_ids,
         request.request_tags,
+        trade_ad::PostOptions::default(),
     )
     .await
     {
-        Ok(message) => {
-            logs.push(message);
+        Ok(posted) => {
+            logs.push(posted.message);
             Ok(TradeAdResponse {
                 success: true,
                 logs,
+                ad_id: posted.ad_id,
             })
         }
         Err(e) => {
@@ -113,26 +131,87 @@ async fn post_trade_ad(request: TradeAdRequest) -> Result<TradeAdResponse, Strin
             Ok(TradeAdResponse {
                 success: false,
                 logs,
+                ad_id: None,
             })
         }
     }
 }
 
+/// Tauri command to show the payload and headers `post_trade_ad` would send, without posting
+#[tauri::command]
+fn preview_trade_ad(
+    request: TradeAdRequest,
+) -> Result<trade_ad::post_trade_ad::TradeAdPreview, String> {
+    trade_ad::preview_trade_ad(
+        &request.roli_verification,
+        request.player_id,
+        &request.offer_item_ids,
+        &request.request_item_ids,
+        &request.request_tags,
+    )
+    .map_err(|e| e.to_string())
+}
+
 // ===== Ads storage commands =====
 
+/// List stored ads, optionally only the enabled (or only the disabled) ones.
 #[tauri::command]
-fn list_ads() -> Result<Vec<ads_storage::AdData>, String> {
-    ads_storage::list_ads().map_err(|e| e.to_string())
+fn list_ads(enabled: Option<bool>) -> Result<Vec<ads_storage::AdData>, String> {
+    let mut ads = ads_storage::list_ads().map_err(|e| e.to_string())?;
+    if let Some(enabled) = enabled {
+        ads.retain(|ad| ad.enabled == enabled);
+    }
+    Ok(ads)
 }
 
+/// Enable or disable an ad. Disabling a running ad stops it.
 #[tauri::command]
-fn save_ad(ad: ads_storage::AdData) -> Result<(), String> {
-    // Validate interval: allow 0 to mean "use global interval"; otherwise enforce minimum 15 minutes
-    if ad.interval_minutes != 0 && ad.interval_minutes < 15 {
-        return Err(
-            "Interval must be at least 15 minutes or 0 to inherit global interval".to_string(),
-        );
+fn set_ad_enabled(id: String, enabled: bool) -> Result<ads_storage::AdData, String> {
+    let ad = ads_storage::set_ad_enabled(&id, enabled).map_err(|e| e.to_string())?;
+    if !enabled {
+        ads_runner::stop_ad(&id).map_err(|e| e.to_string())?;
     }
+    Ok(ad)
+}
+
+/// Duplicate an ad under a new id (name gets " (copy)"); the copy is not started.
+#[tauri::command]
+fn duplicate_ad(id: String) -> Result<ads_storage::AdData, String> {
+    ads_storage::duplicate_ad(&id).map_err(|e| e.to_string())
+}
+
+/// Export every ad preset as pretty-printed JSON. Tokens are stripped and proxy passwords masked
+/// unless `strip_tokens` is false.
+#[tauri::command]
+fn export_ads(strip_tokens: Option<bool>) -> Result<String, String> {
+    ads_storage::export_ads(strip_tokens.unwrap_or(true)).map_err(|e| e.to_string())
+}
+
+/// Tauri command to read ads.json as stored, for the advanced JSON editor. Tokens stay
+/// encrypted.
+#[tauri::command]
+fn get_ads_raw() -> Result<String, String> {
+    ads_storage::get_ads_raw().map_err(|e| e.to_string())
+}
+
+/// Tauri command to overwrite ads.json with edited JSON. The whole file is rejected (with the
+/// parse or validation error) unless every ad is valid; returns how many ads were saved.
+#[tauri::command]
+fn set_ads_raw(json: String) -> Result<usize, String> {
+    ads_storage::set_ads_raw(&json).map_err(|e| e.to_string())
+}
+
+/// Import ad presets from exported JSON, replacing the stored list or merging by id.
+/// Invalid entries are skipped and reported instead of failing the whole import.
+#[tauri::command]
+fn import_ads(json: String, merge: bool) -> Result<ads_storage::ImportReport, String> {
+    ads_storage::import_ads(&json, merge).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_ad(mut ad: ads_storage::AdData) -> Result<(), String> {
+    ad.request_tags = ads_storage::normalize_request_tags(&ad.request_tags)?;
+    ads_storage::validate_ad(&ad)?;
     ads_storage::save_ad(&ad).map_err(|e| e.to_string())
 }
 
@@ -148,41 +227,74 @@ fn get_ad(id: String) -> Result<Option<ads_storage::AdData>, String> {
 
 // ===== Ads runner commands =====
 
+/// Ads linked to a stored account post with that account's token unless they carry their own.
+fn apply_account_token(ad: &mut ads_storage::AdData) -> Result<(), String> {
+    let has_own_token = ad
+        .roli_verification
+        .as_ref()
+        .map(|t| !t.trim().is_empty())
+        .unwrap_or(false);
+    if !has_own_token {
+        if let Some(account_id) = ad.account_id {
+            match auth_storage::get_account(account_id) {
+                Ok(Some(account)) => ad.roli_verification = account.roli_verification,
+                Ok(None) => {
+                    log::warn!("ad {} references unknown account {}", ad.id, account_id);
+                    return Err(format!("Account {} is not stored", account_id));
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
-fn start_ad(
+async fn start_ad(
     window: tauri::Window,
     id: String,
     interval_minutes: Option<u64>,
-) -> Result<(), String> {
+    verify_ownership: Option<bool>,
+) -> Result<ads_runner::StartOutcome, String> {
     // use the top-level logger
 
     let ad_opt = match ads_storage::get_ad(&id) {
         Ok(v) => v,
         Err(e) => {
             let msg = format!("start_ad: failed to read ad {} from storage: {}", id, e);
-            append_app_log(&msg);
+            log::error!("{}", msg);
             return Err(msg);
         }
     };
     let mut ad = ad_opt.ok_or_else(|| "Ad not found".to_string())?;
+    if !ad.enabled {
+        log::warn!("start_ad: ad {} is disabled", id);
+        return Err("This ad is disabled. Enable it before starting.".to_string());
+    }
+    apply_account_token(&mut ad)?;
+    let min_interval = ads_storage::min_interval_minutes();
     if let Some(i) = interval_minutes {
-        if i < 15 {
+        if i < min_interval {
             let msg = format!("start_ad: provided interval {} is below minimum", i);
-            append_app_log(&msg);
-            return Err("Interval must be at least 15 minutes".to_string());
+            log::warn!("{}", msg);
+            return Err(format!(
+                "Interval must be at least {}",
+                ads_storage::describe_min_interval()
+            ));
         }
         ad.interval_minutes = i;
     }
     // Validate stored ad interval as well (0 means inherit global interval)
-    if ad.interval_minutes != 0 && ad.interval_minutes < 15 {
+    if ad.interval_minutes != 0 && ad.interval_minutes < min_interval {
         let msg = format!(
-            "start_ad: stored ad interval {} is invalid (must be 0 or >=15)",
-            ad.interval_minutes
-        );
-        append_app_log(&msg);
-        return Err(
-            "Interval must be at least 15 minutes or 0 to inherit global interval".to_string(),
+            "start_ad: stored ad interval {} is invalid (must be 0 or >={})",
+            ad.interval_minutes, min_interval
         );
+        log::warn!("{}", msg);
+        return Err(format!(
+            "Interval must be at least {} or 0 to inherit global interval",
+            ads_storage::describe_min_interval()
+        ));
     }
     // If neither the stored ad interval nor the provided override are set,
     // we cannot start the runner because the frontend's global interval is required.
@@ -191,62 +303,368 @@ fn start_ad(
             "start_ad: no interval provided for ad {} (stored=0, no override)",
             id
         );
-        append_app_log(&msg);
+        log::warn!("{}", msg);
         return Err("No posting interval specified. Set a global interval in the Ads manager or provide an interval_minutes when starting the ad.".to_string());
     }
+    // Fail fast on a stale token instead of discovering it one interval later.
+    // A missing token is left to the runner, which prompts the UI for one.
+    if let Some(token) = ad
+        .roli_verification
+        .as_ref()
+        .filter(|t| !t.trim().is_empty())
+    {
+        match trade_ad::validate_roli_verification(token).await {
+            Ok(true) => {}
+            Ok(false) => {
+                let msg = format!("start_ad: roli_verification for ad {} is invalid", id);
+                log::warn!("{}", msg);
+                return Err("Your roli_verification token is invalid or expired. Update it before starting this ad.".to_string());
+            }
+            Err(e) => {
+                // network trouble shouldn't block starting; the runner reports post failures
+                log::warn!(
+                    "start_ad: could not validate roli_verification for ad {}: {}",
+                    id,
+                    e
+                );
+            }
+        }
+    }
+    // a bad token on one extra account only drops that account from this run
+    ads_runner::drop_invalid_targets(&window, &mut ad).await;
+    // Optional because inventory catalog ids don't always line up with what was offered
+    if verify_ownership.unwrap_or(false) {
+        if let Err(e) =
+            player_assets::verify_offer_ownership(ad.player_id, &ad.offer_item_ids).await
+        {
+            log::warn!("start_ad: ownership check failed for ad {}: {}", id, e);
+            return Err(e);
+        }
+    }
     match ads_runner::start_ad(ad, window, interval_minutes) {
-        Ok(()) => Ok(()),
+        Ok(outcome) => Ok(outcome),
         Err(e) => {
             let msg = format!("start_ad: runner failed to start ad {}: {}", id, e);
-            append_app_log(&msg);
+            log::error!("{}", msg);
             Err(e.to_string())
         }
     }
 }
 
+/// Outcome for one id of `start_ads` / `stop_ads`: "started", "already_running", "stopped" or
+/// "error".
+#[derive(Debug, Serialize)]
+struct BulkAdResult {
+    id: String,
+    status: &'static str,
+    error: Option<String>,
+}
+
+/// Tauri command to start several ads, each exactly as `start_ad` would. One ad failing (bad
+/// interval, expired token) doesn't stop the rest; every id gets its own result, in order.
+#[tauri::command]
+async fn start_ads(
+    window: tauri::Window,
+    ids: Vec<String>,
+    interval_minutes: Option<u64>,
+) -> Vec<BulkAdResult> {
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        let result = start_ad(window.clone(), id.clone(), interval_minutes, None).await;
+        results.push(match result {
+            Ok(ads_runner::StartOutcome::Started) => BulkAdResult {
+                id,
+                status: "started",
+                error: None,
+            },
+            Ok(ads_runner::StartOutcome::AlreadyRunning) => BulkAdResult {
+                id,
+                status: "already_running",
+                error: None,
+            },
+            Err(e) => BulkAdResult {
+                id,
+                status: "error",
+                error: Some(e),
+            },
+        });
+    }
+    results
+}
+
+/// Tauri command to stop several ads, with one result per id in order.
+#[tauri::command]
+fn stop_ads(ids: Vec<String>) -> Vec<BulkAdResult> {
+    ids.into_iter()
+        .map(|id| match ads_runner::stop_ad(&id) {
+            Ok(()) => BulkAdResult {
+                id,
+                status: "stopped",
+                error: None,
+            },
+            Err(e) => BulkAdResult {
+                id,
+                status: "error",
+                error: Some(e.to_string()),
+            },
+        })
+        .collect()
+}
+
+/// Tauri command to get how many ads may run at once (0 = unlimited).
+#[tauri::command]
+fn get_max_concurrent_ads() -> usize {
+    ads_runner::max_concurrent_ads()
+}
+
+/// Tauri command to cap how many ads may run at once; 0 removes the cap.
+#[tauri::command]
+fn set_max_concurrent_ads(limit: usize) -> Result<(), String> {
+    ads_runner::set_max_concurrent_ads(limit).map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the random wait range `[min, max]` in seconds between ads restarted
+/// at launch.
+#[tauri::command]
+fn get_restore_stagger_secs() -> (u64, u64) {
+    ads_runner::restore_stagger_secs()
+}
+
+/// Tauri command to change the wait range between ads restarted at launch; `[0, 0]` turns
+/// the stagger off. Takes effect the next time the app starts.
+#[tauri::command]
+fn set_restore_stagger_secs(min_secs: u64, max_secs: u64) -> Result<(), String> {
+    ads_runner::set_restore_stagger_secs(min_secs, max_secs).map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the seconds until `player_id` can post another trade ad (0 = now).
+/// Inferred from this session's createad responses, since Rolimons doesn't expose the
+/// cooldown directly.
+#[tauri::command]
+fn get_post_cooldown(player_id: u64) -> u64 {
+    trade_ad::post_cooldown_remaining(player_id)
+}
+
+/// Tauri command to post a stored ad once, right now, without scheduling it. Uses the same
+/// token, proxy and validation as the runner and returns the outcome directly.
+#[tauri::command]
+async fn post_ad_once(
+    app: tauri::AppHandle,
+    id: String,
+) -> Result<trade_ad::PostedTradeAd, String> {
+    let mut ad = ads_storage::get_ad(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Ad not found".to_string())?;
+    ads_storage::validate_ad(&ad)?;
+    apply_account_token(&mut ad)?;
+    let token = ad
+        .roli_verification
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| "Roli verification cookie is required".to_string())?;
+    let client = ads_runner::ad_post_client(&ad)
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(http::client);
+    let offer_item_ids = ads_runner::offer_ids_for(&app, &ad).await;
+    match trade_ad::post_trade_ad_with_client(
+        &client,
+        &token,
+        ad.player_id,
+        offer_item_ids,
+        ad.request_item_ids,
+        ad.request_tags,
+        trade_ad::PostOptions::default(),
+    )
+    .await
+    {
+        Ok(posted) => {
+            log::info!("post_ad_once: ad {} posted ({:?})", id, posted.ad_id);
+            Ok(posted)
+        }
+        Err(e) => {
+            log::warn!("post_ad_once: ad {} failed to post: {}", id, e);
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Tauri command to post a stored ad once at `unix_time` (seconds) instead of on an interval.
+/// The pending post is listed as "scheduled" and can be cancelled with `stop_ad`.
+#[tauri::command]
+fn schedule_ad_at(window: tauri::Window, id: String, unix_time: u64) -> Result<(), String> {
+    let mut ad = ads_storage::get_ad(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Ad not found".to_string())?;
+    if !ad.enabled {
+        return Err("This ad is disabled. Enable it before scheduling.".to_string());
+    }
+    ads_storage::validate_ad(&ad)?;
+    apply_account_token(&mut ad)?;
+    ads_runner::schedule_ad_once(ad, window, unix_time).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn stop_ad(id: String) -> Result<(), String> {
     ads_runner::stop_ad(&id).map_err(|e| e.to_string())
 }
 
+/// Tauri command to drop a stuck runner from the running set immediately (it is still told
+/// to stop). The old task may linger until its in-flight request completes, then exits on its own.
+/// Returns whether the ad was running.
+#[tauri::command]
+fn force_remove_ad(id: String) -> Result<bool, String> {
+    ads_runner::force_remove_ad(&id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn list_running_ads() -> Result<Vec<String>, String> {
     ads_runner::list_running_ads().map_err(|e| e.to_string())
 }
 
+/// Stop all running ads, returning how many were stopped.
+#[tauri::command]
+fn stop_all_ads() -> Result<usize, String> {
+    ads_runner::stop_all_ads().map_err(|e| e.to_string())
+}
+
+/// List running ads together with their runner status (running/paused).
+#[tauri::command]
+fn list_running_ads_detailed() -> Result<Vec<ads_runner::RunningAd>, String> {
+    ads_runner::list_running_ads_detailed().map_err(|e| e.to_string())
+}
+
+/// Lifetime post statistics for an ad (total posts and last post time), kept across restarts.
+#[tauri::command]
+fn get_ad_stats(id: String) -> Result<ad_stats_storage::AdStats, String> {
+    ad_stats_storage::get_stats(&id).map_err(|e| e.to_string())
+}
+
+/// Get the runner status of an ad, including the absolute time of its next post.
+#[tauri::command]
+fn get_ad_status(id: String) -> Result<ads_runner::AdStatus, String> {
+    ads_runner::get_ad_status(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn pause_ad(id: String) -> Result<(), String> {
+    ads_runner::pause_ad(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn resume_ad(id: String) -> Result<(), String> {
+    ads_runner::resume_ad(&id).map_err(|e| e.to_string())
+}
+
+/// Check whether a roli_verification token is still accepted by Rolimons
+#[tauri::command]
+async fn validate_roli_verification(roli_verification: String) -> Result<bool, String> {
+    trade_ad::validate_roli_verification(&roli_verification)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to list the trade ads `player_id` has live on Rolimons, newest first (`ads`
+/// is empty when there are none). With `ad_id`, `duplicate_ad_id` is the live ad offering and
+/// requesting the same items as that stored ad, so the UI can warn before posting it again.
+#[tauri::command]
+async fn get_player_trade_ads(
+    player_id: u64,
+    ad_id: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let stored = match ad_id {
+        Some(id) => Some(
+            ads_storage::get_ad(&id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "Ad not found".to_string())?,
+        ),
+        None => None,
+    };
+    let ads = trade_ad::get_player_trade_ads(player_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let duplicate_ad_id = stored.and_then(|ad| {
+        ads.iter()
+            .find(|live| {
+                live.same_items(&ad.offer_item_ids, &ad.request_item_ids, &ad.request_tags)
+            })
+            .map(|live| live.ad_id)
+    });
+    Ok(serde_json::json!({ "ads": ads, "duplicate_ad_id": duplicate_ad_id }))
+}
+
 /// Tauri command to validate request tags
 #[tauri::command]
 fn validate_request_tag(tag: String) -> bool {
-    matches!(
-        tag.to_lowercase().as_str(),
-        "any"
-            | "demand"
-            | "rares"
-            | "robux"
-            | "upgrade"
-            | "downgrade"
-            | "rap"
-            | "wishlist"
-            | "projecteds"
-            | "adds"
-    )
+    ads_storage::is_known_request_tag(&tag)
 }
 
 /// Tauri command to get available request tags
 #[tauri::command]
 fn get_available_tags() -> Vec<String> {
-    vec![
-        "any".to_string(),
-        "demand".to_string(),
-        "rares".to_string(),
-        "robux".to_string(),
-        "upgrade".to_string(),
-        "downgrade".to_string(),
-        "rap".to_string(),
-        "wishlist".to_string(),
-        "projecteds".to_string(),
-        "adds".to_string(),
-    ]
+    ads_storage::REQUEST_TAGS
+        .iter()
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// Display label and tooltip text for a tag from `ads_storage::REQUEST_TAGS`. A tag added
+/// there without text here still shows up, labelled with its own name.
+fn tag_text(tag: &str) -> (&str, &'static str) {
+    match tag {
+        "any" => ("Any", "Open to any offer."),
+        "demand" => ("Demand", "Looking for items with good demand."),
+        "rares" => (
+            "Rares",
+            "Looking for rare items (few copies in circulation).",
+        ),
+        "robux" => ("Robux", "Willing to take Robux as part of the deal."),
+        "upgrade" => (
+            "Upgrade",
+            "Trading several items for fewer, higher-valued ones.",
+        ),
+        "downgrade" => (
+            "Downgrade",
+            "Trading one item for several lower-valued ones.",
+        ),
+        "rap" => (
+            "RAP",
+            "Judging offers by recent average price rather than value.",
+        ),
+        "wishlist" => ("Wishlist", "Looking for items on your Rolimons wishlist."),
+        "projecteds" => (
+            "Projecteds",
+            "Willing to take projected items (RAP inflated above value).",
+        ),
+        "adds" => (
+            "Adds",
+            "Want small extra items added on top of the main offer.",
+        ),
+        other => (other, ""),
+    }
+}
+
+/// A request tag with the text the UI shows for it.
+#[derive(Serialize)]
+struct TagInfo {
+    tag: String,
+    label: String,
+    description: String,
+}
+
+/// Tauri command to get the request tags with a display label and a tooltip description each
+#[tauri::command]
+fn get_available_tags_detailed() -> Vec<TagInfo> {
+    ads_storage::REQUEST_TAGS
+        .iter()
+        .map(|tag| {
+            let (label, description) = tag_text(tag);
+            TagInfo {
+                tag: tag.to_string(),
+                label: label.to_string(),
+                description: description.to_string(),
+            }
+        })
+        .collect()
 }
 
 // ===== Auth Commands =====
@@ -270,10 +688,64 @@ async fn get_user_details(user_id: u64) -> Result<roblox_user::UserDetails, Stri
         .map_err(|e| e.to_string())
 }
 
+/// Get basic details for many users in one batched request (chunks of 100 ids)
+#[tauri::command]
+async fn get_users_details(ids: Vec<u64>) -> Result<Vec<roblox_user::UserDetails>, String> {
+    roblox_user::get_users_details(ids)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Resolve exact usernames to user ids (unknown names are left out)
+#[tauri::command]
+async fn resolve_usernames(
+    usernames: Vec<String>,
+) -> Result<Vec<roblox_user::ResolvedUser>, String> {
+    roblox_user::resolve_usernames(usernames)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Generate a random verification code (5-10 words)
 #[tauri::command]
 fn generate_verification_code() -> String {
-    verification::generate_verification_code()
+    let code = verification::generate_verification_code();
+    verification::issue_code(&code);
+    code
+}
+
+/// Issue a fresh verification code, invalidating the previously generated one
+#[tauri::command]
+fn reissue_verification_code() -> String {
+    generate_verification_code()
+}
+
+/// Get how long a generated verification code stays valid, in seconds
+#[tauri::command]
+fn get_verification_code_ttl() -> u64 {
+    verification::code_ttl_secs()
+}
+
+/// Set how long a generated verification code stays valid, in seconds
+#[tauri::command]
+fn set_verification_code_ttl(secs: u64) -> Result<(), String> {
+    if secs == 0 {
+        return Err("Verification code lifetime must be at least 1 second".to_string());
+    }
+    verification::set_code_ttl_secs(secs);
+    Ok(())
+}
+
+/// Generate a verification code with an exact word count, optionally from a custom word list
+#[tauri::command]
+fn generate_custom_verification_code(
+    word_count: usize,
+    words: Option<Vec<String>>,
+) -> Result<String, String> {
+    let code = verification::generate_verification_code_with(word_count, words)
+        .map_err(|e| e.to_string())?;
+    verification::issue_code(&code);
+    Ok(code)
 }
 
 /// Verify a user by checking if their Roblox profile description contains the verification code
@@ -284,13 +756,31 @@ async fn verify_user(
     display_name: String,
     verification_code: String,
 ) -> Result<bool, String> {
+    match verification::issued_code_status(&verification_code) {
+        verification::CodeStatus::Valid => {}
+        verification::CodeStatus::Expired => {
+            return Err(
+                "This verification code has expired. Generate a new code and update your profile."
+                    .to_string(),
+            );
+        }
+        verification::CodeStatus::Unknown => {
+            return Err(
+                "This verification code is no longer valid. Generate a new code and update your profile."
+                    .to_string(),
+            );
+        }
+    }
+
     let details = roblox_user::get_user_details(user_id)
         .await
         .map_err(|e| e.to_string())?;
 
-    let verified = details.description.contains(&verification_code);
+    let verified =
+        verification::description_contains_code(&details.description, &verification_code);
 
     if verified {
+        verification::consume_code();
         // Save auth data on successful verification
         let auth = auth_storage::AuthData {
             user_id,
@@ -304,6 +794,27 @@ async fn verify_user(
     Ok(verified)
 }
 
+/// Re-check that an already verified user's profile description still contains their code,
+/// e.g. periodically to confirm they still own the account. Unlike `verify_user` this doesn't
+/// require a freshly issued code and never touches stored auth.
+#[tauri::command]
+async fn recheck_verification(user_id: u64, verification_code: String) -> Result<bool, String> {
+    if verification_code.trim().is_empty() {
+        return Err("No verification code to check".to_string());
+    }
+    let details = roblox_user::get_user_details(user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let present = verification::description_contains_code(&details.description, &verification_code);
+    if !present {
+        log::warn!(
+            "recheck_verification: code no longer in profile of user {}",
+            user_id
+        );
+    }
+    Ok(present)
+}
+
 /// Save authentication data
 #[tauri::command]
 fn save_auth_data(
@@ -330,25 +841,74 @@ fn load_auth_data() -> Result<Option<auth_storage::AuthData>, String> {
 /// Update the roli_verification token for the logged-in user
 #[tauri::command]
 fn update_roli_verification(roli_verification: String) -> Result<(), String> {
+    let roli_verification = normalize_and_validate_cookie(roli_verification)?;
     auth_storage::update_roli_verification(roli_verification).map_err(|e| e.to_string())
 }
 
+/// Tauri command to clean up a pasted _RoliVerification (name prefix, whole cookie header,
+/// quotes, trailing `;`) and check it looks like a token. Returns the bare value.
+#[tauri::command]
+fn normalize_and_validate_cookie(raw: String) -> Result<String, String> {
+    cookie::normalize_roli_verification(&raw).map_err(|e| e.to_string())
+}
+
+/// List all stored accounts
+#[tauri::command]
+fn list_accounts() -> Result<Vec<auth_storage::AuthData>, String> {
+    auth_storage::list_accounts().map_err(|e| e.to_string())
+}
+
+/// Switch the active account to a previously stored user
+#[tauri::command]
+fn set_active_account(user_id: u64) -> Result<(), String> {
+    auth_storage::set_active_account(user_id).map_err(|e| e.to_string())
+}
+
 /// Logout (clear auth data)
 #[tauri::command]
 fn logout() -> Result<(), String> {
     auth_storage::clear_auth().map_err(|e| e.to_string())
 }
 
+/// Read the _RoliVerification cookie from a local browser profile.
+/// `browser` selects the source: "chrome" (default) or "firefox".
+#[tauri::command]
+async fn extract_roli_verification(browser: Option<String>) -> Result<String, String> {
+    let browser = browser
+        .unwrap_or_else(|| "chrome".to_string())
+        .to_lowercase();
+    let source = browser.clone();
+    // browser DB copies retry with blocking sleeps, so keep them off the async workers
+    let result = tauri::async_runtime::spawn_blocking(move || match source.as_str() {
+        "chrome" => {
+            let user_data_dir = cookie::get_chrome_user_data_dir()?;
+            let cookies_db = cookie::resolve_cookies_db(&user_data_dir, None)?;
+            cookie::extract_roli_verification_from_chrome(&user_data_dir, &cookies_db)
+        }
+        "firefox" => cookie::extract_roli_verification_from_firefox(),
+        other => Err(anyhow::anyhow!("Unsupported browser: {}", other)),
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    result.map_err(|e| {
+        log::warn!(
+            "extract_roli_verification: {} extraction failed: {}",
+            browser,
+            e
+        );
+        e.to_string()
+    })
+}
+
 /// Save a global roli_verification token for the current user or create a minimal auth entry.
 #[tauri::command]
 fn save_global_verification(roli_verification: String) -> Result<(), String> {
+    let roli_verification = normalize_and_validate_cookie(roli_verification)?;
     match auth_storage::load_auth() {
         Ok(Some(mut a)) => {
             a.roli_verification = Some(roli_verification.clone());
             auth_storage::save_auth(&a).map_err(|e| e.to_string())?;
-            append_app_log(&format!(
-                "save_global_verification: updated existing auth roli_verification"
-            ));
+            log::info!("save_global_verification: updated existing auth roli_verification");
             Ok(())
         }
         Ok(None) => {
@@ -360,86 +920,276 @@ fn save_global_verification(roli_verification: String) -> Result<(), String> {
                 roli_verification: Some(roli_verification.clone()),
             };
             auth_storage::save_auth(&auth).map_err(|e| e.to_string())?;
-            append_app_log(&format!(
-                "save_global_verification: created auth with roli_verification"
-            ));
+            log::info!("save_global_verification: created auth with roli_verification");
             Ok(())
         }
         Err(e) => Err(e.to_string()),
     }
 }
 
-/// Tauri command: fetch the full catalog for a given search term (no caching)
+// In-flight get_full_catalog calls: fetch id -> cancel sender, like the ad runners' handles.
+static CATALOG_FETCHES: once_cell::sync::Lazy<
+    std::sync::Mutex<HashMap<u64, tokio::sync::oneshot::Sender<()>>>,
+> = once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+static CATALOG_FETCH_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+/// Items converted between cancellation checks in get_full_catalog.
+const CATALOG_PARSE_CHUNK: usize = 2_000;
+
+/// Drops a fetch's registration however get_full_catalog returns.
+struct CatalogFetchGuard(u64);
+
+impl Drop for CatalogFetchGuard {
+    fn drop(&mut self) {
+        CATALOG_FETCHES.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Tauri command: fetch the full catalog for a given search term (served from the item cache).
+/// `cancel_full_catalog` stops it early; the result then has `"cancelled": true` and only the
+/// items converted so far.
 #[tauri::command]
 async fn get_full_catalog(search: Option<String>) -> Result<serde_json::Value, String> {
     let start = std::time::Instant::now();
-    append_app_log(&format!(
-        "get_full_catalog: starting fetch for search={:?}",
-        search
-    ));
+    log::debug!("get_full_catalog: starting fetch for search={:?}", search);
+    let (tx, mut rx) = tokio::sync::oneshot::channel::<()>();
+    let fetch_id = CATALOG_FETCH_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    CATALOG_FETCHES.lock().unwrap().insert(fetch_id, tx);
+    let _guard = CatalogFetchGuard(fetch_id);
+
     // Cap the fetch to a reasonable upper bound to avoid parsing enormous JSON blobs.
     // If you really need everything, implement paged/batched fetching instead.
     const MAX_FULL_CATALOG: usize = 100_000;
-    match trade_ad::fetch_item_details(1usize, MAX_FULL_CATALOG, search.clone()).await {
+    let filter = trade_ad::ItemFilter::default();
+    let fetched = tokio::select! {
+        res = trade_ad::fetch_item_details(
+            1usize,
+            MAX_FULL_CATALOG,
+            search.clone(),
+            trade_ad::MatchMode::Contains,
+            &filter,
+            trade_ad::ItemSort::Value,
+            true,
+            trade_ad::ValueSource::default(),
+        ) => res,
+        _ = &mut rx => {
+            log::info!("get_full_catalog: cancelled after {:?} while fetching", start.elapsed());
+            return Ok(serde_json::json!({"items": [], "total": 0, "cancelled": true}));
+        }
+    };
+    match fetched {
         Ok((items, _total)) => {
-            append_app_log(&format!(
+            log::debug!(
                 "get_full_catalog: fetched {} items in {:?}",
                 items.len(),
                 start.elapsed()
-            ));
-            // convert ItemInfo -> JsonValue and filter rap > 0
+            );
+            // convert ItemInfo -> JsonValue and filter rap > 0, checking for a cancel between chunks
             let mut filtered: Vec<serde_json::Value> = Vec::with_capacity(items.len());
-            for it in items.into_iter() {
-                if it.rap > 0 {
-                    if let Ok(v) = serde_json::to_value(&it) {
-                        filtered.push(v);
+            let mut cancelled = false;
+            for chunk in items.chunks(CATALOG_PARSE_CHUNK) {
+                if rx.try_recv().is_ok() {
+                    cancelled = true;
+                    break;
+                }
+                for it in chunk {
+                    if it.rap > 0 {
+                        if let Ok(v) = serde_json::to_value(it) {
+                            filtered.push(v);
+                        }
                     }
                 }
+                // let a pending cancel_full_catalog call run before the next chunk
+                tokio::task::yield_now().await;
             }
             let t = filtered.len();
-            append_app_log(&format!(
-                "get_full_catalog: filtered to {} items, total duration {:?}",
-                t,
-                start.elapsed()
-            ));
-            Ok(serde_json::json!({"items": filtered, "total": t}))
+            if cancelled {
+                log::info!(
+                    "get_full_catalog: cancelled after {:?} with {} items converted",
+                    start.elapsed(),
+                    t
+                );
+            } else {
+                log::debug!(
+                    "get_full_catalog: filtered to {} items, total duration {:?}",
+                    t,
+                    start.elapsed()
+                );
+            }
+            Ok(serde_json::json!({"items": filtered, "total": t, "cancelled": cancelled}))
         }
         Err(e) => {
-            append_app_log(&format!(
-                "get_full_catalog: error after {:?}: {}",
-                start.elapsed(),
-                e
-            ));
+            log::error!("get_full_catalog: error after {:?}: {}", start.elapsed(), e);
             Err(e.to_string())
         }
     }
 }
 
-/// Tauri command: fetch a player's inventory and enrich with catalog metadata
+/// Tauri command to cancel every in-flight `get_full_catalog` call. Returns how many were
+/// signalled.
+#[tauri::command]
+fn cancel_full_catalog() -> usize {
+    let handles: Vec<_> = CATALOG_FETCHES.lock().unwrap().drain().collect();
+    let n = handles
+        .into_iter()
+        .filter_map(|(_, tx)| tx.send(()).ok())
+        .count();
+    log::info!("cancel_full_catalog: signalled {} fetch(es)", n);
+    n
+}
+
+/// Tauri command: fetch a player's inventory and enrich with catalog metadata.
+/// Alongside `items` the result carries `total_value` and `total_rap`, summed over the
+/// `counted` items that have catalog metadata. With `group_by_catalog` set, instances of the
+/// same item are collapsed into one entry with `quantity` and `instance_ids`; with
+/// `tradable_only` set, instances on hold are left out (totals included).
 #[tauri::command]
 async fn fetch_enriched_inventory(
     app: tauri::AppHandle,
     player_id: Option<u64>,
     playerId: Option<u64>,
     user_id: Option<String>,
+    force_refresh: Option<bool>,
+    group_by_catalog: Option<bool>,
+    tradable_only: Option<bool>,
 ) -> Result<serde_json::Value, String> {
-    let start = std::time::Instant::now();
     // Accept either `player_id` (snake_case) or `playerId` (camelCase) from the frontend.
     let pid = player_id
         .or(playerId)
         .ok_or_else(|| "player_id is required".to_string())?;
-    append_app_log(&format!(
-        "fetch_enriched_inventory: starting for player {}",
-        pid
-    ));
-    // call existing player assets inventory fetch
-    let inv = crate::player_assets::fetch_player_inventory(pid)
+    let mut out = enrich_inventory(&app, pid, user_id, force_refresh, tradable_only).await?;
+    // grouping only reshapes the response; detection and totals above work per instance
+    if group_by_catalog.unwrap_or(false) {
+        if let Some(JsonValue::Array(items)) = out.get_mut("items").map(JsonValue::take) {
+            out["items"] = JsonValue::Array(player_assets::group_by_catalog(items));
+        }
+    }
+    Ok(out)
+}
+
+/// Snapshot `player_id`'s current inventory (copies owned per catalog id), to pass back to
+/// `diff_inventory` later.
+#[tauri::command]
+async fn snapshot_inventory(player_id: u64) -> Result<player_assets::InventorySnapshot, String> {
+    player_assets::snapshot_inventory(player_id).await
+}
+
+/// Compare `player_id`'s current inventory with an earlier `snapshot_inventory` result.
+/// Returns `added`, `removed` and `unchanged` catalog ids with their name, value and rap,
+/// plus the current `snapshot` to diff against next time.
+#[tauri::command]
+async fn diff_inventory(player_id: u64, snapshot_json: String) -> Result<JsonValue, String> {
+    let before: player_assets::InventorySnapshot = serde_json::from_str(&snapshot_json)
+        .map_err(|e| format!("Invalid inventory snapshot: {}", e))?;
+    if before.player_id != player_id {
+        return Err(format!(
+            "Snapshot is for player {}, not {}",
+            before.player_id, player_id
+        ));
+    }
+    let after = player_assets::snapshot_inventory(player_id).await?;
+    let diff = player_assets::diff_snapshots(&before, &after);
+
+    let mut ids: Vec<u64> = before
+        .items
+        .keys()
+        .chain(after.items.keys())
+        .copied()
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let catalog: HashMap<u64, _> = trade_ad::fetch_items_by_ids(ids, trade_ad::ThumbSize::Small)
         .await
-        .map_err(|e| e.to_string())?;
-    append_app_log(&format!(
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|item| (item.id, item))
+        .collect();
+    let describe = |changes: Vec<player_assets::InventoryChange>| -> Vec<JsonValue> {
+        changes
+            .into_iter()
+            .map(|c| {
+                let meta = catalog.get(&c.catalog_id);
+                serde_json::json!({
+                    "catalog_id": c.catalog_id,
+                    "quantity": c.quantity,
+                    "name": meta.map(|m| m.name.clone()),
+                    "value": meta.map(|m| m.value),
+                    "rap": meta.map(|m| m.rap),
+                    "thumbnail": meta.and_then(|m| m.thumbnail.clone()),
+                })
+            })
+            .collect()
+    };
+
+    Ok(serde_json::json!({
+        "added": describe(diff.added),
+        "removed": describe(diff.removed),
+        "unchanged": describe(diff.unchanged),
+        "snapshot": after,
+    }))
+}
+
+/// Catalog ids auto-offer would put up for `player_id` right now: its `count` best inventory
+/// items by the configured metric.
+async fn select_auto_offer(
+    app: &tauri::AppHandle,
+    player_id: u64,
+    config: &ads_storage::AutoOfferConfig,
+) -> Result<Vec<u64>, String> {
+    let inv = enrich_inventory(app, player_id, None, None, Some(config.tradable_only)).await?;
+    let items = inv
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    Ok(player_assets::top_items_by(
+        &items,
+        config.metric.field(),
+        config.count,
+    ))
+}
+
+/// Tauri command to list the items an ad with `config` would auto-offer for `player_id` right
+/// now, in offer order, without posting anything. Same filtering and ranking as the runner.
+#[tauri::command]
+async fn preview_auto_offer(
+    app: tauri::AppHandle,
+    player_id: u64,
+    config: ads_storage::AutoOfferConfig,
+) -> Result<serde_json::Value, String> {
+    config.validate()?;
+    let ids = select_auto_offer(&app, player_id, &config).await?;
+    match trade_ad::fetch_items_by_ids(ids, trade_ad::ThumbSize::Small).await {
+        Ok(items) => Ok(serde_json::json!({"items": items})),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Fetch and enrich `pid`'s inventory, then run value-change detection (and notifications)
+/// for `user_id` when given. Shared by `fetch_enriched_inventory` and the background watcher.
+async fn enrich_inventory(
+    app: &tauri::AppHandle,
+    pid: u64,
+    user_id: Option<String>,
+    force_refresh: Option<bool>,
+    tradable_only: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    let start = std::time::Instant::now();
+    log::debug!("fetch_enriched_inventory: starting for player {}", pid);
+    // call existing player assets inventory fetch, warming the catalog cache meanwhile so
+    // the lookup below doesn't add its download time on top
+    let (inv, warm) = tokio::join!(
+        crate::player_assets::fetch_player_inventory(pid, None, None, force_refresh, tradable_only),
+        trade_ad::warm_item_cache()
+    );
+    if let Err(e) = warm {
+        log::warn!("fetch_enriched_inventory: catalog prefetch failed: {}", e);
+    }
+    let inv = inv.map_err(|e| e.to_string())?;
+    log::debug!(
         "fetch_enriched_inventory: fetched inventory in {:?}",
         start.elapsed()
-    ));
+    );
     let items_arr = inv
         .get("items")
         .and_then(|v| v.as_array())
@@ -468,7 +1218,7 @@ async fn fetch_enriched_inventory(
 
     let mut catalog_map: HashMap<u64, JsonValue> = HashMap::new();
     if !missing.is_empty() {
-        match trade_ad::fetch_items_by_ids(missing.clone()).await {
+        match trade_ad::fetch_items_by_ids(missing.clone(), trade_ad::ThumbSize::Small).await {
             Ok(ci) => {
                 for item in ci {
                     let idv = item.id;
@@ -539,68 +1289,202 @@ async fn fetch_enriched_inventory(
 
     // Check for value changes and send notifications if enabled
     if let Some(uid) = user_id {
-        match notification_settings::get_notification_enabled(&uid) {
-            Ok(true) => {
-                let changes = value_change_detector::detect_value_changes(&enriched);
+        let threshold =
+            notification_settings::get_value_change_threshold(&uid).unwrap_or_else(|e| {
+                log::warn!("Failed to read value change threshold: {}", e);
+                notification_settings::DEFAULT_VALUE_CHANGE_THRESHOLD
+            });
+        // checked before the channel so the quiet period starts with the first check either way
+        let quiet = notification_settings::in_quiet_period(&uid).unwrap_or_else(|e| {
+            log::warn!("Failed to read quiet period: {}", e);
+            false
+        });
+        match notification_settings::get_notification_channel(&uid) {
+            Ok(channel) if channel != notification_settings::NotificationChannel::None => {
+                let metric =
+                    notification_settings::get_notification_metric(&uid).unwrap_or_default();
+                let direction =
+                    notification_settings::get_notification_direction(&uid).unwrap_or_default();
+                let webhook_url = if channel.includes_webhook() {
+                    notification_settings::get_discord_webhook_url(&uid).unwrap_or_else(|e| {
+                        log::warn!("Failed to read Discord webhook URL: {}", e);
+                        None
+                    })
+                } else {
+                    None
+                };
+                let changes =
+                    value_change_detector::detect_value_changes(&uid, &enriched, threshold);
                 for change in changes {
-                    let body = format!(
-                        "Item: {}\nOld Value: {}\nNew Value: {}",
-                        change.name, change.old_value, change.new_value
-                    );
-
-                    match tauri_plugin_notification::NotificationExt::notification(&app)
-                        .builder()
-                        .title("Item Value Changed")
-                        .body(&body)
-                        .show()
-                    {
-                        Ok(_) => {
-                            if let Some(thumbnail_url) = &change.thumbnail {
-                                append_app_log(&format!(
-                                    "Value change notification sent for {} (thumbnail: {})",
-                                    change.name, thumbnail_url
-                                ));
-                            } else {
-                                append_app_log(&format!(
-                                    "Value change notification sent for {} (no thumbnail)",
-                                    change.name
-                                ));
+                    // The detector reports every change so its cache stays accurate;
+                    // only what gets shown is filtered here.
+                    let show_value = metric.includes_value()
+                        && change.value_changed()
+                        && direction.allows(change.old_value, change.new_value);
+                    let show_rap = metric.includes_rap()
+                        && change.rap_changed()
+                        && direction.allows(change.old_rap, change.new_rap);
+                    if !show_value && !show_rap {
+                        continue;
+                    }
+
+                    let mut body = format!("Item: {}", change.name);
+                    if show_value {
+                        body.push_str(&format!(
+                            "\nOld Value: {}\nNew Value: {}",
+                            change.old_value, change.new_value
+                        ));
+                    }
+                    if show_rap {
+                        body.push_str(&format!(
+                            "\nOld RAP: {}\nNew RAP: {}",
+                            change.old_rap, change.new_rap
+                        ));
+                    }
+                    let title = match (show_value, show_rap) {
+                        (true, true) => "Item Value and RAP Changed",
+                        (false, true) => "Item RAP Changed",
+                        _ => "Item Value Changed",
+                    };
+
+                    // keep a record even if the OS notification fails, so nothing is missed
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0);
+                    if let Err(e) = notification_settings::record_notification(&uid, &change, now) {
+                        log::warn!("Failed to record notification for {}: {}", change.name, e);
+                    }
+                    if quiet {
+                        log::info!(
+                            "Value change for {} recorded without notifying (quiet period)",
+                            change.name
+                        );
+                        continue;
+                    }
+
+                    if channel.includes_native() {
+                        match tauri_plugin_notification::NotificationExt::notification(app)
+                            .builder()
+                            .title(title)
+                            .body(&body)
+                            .show()
+                        {
+                            Ok(_) => {
+                                if let Some(thumbnail_url) = &change.thumbnail {
+                                    log::info!(
+                                        "Value change notification sent for {} (thumbnail: {})",
+                                        change.name,
+                                        thumbnail_url
+                                    );
+                                } else {
+                                    log::info!(
+                                        "Value change notification sent for {} (no thumbnail)",
+                                        change.name
+                                    );
+                                }
+                            }
+                            Err(e) => {
+                                log::warn!(
+                                    "Failed to send notification for {}: {}",
+                                    change.name,
+                                    e
+                                );
                             }
-                        }
-                        Err(e) => {
-                            append_app_log(&format!(
-                                "Failed to send notification for {}: {}",
-                                change.name, e
-                            ));
                         }
                     }
+
+                    if let Some(url) = webhook_url.clone() {
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = webhook::send_value_change(
+                                &url, &change, title, show_value, show_rap,
+                            )
+                            .await
+                            {
+                                log::warn!(
+                                    "Failed to send Discord webhook for {}: {}",
+                                    change.name,
+                                    e
+                                );
+                            }
+                        });
+                    }
                 }
             }
-            Ok(false) => {
+            Ok(_) => {
                 // Notifications disabled, still update cache but don't notify
-                let _ = value_change_detector::detect_value_changes(&enriched);
+                let _ = value_change_detector::detect_value_changes(&uid, &enriched, threshold);
             }
             Err(e) => {
-                append_app_log(&format!("Failed to check notification settings: {}", e));
+                log::warn!("Failed to check notification settings: {}", e);
             }
         }
     }
 
-    append_app_log(&format!(
-        "fetch_enriched_inventory: returning {} enriched items, total duration {:?}",
+    // totals over the items that got catalog metadata; the rest have no value/rap to add
+    let mut total_value: u64 = 0;
+    let mut total_rap: u64 = 0;
+    let mut counted: usize = 0;
+    for it in &enriched {
+        let value = it.get("value").and_then(|v| v.as_u64());
+        let rap = it.get("rap").and_then(|v| v.as_u64());
+        if value.is_none() && rap.is_none() {
+            continue;
+        }
+        total_value += value.unwrap_or(0);
+        total_rap += rap.unwrap_or(0);
+        counted += 1;
+    }
+
+    log::debug!(
+        "fetch_enriched_inventory: returning {} enriched items ({} counted), total duration {:?}",
         enriched.len(),
+        counted,
         start.elapsed()
-    ));
-    Ok(serde_json::json!({"items": enriched}))
+    );
+    Ok(serde_json::json!({
+        "items": enriched,
+        "total_value": total_value,
+        "total_rap": total_rap,
+        "counted": counted,
+    }))
+}
+
+/// Tauri command to refresh `player_id`'s inventory in the background every
+/// `interval_minutes` so value-change notifications for `user_id` fire without the inventory
+/// tab open. Starting again for the same user replaces the previous watcher.
+#[tauri::command]
+fn start_value_watch(
+    app: tauri::AppHandle,
+    user_id: String,
+    player_id: u64,
+    interval_minutes: u64,
+) -> Result<(), String> {
+    value_watch::start_value_watch(app, user_id, player_id, interval_minutes)
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to stop the user's background inventory watcher. Returns whether one was
+/// running.
+#[tauri::command]
+fn stop_value_watch(user_id: String) -> bool {
+    value_watch::stop_value_watch(&user_id)
+}
+
+/// Tauri command to list the active background inventory watchers
+#[tauri::command]
+fn list_value_watches() -> Vec<value_watch::ValueWatch> {
+    value_watch::list_value_watches()
 }
 
 /// Wrapper Tauri command to expose thumbnail fetching for specific IDs.
-/// The actual logic lives in `thumbnails::fetch_thumbnails_for_ids_cmd`.
+/// `size` defaults to small. The actual logic lives in `thumbnails::fetch_thumbnails_for_ids_cmd`.
 #[tauri::command]
 async fn fetch_thumbnails_for_ids_cmd(
     ids: Vec<u64>,
+    size: Option<trade_ad::ThumbSize>,
 ) -> Result<std::collections::HashMap<String, String>, String> {
-    thumbnails::fetch_thumbnails_for_ids_cmd(ids).await
+    thumbnails::fetch_thumbnails_for_ids_cmd(ids, size.unwrap_or_default()).await
 }
 
 /// Get notification enabled status for user
@@ -615,74 +1499,621 @@ fn set_notification_enabled(user_id: String, enabled: bool) -> Result<(), String
     notification_settings::set_notification_enabled(&user_id, enabled)
 }
 
+/// Get where value-change notifications are delivered for user: native, webhook, both or none
+#[tauri::command]
+fn get_notification_channel(
+    user_id: String,
+) -> Result<notification_settings::NotificationChannel, String> {
+    notification_settings::get_notification_channel(&user_id)
+}
+
+/// Set where value-change notifications are delivered for user
+#[tauri::command]
+fn set_notification_channel(
+    user_id: String,
+    channel: notification_settings::NotificationChannel,
+) -> Result<(), String> {
+    notification_settings::set_notification_channel(&user_id, channel)
+}
+
+/// Get the minimum percent value change that triggers a notification for user
+#[tauri::command]
+fn get_value_change_threshold(user_id: String) -> Result<f64, String> {
+    notification_settings::get_value_change_threshold(&user_id)
+}
+
+/// Set the minimum percent value change that triggers a notification for user
+#[tauri::command]
+fn set_value_change_threshold(user_id: String, percent: f64) -> Result<(), String> {
+    notification_settings::set_value_change_threshold(&user_id, percent)
+}
+
+/// Get the minutes after a session's first inventory check during which changes are only
+/// recorded to history, not notified
+#[tauri::command]
+fn get_quiet_period_minutes(user_id: String) -> Result<u64, String> {
+    notification_settings::get_quiet_period_minutes(&user_id)
+}
+
+/// Set the quiet period in minutes for user (0 turns it off)
+#[tauri::command]
+fn set_quiet_period_minutes(user_id: String, minutes: u64) -> Result<(), String> {
+    notification_settings::set_quiet_period_minutes(&user_id, minutes)
+}
+
+/// Get whether user is notified about value, rap or both
+#[tauri::command]
+fn get_notification_metric(
+    user_id: String,
+) -> Result<notification_settings::NotificationMetric, String> {
+    notification_settings::get_notification_metric(&user_id)
+}
+
+/// Set whether user is notified about value, rap or both
+#[tauri::command]
+fn set_notification_metric(
+    user_id: String,
+    metric: notification_settings::NotificationMetric,
+) -> Result<(), String> {
+    notification_settings::set_notification_metric(&user_id, metric)
+}
+
+/// Get whether user is notified about increases, decreases or both
+#[tauri::command]
+fn get_notification_direction(
+    user_id: String,
+) -> Result<notification_settings::NotificationDirection, String> {
+    notification_settings::get_notification_direction(&user_id)
+}
+
+/// Set whether user is notified about increases, decreases or both
+#[tauri::command]
+fn set_notification_direction(
+    user_id: String,
+    direction: notification_settings::NotificationDirection,
+) -> Result<(), String> {
+    notification_settings::set_notification_direction(&user_id, direction)
+}
+
+/// Get the Discord webhook URL for user, if any
+#[tauri::command]
+fn get_discord_webhook_url(user_id: String) -> Result<Option<String>, String> {
+    notification_settings::get_discord_webhook_url(&user_id)
+}
+
+/// Set the Discord webhook URL for user; an empty or missing URL clears it
+#[tauri::command]
+fn set_discord_webhook_url(user_id: String, url: Option<String>) -> Result<(), String> {
+    let url = url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    if let Some(u) = &url {
+        if !webhook::is_discord_webhook_url(u) {
+            return Err(
+                "Webhook URL must be a https://discord.com/api/webhooks/... URL".to_string(),
+            );
+        }
+    }
+    notification_settings::set_discord_webhook_url(&user_id, url.as_deref())
+}
+
+/// Tauri command to get the user's fired value-change notifications, newest first
+#[tauri::command]
+fn get_notification_history(
+    user_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<notification_settings::NotificationRecord>, String> {
+    notification_settings::get_notification_history(
+        &user_id,
+        limit.unwrap_or(notification_settings::DEFAULT_HISTORY_LIMIT),
+    )
+}
+
+/// Tauri command to delete the user's notification history, returning how many entries were
+/// removed
+#[tauri::command]
+fn clear_notification_history(user_id: String) -> Result<usize, String> {
+    notification_settings::clear_notification_history(&user_id)
+}
+
+/// Restart ads that were running when the app last exited. Ads that no longer exist
+/// (or fail validation) are dropped from the persisted set. Each ad after the first waits a
+/// random stagger so their immediate first posts don't all hit Rolimons at once.
+fn restore_running_ads(app: &tauri::AppHandle) {
+    let entries = match running_ads_storage::load_running_ads() {
+        Ok(v) => v,
+        Err(e) => {
+            log::warn!("restore_running_ads: failed to load: {}", e);
+            return;
+        }
+    };
+    if entries.is_empty() {
+        return;
+    }
+    let window = match app.get_webview_window("main") {
+        Some(w) => w.as_ref().window(),
+        None => {
+            log::warn!("restore_running_ads: main window not found, skipping restore");
+            return;
+        }
+    };
+    let stagger = ads_runner::restore_stagger_secs();
+    tauri::async_runtime::spawn(async move {
+        for (i, entry) in entries.into_iter().enumerate() {
+            if i > 0 {
+                let delay = ads_runner::restore_stagger_delay(stagger);
+                log::debug!(
+                    "restore_running_ads: waiting {:?} before ad {}",
+                    delay,
+                    entry.id
+                );
+                tokio::time::sleep(delay).await;
+            }
+            match start_ad(
+                window.clone(),
+                entry.id.clone(),
+                entry.interval_override,
+                None,
+            )
+            .await
+            {
+                Ok(ads_runner::StartOutcome::Started) => {
+                    log::info!("restore_running_ads: restarted ad {}", entry.id);
+                }
+                Ok(ads_runner::StartOutcome::AlreadyRunning) => {
+                    log::debug!("restore_running_ads: ad {} is already running", entry.id);
+                }
+                Err(e) => {
+                    log::warn!("restore_running_ads: dropping ad {}: {}", entry.id, e);
+                }
+            }
+        }
+        // rewrite the file from the live runner set so dropped ids don't linger
+        ads_runner::persist_running();
+    });
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    app_log::init();
     tauri::Builder::default()
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            ads_runner::seed_post_counts();
+            restore_running_ads(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             post_trade_ad,
+            preview_trade_ad,
+            post_ad_once,
+            get_post_cooldown,
+            schedule_ad_at,
+            validate_roli_verification,
+            get_player_trade_ads,
             // fetch catalog pages from Rolimons
             get_catalog_items,
             validate_request_tag,
             get_available_tags,
+            get_available_tags_detailed,
             // auth commands
             search_users,
             get_user_details,
+            get_users_details,
+            resolve_usernames,
             // rolimons players search + thumbnails
             rolimons_players::search_players_with_thumbnails,
             // player assets (inventory)
             player_assets::fetch_player_assets,
             player_assets::fetch_player_inventory,
+            snapshot_inventory,
+            diff_inventory,
+            preview_auto_offer,
             // targeted catalog lookup by ids
             get_catalog_items_by_ids,
+            resolve_items,
+            get_trending_items,
+            get_catalog_changes,
+            evaluate_trade,
+            refresh_catalog_cache,
+            get_thumbnail_cache_ttl,
+            set_thumbnail_cache_ttl,
+            get_user_search_disk_cache,
+            set_user_search_disk_cache,
+            get_rate_limit,
+            set_rate_limit,
+            get_proxy,
+            set_proxy,
+            clear_proxy,
+            get_user_agent,
+            set_user_agent,
+            check_services,
+            clear_caches,
+            read_app_log,
+            get_player_assets_cache_ttl,
+            set_player_assets_cache_ttl,
             get_full_catalog,
+            cancel_full_catalog,
             // ads storage
             list_ads,
             save_ad,
+            duplicate_ad,
+            set_ad_enabled,
+            export_ads,
+            import_ads,
+            get_ads_raw,
+            set_ads_raw,
             delete_ad,
             get_ad,
-            // ads runner (start/stop/list)
+            // ads runner (start/stop/pause/resume/list)
             start_ad,
             stop_ad,
+            start_ads,
+            stop_ads,
+            get_max_concurrent_ads,
+            set_max_concurrent_ads,
+            get_restore_stagger_secs,
+            set_restore_stagger_secs,
+            force_remove_ad,
+            stop_all_ads,
+            pause_ad,
+            resume_ad,
             list_running_ads,
+            list_running_ads_detailed,
+            get_ad_status,
+            get_ad_stats,
             generate_verification_code,
+            generate_custom_verification_code,
+            reissue_verification_code,
+            get_verification_code_ttl,
+            set_verification_code_ttl,
             verify_user,
+            recheck_verification,
             // avatar thumbnails for user search
             avatar_thumbnails::fetch_avatar_thumbnails,
             // lazy thumbnail fetching by IDs
             fetch_thumbnails_for_ids_cmd,
             fetch_enriched_inventory,
+            start_value_watch,
+            stop_value_watch,
+            list_value_watches,
             save_auth_data,
             load_auth_data,
             save_global_verification,
+            extract_roli_verification,
             update_roli_verification,
+            normalize_and_validate_cookie,
+            list_accounts,
+            set_active_account,
             logout,
             // notification settings
             get_notification_enabled,
-            set_notification_enabled
+            set_notification_enabled,
+            get_notification_channel,
+            set_notification_channel,
+            get_value_change_threshold,
+            set_value_change_threshold,
+            get_quiet_period_minutes,
+            set_quiet_period_minutes,
+            get_notification_metric,
+            set_notification_metric,
+            get_notification_direction,
+            set_notification_direction,
+            get_discord_webhook_url,
+            set_discord_webhook_url,
+            get_notification_history,
+            clear_notification_history
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
-/// Tauri command to fetch catalog items from Rolimons with pagination and optional search.
+/// Tauri command to fetch catalog items from Rolimons with pagination, optional search and
+/// optional value-range / minimum-demand filters. `total` counts the filtered set.
+/// Sorting defaults to value descending; `match_mode` defaults to a name/abbreviation
+/// substring match. `value_source` picks what "value" means for sorting and display:
+/// value falling back to RAP (default), RAP only, or value only.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn get_catalog_items(
     page: usize,
     per_page: usize,
     search: Option<String>,
+    match_mode: Option<trade_ad::MatchMode>,
+    min_value: Option<u64>,
+    max_value: Option<u64>,
+    min_demand: Option<i8>,
+    sort_by: Option<trade_ad::ItemSort>,
+    sort_desc: Option<bool>,
+    value_source: Option<trade_ad::ValueSource>,
 ) -> Result<serde_json::Value, String> {
-    match trade_ad::fetch_item_details(page, per_page, search).await {
+    let filter = trade_ad::ItemFilter {
+        min_value,
+        max_value,
+        min_demand,
+    };
+    match trade_ad::fetch_item_details(
+        page,
+        per_page,
+        search,
+        match_mode.unwrap_or_default(),
+        &filter,
+        sort_by.unwrap_or_default(),
+        sort_desc.unwrap_or(true),
+        value_source.unwrap_or_default(),
+    )
+    .await
+    {
         Ok((items, total)) => Ok(serde_json::json!({"items": items, "total": total})),
         Err(e) => Err(e.to_string()),
     }
 }
 
-/// Tauri command: fetch catalog entries for specific catalog IDs (targeted lookup)
+/// Number of entries dropped from each cache by `clear_caches`.
+#[derive(Serialize)]
+struct ClearedCaches {
+    thumbnails: usize,
+    catalog_items: usize,
+    player_assets: usize,
+    user_searches: usize,
+    /// Users whose value baselines were reset; None when values were left alone.
+    value_users: Option<usize>,
+}
+
+/// Tauri command to reset the in-memory caches so fresh data is fetched without a restart.
+/// The value-change baselines are only reset when `include_values` is true, since doing so
+/// suppresses notifications until the next inventory fetch repopulates them.
+#[tauri::command]
+fn clear_caches(include_values: Option<bool>) -> ClearedCaches {
+    let cleared = ClearedCaches {
+        thumbnails: thumbnails::clear_cache(),
+        catalog_items: trade_ad::clear_item_cache(),
+        player_assets: player_assets::clear_cache(),
+        user_searches: roblox_user::clear_cache(),
+        value_users: include_values
+            .unwrap_or(false)
+            .then(value_change_detector::reset_cache),
+    };
+    log::info!(
+        "clear_caches: thumbnails={} catalog_items={} player_assets={} user_searches={} value_users={:?}",
+        cleared.thumbnails,
+        cleared.catalog_items,
+        cleared.player_assets,
+        cleared.user_searches,
+        cleared.value_users
+    );
+    cleared
+}
+
+/// Tauri command to get the player assets cache TTL in seconds
+#[tauri::command]
+fn get_player_assets_cache_ttl() -> u64 {
+    player_assets::cache_ttl_secs()
+}
+
+/// Tauri command to set the player assets cache TTL in seconds (0 = always refetch)
+#[tauri::command]
+fn set_player_assets_cache_ttl(secs: u64) {
+    player_assets::set_cache_ttl_secs(secs);
+    log::info!("player assets cache TTL set to {}s", secs);
+}
+
+/// Tauri command to get the thumbnail cache TTL in seconds
+#[tauri::command]
+fn get_thumbnail_cache_ttl() -> u64 {
+    thumbnails::cache_ttl_secs()
+}
+
+/// Tauri command to set the thumbnail cache TTL in seconds (0 = always refetch)
+#[tauri::command]
+fn set_thumbnail_cache_ttl(secs: u64) {
+    thumbnails::set_cache_ttl_secs(secs);
+    log::info!("thumbnail cache TTL set to {}s", secs);
+}
+
+/// Tauri command to get whether Roblox user searches are also cached on disk across restarts
+#[tauri::command]
+fn get_user_search_disk_cache() -> bool {
+    roblox_user::disk_cache_enabled()
+}
+
+/// Tauri command to turn the on-disk user search cache on or off
+#[tauri::command]
+fn set_user_search_disk_cache(enabled: bool) {
+    roblox_user::set_disk_cache_enabled(enabled);
+    log::info!(
+        "user search disk cache {}",
+        if enabled { "enabled" } else { "disabled" }
+    );
+}
+
+/// Tauri command to get the shared Rolimons request limit (requests per minute)
+#[tauri::command]
+fn get_rate_limit() -> u64 {
+    rate_limit::requests_per_minute()
+}
+
+/// Tauri command to set the shared Rolimons request limit (requests per minute, 0 = unlimited)
+#[tauri::command]
+fn set_rate_limit(requests_per_minute: u64) {
+    rate_limit::set_requests_per_minute(requests_per_minute);
+    log::info!("rate limit set to {} requests/min", requests_per_minute);
+}
+
+/// Tauri command to get the outbound proxy URL (password masked), if one is configured
 #[tauri::command]
-async fn get_catalog_items_by_ids(ids: Vec<u64>) -> Result<serde_json::Value, String> {
-    match trade_ad::fetch_items_by_ids(ids).await {
+fn get_proxy() -> Result<Option<String>, String> {
+    http::get_proxy().map_err(|e| e.to_string())
+}
+
+/// Tauri command to route every outbound request (runner, catalog, inventory, posts) through
+/// a proxy. Accepts http, https, socks5 and socks5h URLs, with credentials in the URL.
+#[tauri::command]
+fn set_proxy(url: String) -> Result<(), String> {
+    http::set_proxy(&url).map_err(|e| e.to_string())
+}
+
+/// Tauri command to stop using the proxy and connect directly again
+#[tauri::command]
+fn clear_proxy() -> Result<(), String> {
+    http::clear_proxy().map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the browser User-Agent sent with trade ad posts
+#[tauri::command]
+fn get_user_agent() -> String {
+    http::browser_user_agent()
+}
+
+/// Tauri command to change the browser User-Agent sent with trade ad posts (persisted).
+/// An empty string restores the built-in default.
+#[tauri::command]
+fn set_user_agent(ua: String) -> Result<(), String> {
+    http::set_browser_user_agent(Some(&ua)).map_err(|e| e.to_string())
+}
+
+/// Reachability of one upstream service, as seen by `check_services`.
+#[derive(Debug, Serialize)]
+struct ServiceCheck {
+    up: bool,
+    // HTTP status, when a response came back at all
+    status: Option<u16>,
+    rate_limited: bool,
+    latency_ms: u64,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ServiceStatus {
+    rolimons: ServiceCheck,
+    roblox: ServiceCheck,
+}
+
+/// GET `url` once (no retries) and report how it went. Only the status is looked at.
+async fn check_service(url: &str) -> ServiceCheck {
+    let start = std::time::Instant::now();
+    let result = http::client()
+        .get(url)
+        .timeout(std::time::Duration::from_secs(8))
+        .send()
+        .await;
+    let latency_ms = start.elapsed().as_millis() as u64;
+    match result {
+        Ok(resp) => {
+            let status = resp.status();
+            ServiceCheck {
+                up: status.is_success(),
+                status: Some(status.as_u16()),
+                rate_limited: status.as_u16() == 429,
+                latency_ms,
+                error: None,
+            }
+        }
+        Err(e) => ServiceCheck {
+            up: false,
+            status: None,
+            rate_limited: false,
+            latency_ms,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Tauri command to ping a Rolimons endpoint and the Roblox users endpoint in parallel and
+/// report whether each is up, its HTTP status and latency.
+#[tauri::command]
+async fn check_services() -> ServiceStatus {
+    // a one-player search instead of the full item catalog, so a health check is one small
+    // request and doesn't take a rate-limit permit from the runners
+    let (rolimons, roblox) = tokio::join!(
+        check_service("https://api.rolimons.com/players/v1/playersearch?searchstring=Roblox"),
+        check_service("https://users.roblox.com/v1/users/1")
+    );
+    log::info!(
+        "check_services: rolimons {:?} in {}ms, roblox {:?} in {}ms",
+        rolimons.status,
+        rolimons.latency_ms,
+        roblox.status,
+        roblox.latency_ms
+    );
+    ServiceStatus { rolimons, roblox }
+}
+
+/// Tauri command to re-download the Rolimons catalog now instead of waiting for the cache to
+/// expire. Returns the number of items loaded.
+#[tauri::command]
+async fn refresh_catalog_cache() -> Result<usize, String> {
+    trade_ad::refresh_item_cache()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to resolve offer/request entries given as ids, abbreviations or names to
+/// catalog ids. Each query comes back `resolved`, `ambiguous` (with every candidate, so the
+/// user can pick one) or `unknown`.
+#[tauri::command]
+async fn resolve_items(queries: Vec<String>) -> Result<Vec<trade_ad::ItemResolution>, String> {
+    trade_ad::resolve_items(&queries)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the highest-rated catalog items by demand (default) or trend, for a
+/// "hot items" list. Uses the cached catalog; `limit` defaults to 20 and is capped at 100.
+#[tauri::command]
+async fn get_trending_items(
+    limit: Option<usize>,
+    metric: Option<trade_ad::TrendingMetric>,
+) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(20).min(100);
+    match trade_ad::fetch_trending_items(metric.unwrap_or_default(), limit).await {
+        Ok(items) => Ok(serde_json::json!({"items": items})),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Tauri command to total the value and RAP of each side of a trade, with the difference
+/// (request minus offer) and percent relative to the offer. Request tags add nothing to the
+/// totals and are only echoed back so the UI can show them.
+#[tauri::command]
+async fn evaluate_trade(
+    offer_ids: Vec<u64>,
+    request_ids: Vec<u64>,
+    request_tags: Option<Vec<String>>,
+) -> Result<trade_ad::TradeEvaluation, String> {
+    let tags = ads_storage::normalize_request_tags(&request_tags.unwrap_or_default())?;
+    trade_ad::fetch_trade_evaluation(offer_ids, request_ids, tags)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command to list catalog items whose value changed since the previous call, with old
+/// and new value and percent change, largest first by `sort_by` (percent by default). Refetches the
+/// catalog and then remembers it for next time; the first call only records a baseline
+/// (`baseline_created`). `limit` defaults to 100 and is capped at 1000; `total` is uncapped.
+#[tauri::command]
+async fn get_catalog_changes(
+    limit: Option<usize>,
+    sort_by: Option<trade_ad::ChangeSort>,
+) -> Result<serde_json::Value, String> {
+    let limit = limit.unwrap_or(100).min(1000);
+    match trade_ad::fetch_catalog_changes(limit, sort_by.unwrap_or_default()).await {
+        Ok((changes, total, had_snapshot)) => Ok(serde_json::json!({
+            "changes": changes,
+            "total": total,
+            "baseline_created": !had_snapshot,
+        })),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Tauri command: fetch catalog entries for specific catalog IDs (targeted lookup).
+/// `thumb_size` defaults to small; detail views can ask for large.
+#[tauri::command]
+async fn get_catalog_items_by_ids(
+    ids: Vec<u64>,
+    thumb_size: Option<trade_ad::ThumbSize>,
+) -> Result<serde_json::Value, String> {
+    match trade_ad::fetch_items_by_ids(ids, thumb_size.unwrap_or_default()).await {
         Ok(items) => Ok(serde_json::json!({"items": items})),
         Err(e) => Err(e.to_string()),
     }
@@ -2,11 +2,176 @@
 // Responsibility: Store and retrieve user notification preferences
 
 use dirs::data_local_dir;
+use once_cell::sync::Lazy;
 use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 static SETTINGS_DB: Mutex<Option<Connection>> = Mutex::new(None);
 
+/// Default minimum percent change before a value change is notified.
+pub const DEFAULT_VALUE_CHANGE_THRESHOLD: f64 = 5.0;
+
+/// Longest quiet period a user can set (one day).
+pub const MAX_QUIET_PERIOD_MINUTES: u64 = 24 * 60;
+
+// user_id -> when this session first checked the user's inventory for value changes
+static FIRST_CHECK: Lazy<Mutex<HashMap<String, Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Which figures a value-change notification is sent for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationMetric {
+    #[default]
+    Value,
+    Rap,
+    Both,
+}
+
+impl NotificationMetric {
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationMetric::Value => "value",
+            NotificationMetric::Rap => "rap",
+            NotificationMetric::Both => "both",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "rap" => NotificationMetric::Rap,
+            "both" => NotificationMetric::Both,
+            _ => NotificationMetric::Value,
+        }
+    }
+
+    pub fn includes_value(self) -> bool {
+        self != NotificationMetric::Rap
+    }
+
+    pub fn includes_rap(self) -> bool {
+        self != NotificationMetric::Value
+    }
+}
+
+/// Which direction of change a value-change notification is sent for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationDirection {
+    #[default]
+    Both,
+    IncreaseOnly,
+    DecreaseOnly,
+}
+
+impl NotificationDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationDirection::Both => "both",
+            NotificationDirection::IncreaseOnly => "increase_only",
+            NotificationDirection::DecreaseOnly => "decrease_only",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "increase_only" => NotificationDirection::IncreaseOnly,
+            "decrease_only" => NotificationDirection::DecreaseOnly,
+            _ => NotificationDirection::Both,
+        }
+    }
+
+    /// Whether a move from `old` to `new` should be notified.
+    pub fn allows(self, old: u64, new: u64) -> bool {
+        match self {
+            NotificationDirection::Both => old != new,
+            NotificationDirection::IncreaseOnly => new > old,
+            NotificationDirection::DecreaseOnly => new < old,
+        }
+    }
+}
+
+/// Where value-change notifications are delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationChannel {
+    Native,
+    Webhook,
+    Both,
+    #[default]
+    None,
+}
+
+impl NotificationChannel {
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationChannel::Native => "native",
+            NotificationChannel::Webhook => "webhook",
+            NotificationChannel::Both => "both",
+            NotificationChannel::None => "none",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "native" => NotificationChannel::Native,
+            "webhook" => NotificationChannel::Webhook,
+            "both" => NotificationChannel::Both,
+            _ => NotificationChannel::None,
+        }
+    }
+
+    /// Channel for a row saved before channels existed, from its `enabled` flag. Users who had
+    /// a webhook configured got both deliveries back then, so they keep both.
+    fn from_legacy(enabled: bool, has_webhook: bool) -> Self {
+        match (enabled, has_webhook) {
+            (false, _) => NotificationChannel::None,
+            (true, false) => NotificationChannel::Native,
+            (true, true) => NotificationChannel::Both,
+        }
+    }
+
+    pub fn includes_native(self) -> bool {
+        matches!(
+            self,
+            NotificationChannel::Native | NotificationChannel::Both
+        )
+    }
+
+    pub fn includes_webhook(self) -> bool {
+        matches!(
+            self,
+            NotificationChannel::Webhook | NotificationChannel::Both
+        )
+    }
+}
+
+/// Add a column to notification_settings if an older database doesn't have it yet.
+fn add_column_if_missing(conn: &Connection, column: &str, definition: &str) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("PRAGMA table_info(notification_settings)")
+        .map_err(|e| e.to_string())?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| e.to_string())?
+        .filter_map(Result::ok)
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(
+            &format!(
+                "ALTER TABLE notification_settings ADD COLUMN {} {}",
+                column, definition
+            ),
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 fn get_db_connection() -> Result<&'static Mutex<Option<Connection>>, String> {
     let mut lock = SETTINGS_DB.lock().map_err(|e| e.to_string())?;
 
@@ -27,6 +192,53 @@ fn get_db_connection() -> Result<&'static Mutex<Option<Connection>>, String> {
         )
         .map_err(|e| e.to_string())?;
 
+        add_column_if_missing(
+            &conn,
+            "value_change_threshold",
+            &format!(
+                "REAL NOT NULL DEFAULT {:.1}",
+                DEFAULT_VALUE_CHANGE_THRESHOLD
+            ),
+        )?;
+        add_column_if_missing(&conn, "notify_metric", "TEXT NOT NULL DEFAULT 'value'")?;
+        add_column_if_missing(&conn, "notify_direction", "TEXT NOT NULL DEFAULT 'both'")?;
+        add_column_if_missing(&conn, "discord_webhook_url", "TEXT")?;
+        add_column_if_missing(&conn, "notify_channel", "TEXT")?;
+        add_column_if_missing(&conn, "quiet_period_minutes", "INTEGER NOT NULL DEFAULT 0")?;
+        // rows from before channels existed: derive the channel from `enabled`
+        conn.execute(
+            "UPDATE notification_settings SET notify_channel = CASE
+                WHEN enabled = 0 THEN 'none'
+                WHEN COALESCE(discord_webhook_url, '') != '' THEN 'both'
+                ELSE 'native' END
+             WHERE notify_channel IS NULL",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS notification_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT NOT NULL,
+                fired_at INTEGER NOT NULL,
+                catalog_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                old_value INTEGER NOT NULL,
+                new_value INTEGER NOT NULL,
+                old_rap INTEGER NOT NULL,
+                new_rap INTEGER NOT NULL,
+                thumbnail TEXT
+            )",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_notification_history_user
+             ON notification_history (user_id, fired_at)",
+            [],
+        )
+        .map_err(|e| e.to_string())?;
+
         *lock = Some(conn);
     }
 
@@ -34,39 +246,375 @@ fn get_db_connection() -> Result<&'static Mutex<Option<Connection>>, String> {
     Ok(&SETTINGS_DB)
 }
 
-/// Get notification setting for user (default: false)
+/// Get where the user's notifications are delivered (default: none)
+pub fn get_notification_channel(user_id: &str) -> Result<NotificationChannel, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let result = conn
+        .query_row(
+            "SELECT notify_channel, enabled, discord_webhook_url
+             FROM notification_settings WHERE user_id = ?1",
+            params![user_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, i32>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(match result {
+        Some((Some(channel), _, _)) => NotificationChannel::parse(&channel),
+        Some((None, enabled, webhook)) => NotificationChannel::from_legacy(
+            enabled != 0,
+            webhook.is_some_and(|url| !url.is_empty()),
+        ),
+        None => NotificationChannel::default(),
+    })
+}
+
+/// Set where the user's notifications are delivered. `enabled` is kept in step.
+pub fn set_notification_channel(user_id: &str, channel: NotificationChannel) -> Result<(), String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let enabled = i32::from(channel != NotificationChannel::None);
+    conn.execute(
+        "INSERT INTO notification_settings (user_id, enabled, notify_channel) VALUES (?1, ?2, ?3)
+         ON CONFLICT(user_id) DO UPDATE SET
+            enabled = excluded.enabled, notify_channel = excluded.notify_channel",
+        params![user_id, enabled, channel.as_str()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get notification setting for user (default: false). True for any channel but none.
 pub fn get_notification_enabled(user_id: &str) -> Result<bool, String> {
+    Ok(get_notification_channel(user_id)? != NotificationChannel::None)
+}
+
+/// Set notification setting for user. Enabling keeps a chosen channel and falls back to
+/// native notifications; disabling sets the channel to none.
+pub fn set_notification_enabled(user_id: &str, enabled: bool) -> Result<(), String> {
+    let channel = match get_notification_channel(user_id)? {
+        _ if !enabled => NotificationChannel::None,
+        NotificationChannel::None => NotificationChannel::Native,
+        current => current,
+    };
+    set_notification_channel(user_id, channel)
+}
+
+/// Get the minimum percent change that triggers a value notification
+/// (default: DEFAULT_VALUE_CHANGE_THRESHOLD)
+pub fn get_value_change_threshold(user_id: &str) -> Result<f64, String> {
     let db = get_db_connection()?;
     let lock = db.lock().map_err(|e| e.to_string())?;
     let conn = lock.as_ref().ok_or("Database not initialized")?;
 
-    let mut stmt = conn
-        .prepare("SELECT enabled FROM notification_settings WHERE user_id = ?1")
+    let result = conn
+        .query_row(
+            "SELECT value_change_threshold FROM notification_settings WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get::<_, f64>(0),
+        )
+        .optional()
         .map_err(|e| e.to_string())?;
 
-    let result = stmt
-        .query_row(params![user_id], |row| row.get::<_, i32>(0))
+    Ok(result.unwrap_or(DEFAULT_VALUE_CHANGE_THRESHOLD))
+}
+
+/// Set the minimum percent change that triggers a value notification
+pub fn set_value_change_threshold(user_id: &str, percent: f64) -> Result<(), String> {
+    if !percent.is_finite() || percent < 0.0 {
+        return Err("Threshold must be a non-negative percentage".to_string());
+    }
+
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    conn.execute(
+        "INSERT INTO notification_settings (user_id, value_change_threshold) VALUES (?1, ?2)
+         ON CONFLICT(user_id) DO UPDATE SET value_change_threshold = excluded.value_change_threshold",
+        params![user_id, percent],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get how many minutes after the first check of a session changes are recorded but not
+/// notified (default: 0, off)
+pub fn get_quiet_period_minutes(user_id: &str) -> Result<u64, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let result = conn
+        .query_row(
+            "SELECT quiet_period_minutes FROM notification_settings WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get::<_, i64>(0),
+        )
         .optional()
         .map_err(|e| e.to_string())?;
 
-    Ok(result.unwrap_or(0) != 0)
+    Ok(result.unwrap_or(0).max(0) as u64)
 }
 
-/// Set notification setting for user
-pub fn set_notification_enabled(user_id: &str, enabled: bool) -> Result<(), String> {
+/// Set the quiet period in minutes; 0 turns it off
+pub fn set_quiet_period_minutes(user_id: &str, minutes: u64) -> Result<(), String> {
+    if minutes > MAX_QUIET_PERIOD_MINUTES {
+        return Err(format!(
+            "Quiet period must be at most {} minutes",
+            MAX_QUIET_PERIOD_MINUTES
+        ));
+    }
+
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    conn.execute(
+        "INSERT INTO notification_settings (user_id, quiet_period_minutes) VALUES (?1, ?2)
+         ON CONFLICT(user_id) DO UPDATE SET quiet_period_minutes = excluded.quiet_period_minutes",
+        params![user_id, minutes as i64],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn within_quiet_period(since_first_check: Duration, minutes: u64) -> bool {
+    since_first_check < Duration::from_secs(minutes * 60)
+}
+
+/// Whether `user_id` is still in their quiet period: the first `quiet_period_minutes` after
+/// this session first checked their inventory, while the value cache catches up with changes
+/// made while the app was closed. The first call for a user starts the clock.
+pub fn in_quiet_period(user_id: &str) -> Result<bool, String> {
+    let first = *FIRST_CHECK
+        .lock()
+        .unwrap()
+        .entry(user_id.to_string())
+        .or_insert_with(Instant::now);
+    let minutes = get_quiet_period_minutes(user_id)?;
+    Ok(within_quiet_period(first.elapsed(), minutes))
+}
+
+/// Get which figures (value, rap or both) the user is notified about (default: value)
+pub fn get_notification_metric(user_id: &str) -> Result<NotificationMetric, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let result = conn
+        .query_row(
+            "SELECT notify_metric FROM notification_settings WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(result
+        .map(|s| NotificationMetric::parse(&s))
+        .unwrap_or_default())
+}
+
+/// Set which figures (value, rap or both) the user is notified about
+pub fn set_notification_metric(user_id: &str, metric: NotificationMetric) -> Result<(), String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    conn.execute(
+        "INSERT INTO notification_settings (user_id, notify_metric) VALUES (?1, ?2)
+         ON CONFLICT(user_id) DO UPDATE SET notify_metric = excluded.notify_metric",
+        params![user_id, metric.as_str()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get which direction of change the user is notified about (default: both)
+pub fn get_notification_direction(user_id: &str) -> Result<NotificationDirection, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let result = conn
+        .query_row(
+            "SELECT notify_direction FROM notification_settings WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(result
+        .map(|s| NotificationDirection::parse(&s))
+        .unwrap_or_default())
+}
+
+/// Set which direction of change the user is notified about
+pub fn set_notification_direction(
+    user_id: &str,
+    direction: NotificationDirection,
+) -> Result<(), String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    conn.execute(
+        "INSERT INTO notification_settings (user_id, notify_direction) VALUES (?1, ?2)
+         ON CONFLICT(user_id) DO UPDATE SET notify_direction = excluded.notify_direction",
+        params![user_id, direction.as_str()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Get the user's Discord webhook URL, if one is configured
+pub fn get_discord_webhook_url(user_id: &str) -> Result<Option<String>, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let result = conn
+        .query_row(
+            "SELECT discord_webhook_url FROM notification_settings WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(result.flatten())
+}
+
+/// Set or clear (None) the user's Discord webhook URL
+pub fn set_discord_webhook_url(user_id: &str, url: Option<&str>) -> Result<(), String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    conn.execute(
+        "INSERT INTO notification_settings (user_id, discord_webhook_url) VALUES (?1, ?2)
+         ON CONFLICT(user_id) DO UPDATE SET discord_webhook_url = excluded.discord_webhook_url",
+        params![user_id, url],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Default number of rows returned by `get_notification_history`.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// A value-change notification that was shown to the user.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NotificationRecord {
+    /// Unix seconds.
+    pub fired_at: u64,
+    pub catalog_id: u64,
+    pub name: String,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub old_rap: u64,
+    pub new_rap: u64,
+    pub thumbnail: Option<String>,
+}
+
+/// Append a fired notification to the user's history
+pub fn record_notification(
+    user_id: &str,
+    change: &crate::value_change_detector::ValueChange,
+    fired_at: u64,
+) -> Result<(), String> {
     let db = get_db_connection()?;
     let lock = db.lock().map_err(|e| e.to_string())?;
     let conn = lock.as_ref().ok_or("Database not initialized")?;
 
     conn.execute(
-        "INSERT OR REPLACE INTO notification_settings (user_id, enabled) VALUES (?1, ?2)",
-        params![user_id, if enabled { 1 } else { 0 }],
+        "INSERT INTO notification_history
+            (user_id, fired_at, catalog_id, name, old_value, new_value, old_rap, new_rap, thumbnail)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            user_id,
+            fired_at as i64,
+            change.catalog_id as i64,
+            change.name,
+            change.old_value as i64,
+            change.new_value as i64,
+            change.old_rap as i64,
+            change.new_rap as i64,
+            change.thumbnail,
+        ],
     )
     .map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
+/// The user's most recent notifications, newest first
+pub fn get_notification_history(
+    user_id: &str,
+    limit: usize,
+) -> Result<Vec<NotificationRecord>, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT fired_at, catalog_id, name, old_value, new_value, old_rap, new_rap, thumbnail
+             FROM notification_history WHERE user_id = ?1
+             ORDER BY fired_at DESC, id DESC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![user_id, limit as i64], |row| {
+            Ok(NotificationRecord {
+                fired_at: row.get::<_, i64>(0)? as u64,
+                catalog_id: row.get::<_, i64>(1)? as u64,
+                name: row.get(2)?,
+                old_value: row.get::<_, i64>(3)? as u64,
+                new_value: row.get::<_, i64>(4)? as u64,
+                old_rap: row.get::<_, i64>(5)? as u64,
+                new_rap: row.get::<_, i64>(6)? as u64,
+                thumbnail: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Delete the user's notification history, returning the number of rows removed
+pub fn clear_notification_history(user_id: &str) -> Result<usize, String> {
+    let db = get_db_connection()?;
+    let lock = db.lock().map_err(|e| e.to_string())?;
+    let conn = lock.as_ref().ok_or("Database not initialized")?;
+
+    conn.execute(
+        "DELETE FROM notification_history WHERE user_id = ?1",
+        params![user_id],
+    )
+    .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,4 +687,195 @@ mod tests {
         assert_eq!(get_notification_enabled(user1).unwrap(), false);
         assert_eq!(get_notification_enabled(user2).unwrap(), true);
     }
+
+    #[test]
+    fn test_default_threshold() {
+        let threshold = get_value_change_threshold("test_user_threshold_1").unwrap();
+        assert_eq!(threshold, DEFAULT_VALUE_CHANGE_THRESHOLD);
+    }
+
+    #[test]
+    fn test_threshold_independent_of_enabled() {
+        let user_id = "test_user_threshold_2";
+
+        set_value_change_threshold(user_id, 12.5).unwrap();
+        set_notification_enabled(user_id, true).unwrap();
+
+        assert_eq!(get_value_change_threshold(user_id).unwrap(), 12.5);
+        assert!(get_notification_enabled(user_id).unwrap());
+    }
+
+    #[test]
+    fn test_quiet_period() {
+        let user_id = "test_user_quiet_1";
+        // the test database outlives a run, so start from a known state
+        set_quiet_period_minutes(user_id, 0).unwrap();
+        assert_eq!(get_quiet_period_minutes(user_id).unwrap(), 0);
+        assert!(!in_quiet_period(user_id).unwrap());
+
+        set_quiet_period_minutes(user_id, 30).unwrap();
+        assert_eq!(get_quiet_period_minutes(user_id).unwrap(), 30);
+        assert!(in_quiet_period(user_id).unwrap());
+        assert!(set_quiet_period_minutes(user_id, MAX_QUIET_PERIOD_MINUTES + 1).is_err());
+
+        assert!(within_quiet_period(Duration::from_secs(29 * 60), 30));
+        assert!(!within_quiet_period(Duration::from_secs(30 * 60), 30));
+        assert!(!within_quiet_period(Duration::ZERO, 0));
+    }
+
+    #[test]
+    fn test_negative_threshold_rejected() {
+        assert!(set_value_change_threshold("test_user_threshold_3", -1.0).is_err());
+        assert!(set_value_change_threshold("test_user_threshold_3", f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_default_metric() {
+        let metric = get_notification_metric("test_user_metric_1").unwrap();
+        assert_eq!(metric, NotificationMetric::Value);
+    }
+
+    #[test]
+    fn test_notification_metric_roundtrip() {
+        let user_id = "test_user_metric_2";
+
+        set_notification_metric(user_id, NotificationMetric::Both).unwrap();
+        assert_eq!(
+            get_notification_metric(user_id).unwrap(),
+            NotificationMetric::Both
+        );
+        set_notification_metric(user_id, NotificationMetric::Rap).unwrap();
+        assert_eq!(
+            get_notification_metric(user_id).unwrap(),
+            NotificationMetric::Rap
+        );
+    }
+
+    #[test]
+    fn test_notification_metric_includes() {
+        assert!(NotificationMetric::Value.includes_value());
+        assert!(!NotificationMetric::Value.includes_rap());
+        assert!(!NotificationMetric::Rap.includes_value());
+        assert!(NotificationMetric::Rap.includes_rap());
+        assert!(NotificationMetric::Both.includes_value());
+        assert!(NotificationMetric::Both.includes_rap());
+    }
+
+    #[test]
+    fn test_notification_direction_roundtrip() {
+        let user_id = "test_user_direction_1";
+
+        set_notification_direction(user_id, NotificationDirection::DecreaseOnly).unwrap();
+        assert_eq!(
+            get_notification_direction(user_id).unwrap(),
+            NotificationDirection::DecreaseOnly
+        );
+        set_notification_direction(user_id, NotificationDirection::IncreaseOnly).unwrap();
+        assert_eq!(
+            get_notification_direction(user_id).unwrap(),
+            NotificationDirection::IncreaseOnly
+        );
+    }
+
+    #[test]
+    fn test_notification_direction_allows() {
+        assert!(NotificationDirection::Both.allows(100, 200));
+        assert!(NotificationDirection::Both.allows(200, 100));
+        assert!(NotificationDirection::IncreaseOnly.allows(100, 200));
+        assert!(!NotificationDirection::IncreaseOnly.allows(200, 100));
+        assert!(NotificationDirection::DecreaseOnly.allows(200, 100));
+        assert!(!NotificationDirection::DecreaseOnly.allows(100, 200));
+        assert!(!NotificationDirection::Both.allows(100, 100));
+    }
+
+    #[test]
+    fn test_notification_channel_roundtrip() {
+        let user_id = "test_user_channel_1";
+
+        set_notification_channel(user_id, NotificationChannel::Webhook).unwrap();
+        assert_eq!(
+            get_notification_channel(user_id).unwrap(),
+            NotificationChannel::Webhook
+        );
+        assert!(get_notification_enabled(user_id).unwrap());
+
+        // the old toggle keeps a chosen channel when enabling
+        set_notification_enabled(user_id, true).unwrap();
+        assert_eq!(
+            get_notification_channel(user_id).unwrap(),
+            NotificationChannel::Webhook
+        );
+
+        set_notification_enabled(user_id, false).unwrap();
+        assert_eq!(
+            get_notification_channel(user_id).unwrap(),
+            NotificationChannel::None
+        );
+        set_notification_enabled(user_id, true).unwrap();
+        assert_eq!(
+            get_notification_channel(user_id).unwrap(),
+            NotificationChannel::Native
+        );
+    }
+
+    #[test]
+    fn test_notification_channel_legacy_mapping() {
+        use NotificationChannel::*;
+        assert_eq!(NotificationChannel::from_legacy(false, true), None);
+        assert_eq!(NotificationChannel::from_legacy(true, false), Native);
+        assert_eq!(NotificationChannel::from_legacy(true, true), Both);
+        assert!(Both.includes_native() && Both.includes_webhook());
+        assert!(!Webhook.includes_native());
+        assert!(!Native.includes_webhook());
+        assert_eq!(NotificationChannel::parse("garbage"), None);
+    }
+
+    #[test]
+    fn test_discord_webhook_set_and_clear() {
+        let user_id = "test_user_webhook_1";
+        let url = "https://discord.com/api/webhooks/1/abc";
+
+        set_discord_webhook_url(user_id, Some(url)).unwrap();
+        assert_eq!(
+            get_discord_webhook_url(user_id).unwrap().as_deref(),
+            Some(url)
+        );
+
+        set_discord_webhook_url(user_id, None).unwrap();
+        assert_eq!(get_discord_webhook_url(user_id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_notification_history() {
+        let user_id = "test_user_history_1";
+        clear_notification_history(user_id).unwrap();
+        let change = |id: u64, new_value: u64| crate::value_change_detector::ValueChange {
+            catalog_id: id,
+            name: format!("Item {}", id),
+            old_value: 1000,
+            new_value,
+            old_rap: 900,
+            new_rap: 900,
+            thumbnail: None,
+        };
+        record_notification(user_id, &change(1, 1100), 100).unwrap();
+        record_notification(user_id, &change(2, 800), 200).unwrap();
+        record_notification("test_user_history_other", &change(3, 500), 300).unwrap();
+
+        let history = get_notification_history(user_id, 10).unwrap();
+        let ids: Vec<u64> = history.iter().map(|r| r.catalog_id).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(history[0].new_value, 800);
+        assert_eq!(get_notification_history(user_id, 1).unwrap().len(), 1);
+
+        assert_eq!(clear_notification_history(user_id).unwrap(), 2);
+        assert!(get_notification_history(user_id, 10).unwrap().is_empty());
+        assert_eq!(
+            get_notification_history("test_user_history_other", 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        clear_notification_history("test_user_history_other").unwrap();
+    }
 }
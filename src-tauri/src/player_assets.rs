@@ -1,58 +1,75 @@
+use crate::api_error::{check_status, ApiError};
+use crate::http;
+use crate::rate_limit;
 use once_cell::sync::Lazy;
-use reqwest::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-// Simple in-memory TTL cache for player assets: player_id -> (json, expiry_unix_secs)
+// Simple in-memory TTL cache for player assets: player_id -> (json, fetched_at_unix_secs)
 static PLAYER_ASSETS_CACHE: Lazy<Mutex<HashMap<u64, (Value, u64)>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
-const PLAYER_ASSETS_TTL_SECS: u64 = 30; // 30 seconds TTL
+pub const DEFAULT_PLAYER_ASSETS_TTL_SECS: u64 = 30; // 30 seconds TTL
+/// Cache TTL in seconds, adjustable at runtime. Zero disables the cache.
+static PLAYER_ASSETS_TTL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_PLAYER_ASSETS_TTL_SECS);
 
-async fn fetch_player_assets_raw(player_id: u64) -> Result<Value, String> {
+pub fn cache_ttl_secs() -> u64 {
+    PLAYER_ASSETS_TTL_SECS.load(Ordering::Relaxed)
+}
+
+pub fn set_cache_ttl_secs(secs: u64) {
+    PLAYER_ASSETS_TTL_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Drop all cached player assets. Returns the number of players removed.
+pub fn clear_cache() -> usize {
+    let mut cache = PLAYER_ASSETS_CACHE.lock().unwrap();
+    let n = cache.len();
+    cache.clear();
+    n
+}
+
+async fn fetch_player_assets_raw(player_id: u64) -> Result<Value, ApiError> {
     let start = std::time::Instant::now();
-    eprintln!("fetch_player_assets_raw: starting for player {}", player_id);
+    log::debug!("fetch_player_assets_raw: starting for player {}", player_id);
     let url = format!(
         "https://api.rolimons.com/players/v1/playerassets/{}",
         player_id
     );
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| e.to_string())?;
-    let resp = client
-        .get(&url)
-        .header(USER_AGENT, "rolimons-player-assets-fetcher/1.0")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !resp.status().is_success() {
-        return Err(format!(
-            "Rolimons player assets HTTP error: {}",
-            resp.status()
-        ));
-    }
+    rate_limit::acquire().await;
+    let resp = http::send_with_backoff(|| http::client().get(&url)).await?;
+    let resp = check_status(resp).await?;
 
-    let json: Value = resp.json().await.map_err(|e| e.to_string())?;
-    eprintln!("fetch_player_assets_raw: completed for player {} in {:?}", player_id, start.elapsed());
+    let json: Value = resp.json().await?;
+    log::debug!(
+        "fetch_player_assets_raw: completed for player {} in {:?}",
+        player_id,
+        start.elapsed()
+    );
     Ok(json)
 }
 
 /// Fetch player assets with a small TTL cache to avoid repeated Rolimons calls when navigating UI.
+/// `force_refresh` bypasses the cache (e.g. right after a trade completes) and refills it.
 #[tauri::command]
-pub async fn fetch_player_assets(player_id: u64) -> Result<serde_json::Value, String> {
+pub async fn fetch_player_assets(
+    player_id: u64,
+    force_refresh: Option<bool>,
+) -> Result<serde_json::Value, String> {
     // Check cache
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or(Duration::from_secs(0))
         .as_secs();
-    {
+    if !force_refresh.unwrap_or(false) {
+        let ttl = cache_ttl_secs();
         let cache = PLAYER_ASSETS_CACHE.lock().unwrap();
-        if let Some((val, expiry)) = cache.get(&player_id) {
-            if *expiry > now {
+        if let Some((val, fetched_at)) = cache.get(&player_id) {
+            if now.saturating_sub(*fetched_at) < ttl {
                 // return cloned value
                 return Ok(val.clone());
             }
@@ -60,13 +77,14 @@ pub async fn fetch_player_assets(player_id: u64) -> Result<serde_json::Value, St
     }
 
     // Miss or expired -> fetch
-    let fetched = fetch_player_assets_raw(player_id).await?;
+    let fetched = fetch_player_assets_raw(player_id)
+        .await
+        .map_err(|e| e.to_string())?;
 
     // Store in cache
-    let expiry = now + PLAYER_ASSETS_TTL_SECS;
     {
         let mut cache = PLAYER_ASSETS_CACHE.lock().unwrap();
-        cache.insert(player_id, (fetched.clone(), expiry));
+        cache.insert(player_id, (fetched.clone(), now));
     }
 
     // Return trimmed subset: playerId, playerAssets, holds
@@ -87,11 +105,8 @@ pub async fn fetch_player_assets(player_id: u64) -> Result<serde_json::Value, St
     Ok(out)
 }
 
-/// Return a flattened inventory list: [{ catalog_id: String, instance_id: u64, held: bool }, ...]
-#[tauri::command]
-pub async fn fetch_player_inventory(player_id: u64) -> Result<serde_json::Value, String> {
-    let data = fetch_player_assets(player_id).await?;
-    let player_id_val = data.get("playerId").cloned().unwrap_or(Value::Null);
+/// Flatten a playerassets response into [{ catalog_id: String, instance_id: u64, held: bool }, ...]
+fn flatten_inventory(data: &Value) -> Vec<Value> {
     let holds_arr = data
         .get("holds")
         .and_then(|v| v.as_array())
@@ -118,12 +133,382 @@ pub async fn fetch_player_inventory(player_id: u64) -> Result<serde_json::Value,
             }
         }
     }
+    items
+}
+
+/// Take `limit` items starting at `offset` (all remaining items when `limit` is None).
+fn slice_items(items: Vec<Value>, offset: usize, limit: Option<usize>) -> Vec<Value> {
+    let iter = items.into_iter().skip(offset);
+    match limit {
+        Some(n) => iter.take(n).collect(),
+        None => iter.collect(),
+    }
+}
+
+/// Drop instances that are on hold, keeping only what can be traded right now.
+fn tradable_items(items: Vec<Value>) -> Vec<Value> {
+    items.into_iter().filter(|it| it["held"] != true).collect()
+}
+
+/// Return a flattened inventory list: [{ catalog_id: String, instance_id: u64, held: bool }, ...]
+/// Optional `offset` / `limit` return a slice of the list; `total` is always the full count.
+/// `force_refresh` bypasses the assets cache and `tradable_only` leaves out held instances
+/// (`total` then counts tradable instances only).
+#[tauri::command]
+pub async fn fetch_player_inventory(
+    player_id: u64,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    force_refresh: Option<bool>,
+    tradable_only: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    let data = fetch_player_assets(player_id, force_refresh).await?;
+    let player_id_val = data.get("playerId").cloned().unwrap_or(Value::Null);
+
+    let mut items = flatten_inventory(&data);
+    if tradable_only.unwrap_or(false) {
+        items = tradable_items(items);
+    }
+    let total = items.len();
+    let items = slice_items(items, offset.unwrap_or(0), limit);
 
     let out = json!({
         "success": true,
         "playerId": player_id_val,
         "items": items,
+        "total": total,
     });
 
     Ok(out)
 }
+
+/// Catalog id of an inventory entry; `fetch_player_inventory` returns it as a string.
+fn catalog_id_of(item: &Value) -> Option<u64> {
+    match &item["catalog_id"] {
+        Value::String(s) => s.parse::<u64>().ok(),
+        v => v.as_u64(),
+    }
+}
+
+/// Offered catalog ids the inventory can't cover. Each offered id needs its own
+/// instance, so offering the same item twice requires owning two copies.
+/// `items` are entries as returned by `fetch_player_inventory`.
+pub fn missing_offer_items(items: &[Value], offer_item_ids: &[u64]) -> Vec<u64> {
+    let mut owned: HashMap<u64, usize> = HashMap::new();
+    for item in items {
+        if let Some(id) = catalog_id_of(item) {
+            *owned.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    let mut missing = Vec::new();
+    for id in offer_item_ids {
+        match owned.get_mut(id) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => missing.push(*id),
+        }
+    }
+    missing
+}
+
+/// Make sure `player_id` owns every offered item before an ad is posted.
+/// Returns an error listing the catalog ids that are missing from the inventory.
+pub async fn verify_offer_ownership(player_id: u64, offer_item_ids: &[u64]) -> Result<(), String> {
+    let inventory = fetch_player_inventory(player_id, None, None, None, None).await?;
+    let items = inventory
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let missing = missing_offer_items(&items, offer_item_ids);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let ids: Vec<String> = missing.iter().map(|id| id.to_string()).collect();
+    Err(format!(
+        "Player {} does not own offered item(s): {}",
+        player_id,
+        ids.join(", ")
+    ))
+}
+
+/// A player's inventory at one point in time, as copies owned per catalog id. Serializable so
+/// the frontend can keep it and pass it back to `diff_snapshots` later.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventorySnapshot {
+    pub player_id: u64,
+    /// unix seconds
+    pub taken_at: u64,
+    pub items: BTreeMap<u64, usize>,
+}
+
+/// One catalog id in an inventory diff. `quantity` is how many copies were gained or lost, or
+/// for unchanged entries how many are held.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InventoryChange {
+    pub catalog_id: u64,
+    pub quantity: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct InventoryDiff {
+    pub added: Vec<InventoryChange>,
+    pub removed: Vec<InventoryChange>,
+    pub unchanged: Vec<InventoryChange>,
+}
+
+fn snapshot_from_items(player_id: u64, items: &[Value], taken_at: u64) -> InventorySnapshot {
+    let mut counts = BTreeMap::new();
+    for id in items.iter().filter_map(catalog_id_of) {
+        *counts.entry(id).or_insert(0) += 1;
+    }
+    InventorySnapshot {
+        player_id,
+        taken_at,
+        items: counts,
+    }
+}
+
+/// Snapshot `player_id`'s current inventory, bypassing the assets cache.
+pub async fn snapshot_inventory(player_id: u64) -> Result<InventorySnapshot, String> {
+    let inventory = fetch_player_inventory(player_id, None, None, Some(true), None).await?;
+    let items = inventory
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs();
+    Ok(snapshot_from_items(player_id, &items, now))
+}
+
+/// What changed between two snapshots, per catalog id in id order. A catalog id whose count
+/// went up is added, one whose count went down is removed (even if copies remain).
+pub fn diff_snapshots(before: &InventorySnapshot, after: &InventorySnapshot) -> InventoryDiff {
+    let ids: BTreeSet<u64> = before
+        .items
+        .keys()
+        .chain(after.items.keys())
+        .copied()
+        .collect();
+    let mut diff = InventoryDiff::default();
+    for catalog_id in ids {
+        let old = before.items.get(&catalog_id).copied().unwrap_or(0);
+        let new = after.items.get(&catalog_id).copied().unwrap_or(0);
+        let (list, quantity) = match new.cmp(&old) {
+            std::cmp::Ordering::Greater => (&mut diff.added, new - old),
+            std::cmp::Ordering::Less => (&mut diff.removed, old - new),
+            std::cmp::Ordering::Equal => (&mut diff.unchanged, new),
+        };
+        list.push(InventoryChange {
+            catalog_id,
+            quantity,
+        });
+    }
+    diff
+}
+
+/// Catalog ids of the `count` highest entries by `field` ("rap" or "value") in an enriched
+/// inventory, one per instance, so owning two copies lets both be picked. Entries without
+/// the figure (or at 0) are skipped; ties go to the lower catalog id.
+pub fn top_items_by(items: &[Value], field: &str, count: usize) -> Vec<u64> {
+    let mut ranked: Vec<(u64, u64)> = items
+        .iter()
+        .filter_map(|it| {
+            let figure = it[field].as_u64().filter(|v| *v > 0)?;
+            Some((catalog_id_of(it)?, figure))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked.into_iter().take(count).map(|(id, _)| id).collect()
+}
+
+/// Collapse instances of the same catalog id into one entry, in first-seen order. Each entry
+/// keeps the first instance's fields, minus `instance_id` / `held`, plus `quantity`,
+/// `instance_ids` and `held_count`. Entries without a catalog id are passed through as-is.
+pub fn group_by_catalog(items: Vec<Value>) -> Vec<Value> {
+    let mut grouped: Vec<Value> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for item in items {
+        let key = match &item["catalog_id"] {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => {
+                grouped.push(item);
+                continue;
+            }
+        };
+        let instance_id = item.get("instance_id").cloned().unwrap_or(Value::Null);
+        let held = item["held"] == true;
+        let i = *index.entry(key).or_insert_with(|| {
+            let mut entry = item.clone();
+            if let Some(obj) = entry.as_object_mut() {
+                obj.remove("instance_id");
+                obj.remove("held");
+                obj.insert("quantity".to_string(), json!(0));
+                obj.insert("instance_ids".to_string(), json!([]));
+                obj.insert("held_count".to_string(), json!(0));
+            }
+            grouped.push(entry);
+            grouped.len() - 1
+        });
+        let entry = &mut grouped[i];
+        entry["quantity"] = json!(entry["quantity"].as_u64().unwrap_or(0) + 1);
+        if held {
+            entry["held_count"] = json!(entry["held_count"].as_u64().unwrap_or(0) + 1);
+        }
+        if let Some(ids) = entry["instance_ids"].as_array_mut() {
+            ids.push(instance_id);
+        }
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic_assets() -> Value {
+        json!({
+            "playerId": 1,
+            "playerAssets": {
+                "1028606": [101, 102],
+                "1029025": [201],
+                "1365767": [301, 302, 303],
+            },
+            "holds": [102, 303],
+        })
+    }
+
+    #[test]
+    fn test_flatten_marks_held() {
+        let items = flatten_inventory(&synthetic_assets());
+        assert_eq!(items.len(), 6);
+        let held: Vec<u64> = items
+            .iter()
+            .filter(|it| it["held"] == true)
+            .map(|it| it["instance_id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(held, vec![102, 303]);
+    }
+
+    #[test]
+    fn test_tradable_items_skips_held() {
+        let items = tradable_items(flatten_inventory(&synthetic_assets()));
+        let mut ids: Vec<u64> = items
+            .iter()
+            .map(|it| it["instance_id"].as_u64().unwrap())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![101, 201, 301, 302]);
+    }
+
+    #[test]
+    fn test_slice_inventory() {
+        let items = flatten_inventory(&synthetic_assets());
+
+        let page = slice_items(items.clone(), 2, Some(3));
+        let ids: Vec<u64> = page
+            .iter()
+            .map(|it| it["instance_id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![201, 301, 302]);
+
+        assert_eq!(slice_items(items.clone(), 4, None).len(), 2);
+        assert_eq!(slice_items(items.clone(), 10, Some(5)).len(), 0);
+        assert_eq!(slice_items(items, 0, None).len(), 6);
+    }
+
+    #[test]
+    fn test_missing_offer_items() {
+        let items = flatten_inventory(&synthetic_assets());
+
+        assert!(missing_offer_items(&items, &[1028606, 1365767]).is_empty());
+        // two copies of 1028606 are owned, a third is not
+        assert_eq!(
+            missing_offer_items(&items, &[1028606, 1028606, 1028606]),
+            vec![1028606]
+        );
+        assert_eq!(missing_offer_items(&items, &[1029025, 999]), vec![999]);
+    }
+
+    #[test]
+    fn test_group_by_catalog() {
+        let items = flatten_inventory(&synthetic_assets());
+        let grouped = group_by_catalog(items);
+        assert_eq!(grouped.len(), 3);
+
+        let triple = grouped
+            .iter()
+            .find(|it| it["catalog_id"] == "1365767")
+            .unwrap();
+        assert_eq!(triple["quantity"], 3);
+        assert_eq!(triple["held_count"], 1);
+        let mut ids: Vec<u64> = triple["instance_ids"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_u64().unwrap())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![301, 302, 303]);
+        assert!(triple.get("instance_id").is_none());
+
+        let total: u64 = grouped
+            .iter()
+            .map(|it| it["quantity"].as_u64().unwrap())
+            .sum();
+        assert_eq!(total, 6);
+    }
+
+    #[test]
+    fn test_top_items_by() {
+        let items = vec![
+            json!({ "catalog_id": "10", "rap": 500, "value": 900 }),
+            json!({ "catalog_id": "20", "rap": 800, "value": 100 }),
+            json!({ "catalog_id": "20", "rap": 800, "value": 100 }),
+            json!({ "catalog_id": "30", "rap": 0, "value": 50 }),
+            json!({ "catalog_id": "40" }),
+        ];
+        assert_eq!(top_items_by(&items, "rap", 3), vec![20, 20, 10]);
+        assert_eq!(top_items_by(&items, "value", 2), vec![10, 20]);
+        // unrated entries are never padded in
+        assert_eq!(top_items_by(&items, "rap", 10).len(), 3);
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let before = snapshot_from_items(1, &flatten_inventory(&synthetic_assets()), 100);
+        assert_eq!(before.items.get(&1365767), Some(&3));
+
+        let after = snapshot_from_items(
+            1,
+            &flatten_inventory(&json!({
+                "playerId": 1,
+                "playerAssets": {
+                    "1028606": [101, 102],
+                    "1365767": [301],
+                    "20573078": [401],
+                },
+            })),
+            200,
+        );
+        let diff = diff_snapshots(&before, &after);
+        let pairs = |list: &[InventoryChange]| -> Vec<(u64, usize)> {
+            list.iter().map(|c| (c.catalog_id, c.quantity)).collect()
+        };
+        assert_eq!(pairs(&diff.added), vec![(20573078, 1)]);
+        assert_eq!(pairs(&diff.removed), vec![(1029025, 1), (1365767, 2)]);
+        assert_eq!(pairs(&diff.unchanged), vec![(1028606, 2)]);
+
+        // the snapshot survives a trip through the frontend as JSON
+        let raw = serde_json::to_string(&before).unwrap();
+        assert_eq!(
+            serde_json::from_str::<InventorySnapshot>(&raw).unwrap(),
+            before
+        );
+    }
+}
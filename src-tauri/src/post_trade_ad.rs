@@ -1,36 +1,367 @@
 // post.rs
 // Responsibility: Post trade ads to Rolimons API using reqwest.
 
-use anyhow::{anyhow, Result};
+use crate::api_error::{check_status, is_challenge_page, retry_after_header, ApiError};
+use crate::http;
+use crate::rate_limit;
+use once_cell::sync::Lazy;
+use rand::Rng;
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, COOKIE, USER_AGENT};
 use reqwest::header::{ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, ORIGIN, REFERER};
+use serde::Serialize;
 use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-/// Posts a trade ad to Rolimons using reqwest, setting the _RoliVerification cookie manually.
-pub async fn post_trade_ad_direct(
-    roli_verification: &str,
-    player_id: u64,
-    offer_item_ids: Vec<u64>,
-    request_item_ids: Vec<u64>,
-    request_tags: Vec<String>,
-) -> Result<String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+const CREATE_AD_URL: &str = "https://api.rolimons.com/tradeads/v1/createad";
+const RECENT_ADS_URL: &str = "https://api.rolimons.com/tradeads/v1/getrecentads";
+
+/// Timeout and retry budget for one createad call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PostOptions {
+    /// Per-attempt request timeout.
+    pub timeout: Duration,
+    /// Extra attempts after a network error, timeout or 5xx. Other failures are never retried.
+    pub max_retries: u32,
+}
+
+impl Default for PostOptions {
+    /// One attempt with the shared client's 10 second timeout.
+    fn default() -> Self {
+        PostOptions {
+            timeout: http::DEFAULT_TIMEOUT,
+            max_retries: 0,
+        }
+    }
+}
+
+/// Failures that may go away on their own. Cooldowns, rejected cookies, challenge pages and
+/// other 4xx answers would just fail the same way again.
+fn is_transient(err: &ApiError) -> bool {
+    matches!(err, ApiError::Network(_) | ApiError::ServerError(_))
+}
+
+/// Gap Rolimons enforces between two trade ads from the same player.
+pub const POST_COOLDOWN_SECS: u64 = 15 * 60;
+
+// player_id -> unix time (secs) the next post is expected to be accepted. Rolimons has no
+// endpoint for this, so it is inferred from our own createad responses.
+static NEXT_POST_ALLOWED: Lazy<Mutex<HashMap<u64, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn record_cooldown(player_id: u64, secs: u64) {
+    NEXT_POST_ALLOWED
+        .lock()
+        .unwrap()
+        .insert(player_id, unix_now() + secs);
+}
+
+/// Seconds until `player_id` may post again, as far as this session knows: from the last
+/// successful post (POST_COOLDOWN_SECS) or the cooldown Rolimons reported on a rejection.
+/// Zero when no post is pending a cooldown.
+pub fn post_cooldown_remaining(player_id: u64) -> u64 {
+    let deadline = NEXT_POST_ALLOWED.lock().unwrap().get(&player_id).copied();
+    deadline.map_or(0, |d| d.saturating_sub(unix_now()))
+}
+
+/// Look for a cooldown / seconds-remaining field in a createad error body.
+fn parse_cooldown_secs(body: &str) -> Option<u64> {
+    const KEYS: &[&str] = &[
+        "cooldown",
+        "cooldown_seconds",
+        "cooldown_remaining",
+        "seconds_remaining",
+        "time_remaining",
+        "retry_after",
+    ];
+    let root: serde_json::Value = serde_json::from_str(body).ok()?;
+    let candidates = [Some(&root), root.get("data")];
+    for obj in candidates.into_iter().flatten() {
+        for key in KEYS {
+            let secs = match obj.get(*key) {
+                Some(serde_json::Value::Number(n)) => n
+                    .as_u64()
+                    .or_else(|| n.as_f64().map(|f| f.ceil().max(0.0) as u64)),
+                Some(serde_json::Value::String(s)) => s.trim().parse::<u64>().ok(),
+                _ => None,
+            };
+            if let Some(secs) = secs.filter(|s| *s > 0) {
+                return Some(secs);
+            }
+        }
+    }
+    None
+}
+
+/// A successful createad call. Rolimons doesn't document the success body, so the id and
+/// timestamp are whatever it happens to include.
+#[derive(Debug, Clone, Serialize)]
+pub struct PostedTradeAd {
+    /// UI-friendly text; this is all older frontends ever received.
+    pub message: String,
+    pub ad_id: Option<String>,
+    pub created_unix: Option<u64>,
+}
+
+impl std::fmt::Display for PostedTradeAd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// First of `keys` present at the top level of `root` or in its `data` / `trade_ad` object.
+fn find_field<'a>(root: &'a serde_json::Value, keys: &[&str]) -> Option<&'a serde_json::Value> {
+    let candidates = [Some(root), root.get("data"), root.get("trade_ad")];
+    candidates
+        .into_iter()
+        .flatten()
+        .find_map(|obj| keys.iter().find_map(|key| obj.get(*key)))
+}
+
+/// Pull the ad id and creation time out of a createad success body, keeping the generic
+/// message when the body isn't JSON or has neither.
+fn parse_post_response(body: &str) -> PostedTradeAd {
+    let mut posted = PostedTradeAd {
+        message: "trade ad post success".to_string(),
+        ad_id: None,
+        created_unix: None,
+    };
+    let Ok(root) = serde_json::from_str::<serde_json::Value>(body) else {
+        return posted;
+    };
+    posted.ad_id = match find_field(&root, &["trade_ad_id", "tradeAdId", "ad_id", "id"]) {
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        Some(serde_json::Value::String(s)) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => None,
+    };
+    let created = find_field(&root, &["created", "created_at", "timestamp", "posted"]);
+    posted.created_unix = match created {
+        Some(serde_json::Value::Number(n)) => n.as_u64(),
+        Some(serde_json::Value::String(s)) => s.trim().parse::<u64>().ok(),
+        _ => None,
+    };
+    posted
+}
+
+/// Heuristic shared by posting and token validation: does this response mean the
+/// _RoliVerification cookie was rejected?
+fn is_verification_failure(status: u16, body: &str) -> bool {
+    let lower = body.to_lowercase();
+    matches!(status, 401 | 403)
+        || lower.contains("verification")
+        || lower.contains("roli_verification")
+        || lower.contains("invalid token")
+        || lower.contains("not authenticated")
+}
+
+fn content_type_of(resp: &reqwest::Response) -> Option<String> {
+    resp.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Check whether a _RoliVerification cookie is still accepted by Rolimons.
+/// Sends an intentionally empty createad payload: the API rejects it as a bad request when the
+/// cookie is valid, and with a verification error when it is not, so no ad is ever created.
+pub async fn validate_roli_verification(roli_verification: &str) -> Result<bool, ApiError> {
+    if roli_verification.trim().is_empty() {
+        return Ok(false);
+    }
+    let cookie_header = format!("_RoliVerification={}", roli_verification);
+    // A token that can't even be sent as a header is certainly not valid
+    let Ok(cookie) = HeaderValue::from_str(&cookie_header) else {
+        return Ok(false);
+    };
+    rate_limit::acquire().await;
+    let resp = http::client()
+        .post(CREATE_AD_URL)
+        .header(USER_AGENT, http::browser_user_agent())
+        .header(ORIGIN, "https://www.rolimons.com")
+        .header(REFERER, "https://www.rolimons.com/tradeads")
+        .header(COOKIE, cookie)
+        .json(&json!({}))
+        .send()
+        .await?;
+
+    let status = resp.status().as_u16();
+    let content_type = content_type_of(&resp);
+    let text = resp.text().await.unwrap_or_default();
+    // a challenge page says nothing about the cookie either way
+    if is_challenge_page(content_type.as_deref(), &text) {
+        return Err(ApiError::BlockedByChallenge);
+    }
+    Ok(!is_verification_failure(status, &text))
+}
+
+/// A trade ad that is live on Rolimons.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActiveTradeAd {
+    pub ad_id: u64,
+    pub created_unix: u64,
+    pub offer_item_ids: Vec<u64>,
+    pub offer_robux: u64,
+    pub request_item_ids: Vec<u64>,
+    pub request_tags: Vec<String>,
+}
+
+impl ActiveTradeAd {
+    /// Whether this ad offers and requests the same things, ignoring order.
+    pub fn same_items(
+        &self,
+        offer_item_ids: &[u64],
+        request_item_ids: &[u64],
+        request_tags: &[String],
+    ) -> bool {
+        fn sorted<T: Ord + Clone>(v: &[T]) -> Vec<T> {
+            let mut v = v.to_vec();
+            v.sort();
+            v
+        }
+        let tags: Vec<String> = request_tags
+            .iter()
+            .map(|t| t.trim().to_lowercase())
+            .collect();
+        sorted(&self.offer_item_ids) == sorted(offer_item_ids)
+            && sorted(&self.request_item_ids) == sorted(request_item_ids)
+            && sorted(&self.request_tags) == sorted(&tags)
+    }
+}
+
+/// Parse one getrecentads entry:
+/// `[ad_id, created, player_id, username, {"items": [..], "robux": n}, {"items": [..], "tags": [..]}]`.
+fn parse_recent_ad(entry: &serde_json::Value) -> Option<(u64, ActiveTradeAd)> {
+    let ids = |v: &serde_json::Value| -> Vec<u64> {
+        v.as_array()
+            .map(|a| a.iter().filter_map(|id| id.as_u64()).collect())
+            .unwrap_or_default()
+    };
+    let fields = entry.as_array()?;
+    let offer = fields.get(4)?;
+    let request = fields.get(5)?;
+    let ad = ActiveTradeAd {
+        ad_id: fields.first()?.as_u64()?,
+        created_unix: fields.get(1)?.as_u64()?,
+        offer_item_ids: ids(&offer["items"]),
+        offer_robux: offer["robux"].as_u64().unwrap_or(0),
+        request_item_ids: ids(&request["items"]),
+        request_tags: request["tags"]
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|t| t.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    Some((fields.get(2)?.as_u64()?, ad))
+}
 
-    // Map request_tags to lowercase strings
-    let mapped_tags: Vec<String> = request_tags.iter().map(|tag| tag.to_lowercase()).collect();
+/// `player_id`'s ads from a getrecentads body, newest first.
+fn parse_player_trade_ads(body: &str, player_id: u64) -> Result<Vec<ActiveTradeAd>, ApiError> {
+    let root: serde_json::Value = serde_json::from_str(body)?;
+    let entries = root["trade_ads"]
+        .as_array()
+        .ok_or_else(|| ApiError::Parse("recent trade ads response has no trade_ads".to_string()))?;
+    let mut ads: Vec<ActiveTradeAd> = entries
+        .iter()
+        .filter_map(|entry| {
+            let parsed = parse_recent_ad(entry);
+            if parsed.is_none() {
+                log::debug!(
+                    "get_player_trade_ads: skipping unrecognised entry {}",
+                    entry
+                );
+            }
+            parsed
+        })
+        .filter(|(owner, _)| *owner == player_id)
+        .map(|(_, ad)| ad)
+        .collect();
+    ads.sort_by_key(|ad| std::cmp::Reverse(ad.created_unix));
+    Ok(ads)
+}
+
+/// Ads `player_id` currently has live, newest first (empty when there are none). Rolimons
+/// only lists live ads in its recent ads feed, so this reads that feed and keeps the player's.
+pub async fn get_player_trade_ads(player_id: u64) -> Result<Vec<ActiveTradeAd>, ApiError> {
+    rate_limit::acquire().await;
+    let resp = http::send_with_backoff(|| {
+        http::client()
+            .get(RECENT_ADS_URL)
+            .header(USER_AGENT, http::browser_user_agent())
+    })
+    .await?;
+    let content_type = content_type_of(&resp);
+    let resp = check_status(resp).await?;
+    let body = resp.text().await?;
+    if is_challenge_page(content_type.as_deref(), &body) {
+        return Err(ApiError::BlockedByChallenge);
+    }
+    let ads = parse_player_trade_ads(&body, player_id)?;
+    log::debug!(
+        "get_player_trade_ads: player {} has {} active ads",
+        player_id,
+        ads.len()
+    );
+    Ok(ads)
+}
+
+/// What `post_trade_ad_direct` would send, without sending it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeAdPreview {
+    pub url: String,
+    pub payload: serde_json::Value,
+    /// Header name -> value. The verification cookie is masked.
+    pub headers: BTreeMap<String, String>,
+}
+
+/// JSON body for createad.
+///
+/// Rolimons accepts 1-4 offered item ids and 1-4 requests, which can be item ids only, tags
+/// only, or a mix of both (see `validate_item_counts`). A request side that is empty is left
+/// out of the body rather than sent as `[]`, which Rolimons has been seen to reject for
+/// tag-only ads. Tags are trimmed, lowercased and de-duplicated as Rolimons expects.
+fn build_payload(
+    player_id: u64,
+    offer_item_ids: &[u64],
+    request_item_ids: &[u64],
+    request_tags: &[String],
+) -> serde_json::Value {
+    let mut mapped_tags: Vec<String> = Vec::with_capacity(request_tags.len());
+    for tag in request_tags.iter().map(|tag| tag.trim().to_lowercase()) {
+        if !tag.is_empty() && !mapped_tags.contains(&tag) {
+            mapped_tags.push(tag);
+        }
+    }
 
-    let payload = json!({
+    let mut payload = json!({
         "player_id": player_id,
         "offer_item_ids": offer_item_ids,
-        "request_item_ids": request_item_ids,
-        "request_tags": mapped_tags,
     });
+    if !request_item_ids.is_empty() {
+        payload["request_item_ids"] = json!(request_item_ids);
+    }
+    if !mapped_tags.is_empty() {
+        payload["request_tags"] = json!(mapped_tags);
+    }
+    payload
+}
 
+/// Browser-like headers for createad, with only the _RoliVerification cookie set.
+fn build_headers(roli_verification: &str) -> Result<HeaderMap, ApiError> {
     let mut headers = HeaderMap::new();
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-    headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36"));
+    let user_agent = HeaderValue::from_str(&http::browser_user_agent())
+        .unwrap_or_else(|_| HeaderValue::from_static(http::DEFAULT_BROWSER_USER_AGENT));
+    headers.insert(USER_AGENT, user_agent);
     headers.insert(
         ACCEPT,
         HeaderValue::from_static("application/json, text/plain, */*"),
@@ -48,40 +379,492 @@ pub async fn post_trade_ad_direct(
 
     // Send only _RoliVerification cookie
     let cookie_header = format!("_RoliVerification={}", roli_verification);
-    headers.insert(COOKIE, HeaderValue::from_str(&cookie_header).unwrap());
+    let cookie = HeaderValue::from_str(&cookie_header).map_err(|_| ApiError::Unauthorized)?;
+    headers.insert(COOKIE, cookie);
+    Ok(headers)
+}
 
-    let url = "https://api.rolimons.com/tradeads/v1/createad";
+/// Keep just enough of the cookie to tell two values apart.
+fn mask_secret(value: &str) -> String {
+    let prefix: String = value.chars().take(4).collect();
+    if value.chars().count() <= 4 {
+        "****".to_string()
+    } else {
+        format!("{}****", prefix)
+    }
+}
+
+/// Build the createad request exactly as `post_trade_ad_direct` would, without sending it.
+pub fn preview_trade_ad(
+    roli_verification: &str,
+    player_id: u64,
+    offer_item_ids: &[u64],
+    request_item_ids: &[u64],
+    request_tags: &[String],
+) -> Result<TradeAdPreview, ApiError> {
+    let payload = build_payload(player_id, offer_item_ids, request_item_ids, request_tags);
+    let header_map = build_headers(roli_verification)?;
+
+    let mut headers = BTreeMap::new();
+    for (name, value) in header_map.iter() {
+        let value = if name == COOKIE {
+            format!("_RoliVerification={}", mask_secret(roli_verification))
+        } else {
+            value.to_str().unwrap_or_default().to_string()
+        };
+        headers.insert(name.as_str().to_string(), value);
+    }
 
+    Ok(TradeAdPreview {
+        url: CREATE_AD_URL.to_string(),
+        payload,
+        headers,
+    })
+}
+
+/// Posts a trade ad to Rolimons using reqwest, setting the _RoliVerification cookie manually.
+/// Cooldown rejections come back as `ApiError::RateLimited` and a rejected cookie as
+/// `ApiError::Unauthorized`. Transient failures are retried per `options`.
+pub async fn post_trade_ad_direct(
+    roli_verification: &str,
+    player_id: u64,
+    offer_item_ids: Vec<u64>,
+    request_item_ids: Vec<u64>,
+    request_tags: Vec<String>,
+    options: PostOptions,
+) -> Result<PostedTradeAd, ApiError> {
+    post_trade_ad_with_client(
+        &http::client(),
+        roli_verification,
+        player_id,
+        offer_item_ids,
+        request_item_ids,
+        request_tags,
+        options,
+    )
+    .await
+}
+
+/// `post_trade_ad_direct` over a specific client, e.g. one routed through an ad's own proxy.
+pub async fn post_trade_ad_with_client(
+    client: &reqwest::Client,
+    roli_verification: &str,
+    player_id: u64,
+    offer_item_ids: Vec<u64>,
+    request_item_ids: Vec<u64>,
+    request_tags: Vec<String>,
+    options: PostOptions,
+) -> Result<PostedTradeAd, ApiError> {
+    post_trade_ad_to(
+        client,
+        CREATE_AD_URL,
+        roli_verification,
+        player_id,
+        offer_item_ids,
+        request_item_ids,
+        request_tags,
+        options,
+    )
+    .await
+}
+
+/// The createad call against any `url`, so tests can point it at a local server. Retries
+/// transient failures up to `options.max_retries` times with exponential backoff.
+#[allow(clippy::too_many_arguments)]
+async fn post_trade_ad_to(
+    client: &reqwest::Client,
+    url: &str,
+    roli_verification: &str,
+    player_id: u64,
+    offer_item_ids: Vec<u64>,
+    request_item_ids: Vec<u64>,
+    request_tags: Vec<String>,
+    options: PostOptions,
+) -> Result<PostedTradeAd, ApiError> {
+    let payload = build_payload(player_id, &offer_item_ids, &request_item_ids, &request_tags);
+    let headers = build_headers(roli_verification)?;
+
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        match send_post(client, url, &headers, &payload, player_id, options.timeout).await {
+            Err(e) if is_transient(&e) && attempt <= options.max_retries => {
+                let jitter_ms = rand::thread_rng().gen_range(0..500);
+                let wait = http::backoff_delay(attempt, None, jitter_ms);
+                log::warn!(
+                    "post_trade_ad: {}; retrying after {:?} (attempt {}/{})",
+                    e,
+                    wait,
+                    attempt,
+                    options.max_retries + 1
+                );
+                tokio::time::sleep(wait).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// One createad attempt, classifying the response.
+async fn send_post(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderMap,
+    payload: &serde_json::Value,
+    player_id: u64,
+    timeout: Duration,
+) -> Result<PostedTradeAd, ApiError> {
+    rate_limit::acquire().await;
     let resp = client
         .post(url)
-        .headers(headers)
-        .json(&payload)
+        .headers(headers.clone())
+        .json(payload)
+        .timeout(timeout)
         .send()
         .await?;
 
     let status = resp.status();
+    let retry_after = retry_after_header(&resp);
+    let content_type = content_type_of(&resp);
     let bytes = resp.bytes().await.unwrap_or_default();
     let text = match String::from_utf8(bytes.to_vec()) {
         Ok(t) => t,
         Err(_) => format!("<non-UTF8 response: {} bytes>", bytes.len()),
     };
 
+    // Checked first: challenge pages often come with a 403 and would otherwise look like an
+    // expired cookie, or with a 200 and look like a successful post.
+    if is_challenge_page(content_type.as_deref(), &text) {
+        log::warn!(
+            "post_trade_ad: got an HTML challenge page (HTTP {}) instead of JSON",
+            status.as_u16()
+        );
+        return Err(ApiError::BlockedByChallenge);
+    }
+
     // Detect common verification-related failures so callers can act only on those.
-    let lower = text.to_lowercase();
-    let verification_related = matches!(status.as_u16(), 401 | 403)
-        || lower.contains("verification")
-        || lower.contains("roli_verification")
-        || lower.contains("invalid token")
-        || lower.contains("not authenticated");
+    let verification_related = is_verification_failure(status.as_u16(), &text);
 
     if !status.is_success() {
+        // Respect the server's cooldown when it tells us how long to wait.
+        let cooldown = parse_cooldown_secs(&text).or(if status.as_u16() == 429 {
+            retry_after
+        } else {
+            None
+        });
+        if let Some(secs) = cooldown {
+            record_cooldown(player_id, secs);
+            return Err(ApiError::RateLimited {
+                retry_after: Some(secs),
+            });
+        }
         if verification_related {
-            // Special error marker so the runner/frontend can detect verification expiration
-            return Err(anyhow!("verification_required: {} - {}", status, text));
+            // Lets the runner/frontend detect verification expiration
+            return Err(ApiError::Unauthorized);
         }
-        return Err(anyhow!("Failed to post trade ad: {} - {}", status, text));
+        return Err(ApiError::from_status(status.as_u16(), retry_after, &text));
     }
 
-    // Return a concise, UI-friendly success string (frontend will display this)
-    Ok("trade ad post success".to_string())
+    // a lowered test minimum (ROLI_MIN_INTERVAL_SECS) shortens the assumed cooldown too
+    record_cooldown(
+        player_id,
+        POST_COOLDOWN_SECS.min(crate::ads_storage::min_interval_secs()),
+    );
+    Ok(parse_post_response(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cooldown_secs() {
+        let body = r#"{"success":false,"message":"Ad creation cooldown","cooldown":742}"#;
+        assert_eq!(parse_cooldown_secs(body), Some(742));
+
+        let nested = r#"{"success":false,"data":{"seconds_remaining":"90"}}"#;
+        assert_eq!(parse_cooldown_secs(nested), Some(90));
+    }
+
+    #[test]
+    fn test_parse_post_response() {
+        let posted =
+            parse_post_response(r#"{"success":true,"trade_ad_id":123456,"created":1700000000}"#);
+        assert_eq!(posted.ad_id.as_deref(), Some("123456"));
+        assert_eq!(posted.created_unix, Some(1700000000));
+        assert_eq!(posted.to_string(), "trade ad post success");
+
+        let nested = parse_post_response(r#"{"success":true,"data":{"id":"abc"}}"#);
+        assert_eq!(nested.ad_id.as_deref(), Some("abc"));
+        assert_eq!(nested.created_unix, None);
+
+        let plain = parse_post_response("OK");
+        assert_eq!(plain.ad_id, None);
+        assert_eq!(plain.message, "trade ad post success");
+    }
+
+    #[test]
+    fn test_parse_player_trade_ads() {
+        let body = r#"{"success": true, "trade_ad_count": 4, "trade_ads": [
+            [101, 1700000000, 42, "main", {"items": [1028606, 20573078]}, {"tags": ["any", "demand"]}],
+            [102, 1700000300, 7, "other", {"items": [1]}, {"items": [2]}],
+            [103, 1700000600, 42, "main", {"items": [1028606], "robux": 500}, {"items": [20573078], "tags": ["upgrade"]}],
+            "not an ad"
+        ]}"#;
+        let ads = parse_player_trade_ads(body, 42).unwrap();
+        assert_eq!(
+            ads.iter().map(|a| a.ad_id).collect::<Vec<_>>(),
+            vec![103, 101]
+        );
+        assert_eq!(ads[0].offer_robux, 500);
+        assert_eq!(ads[0].request_item_ids, vec![20573078]);
+        assert_eq!(ads[1].request_item_ids, Vec::<u64>::new());
+        assert!(ads[1].same_items(
+            &[20573078, 1028606],
+            &[],
+            &["Demand".to_string(), "any".to_string()]
+        ));
+        assert!(!ads[1].same_items(&[1028606], &[], &["any".to_string(), "demand".to_string()]));
+
+        assert!(parse_player_trade_ads(body, 9).unwrap().is_empty());
+        let empty = r#"{"success": true, "trade_ad_count": 0, "trade_ads": []}"#;
+        assert!(parse_player_trade_ads(empty, 42).unwrap().is_empty());
+        assert!(parse_player_trade_ads(r#"{"success": false}"#, 42).is_err());
+    }
+
+    #[test]
+    fn test_parse_cooldown_secs_absent() {
+        assert_eq!(parse_cooldown_secs(r#"{"success":false,"code":400}"#), None);
+        assert_eq!(parse_cooldown_secs("<html>nope</html>"), None);
+    }
+
+    #[test]
+    fn test_cooldown_error_carries_retry_after() {
+        let err = ApiError::RateLimited {
+            retry_after: Some(60),
+        };
+        assert_eq!(err.retry_after_secs(), Some(60));
+        assert!(!err.to_string().contains("verification"));
+    }
+
+    #[test]
+    fn test_post_cooldown_remaining() {
+        // ids far outside real Roblox ids so other tests never touch them
+        assert_eq!(post_cooldown_remaining(u64::MAX), 0);
+        record_cooldown(u64::MAX - 1, 120);
+        let remaining = post_cooldown_remaining(u64::MAX - 1);
+        assert!(remaining > 115 && remaining <= 120);
+    }
+
+    #[test]
+    fn test_preview_matches_payload_and_masks_cookie() {
+        let preview =
+            preview_trade_ad("abcdefgh123", 42, &[1, 2], &[3], &["Upgrade".to_string()]).unwrap();
+        assert_eq!(preview.url, CREATE_AD_URL);
+        assert_eq!(preview.payload["offer_item_ids"], json!([1, 2]));
+        assert_eq!(preview.payload["request_item_ids"], json!([3]));
+        assert_eq!(preview.payload["request_tags"], json!(["upgrade"]));
+        assert_eq!(preview.headers["cookie"], "_RoliVerification=abcd****");
+        assert!(!preview.headers["cookie"].contains("123"));
+    }
+
+    #[test]
+    fn test_payload_omits_empty_request_side() {
+        let tags = vec![" Any ".to_string(), "demand".to_string(), "ANY".to_string()];
+        let tag_only = build_payload(42, &[1028606], &[], &tags);
+        assert_eq!(
+            tag_only,
+            json!({ "player_id": 42, "offer_item_ids": [1028606], "request_tags": ["any", "demand"] })
+        );
+
+        let items_only = build_payload(42, &[1028606], &[20573078], &[]);
+        assert_eq!(
+            items_only,
+            json!({ "player_id": 42, "offer_item_ids": [1028606], "request_item_ids": [20573078] })
+        );
+
+        let mixed = build_payload(42, &[1], &[2], &["rap".to_string()]);
+        assert_eq!(mixed["request_item_ids"], json!([2]));
+        assert_eq!(mixed["request_tags"], json!(["rap"]));
+    }
+
+    /// Answer a single request on a local port with `status` and `body`, returning the URL.
+    fn serve_once(status: &'static str, body: &'static str) -> String {
+        serve_once_as(status, "application/json", body)
+    }
+
+    fn serve_once_as(
+        status: &'static str,
+        content_type: &'static str,
+        body: &'static str,
+    ) -> String {
+        serve_responses(vec![(status, content_type, body)]).0
+    }
+
+    /// Answer one request per entry, in order. The counter tracks how many were answered.
+    fn serve_responses(
+        responses: Vec<(&'static str, &'static str, &'static str)>,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::io::{Read, Write};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/tradeads/v1/createad",
+            listener.local_addr().unwrap()
+        );
+        let served = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        std::thread::spawn(move || {
+            for (status, content_type, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                // read the whole request (headers, then Content-Length bytes of body) before replying
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_lowercase();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let len = text
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .and_then(|v| v.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + len {
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    body.len(),
+                    body
+                );
+                // counted before replying so the client can't observe the reply first
+                counter.fetch_add(1, Ordering::SeqCst);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, served)
+    }
+
+    async fn post_to(url: &str) -> Result<PostedTradeAd, ApiError> {
+        post_with(url, PostOptions::default()).await
+    }
+
+    async fn post_with(url: &str, options: PostOptions) -> Result<PostedTradeAd, ApiError> {
+        // no_proxy: a proxy from the environment must not intercept the local server
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        post_trade_ad_to(
+            &client,
+            url,
+            "abcdefgh12345678",
+            // ids far outside real Roblox ids so the cooldown map isn't shared with other tests
+            u64::MAX - 10,
+            vec![1],
+            vec![],
+            vec!["any".to_string()],
+            options,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_post_success() {
+        let url = serve_once("200 OK", r#"{"success":true,"trade_ad_id":987}"#);
+        let posted = post_to(&url).await.unwrap();
+        assert_eq!(posted.ad_id.as_deref(), Some("987"));
+        assert_eq!(posted.message, "trade ad post success");
+    }
+
+    #[tokio::test]
+    async fn test_post_unauthorized_statuses() {
+        for status in ["401 Unauthorized", "403 Forbidden"] {
+            let url = serve_once(status, r#"{"success":false}"#);
+            assert_eq!(post_to(&url).await.unwrap_err(), ApiError::Unauthorized);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_invalid_token_body() {
+        let url = serve_once(
+            "400 Bad Request",
+            r#"{"success":false,"message":"Invalid token"}"#,
+        );
+        assert_eq!(post_to(&url).await.unwrap_err(), ApiError::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_post_server_error() {
+        let url = serve_once("500 Internal Server Error", r#"{"success":false}"#);
+        let err = post_to(&url).await.unwrap_err();
+        assert_eq!(err, ApiError::ServerError(500));
+        assert_eq!(err.kind(), "server_error");
+    }
+
+    #[tokio::test]
+    async fn test_post_retries_server_error() {
+        let (url, served) = serve_responses(vec![
+            (
+                "500 Internal Server Error",
+                "application/json",
+                r#"{"success":false}"#,
+            ),
+            (
+                "200 OK",
+                "application/json",
+                r#"{"success":true,"trade_ad_id":988}"#,
+            ),
+        ]);
+        let options = PostOptions {
+            max_retries: 1,
+            ..Default::default()
+        };
+        let posted = post_with(&url, options).await.unwrap();
+        assert_eq!(posted.ad_id.as_deref(), Some("988"));
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_does_not_retry_forbidden() {
+        let (url, served) = serve_responses(vec![
+            ("403 Forbidden", "application/json", r#"{"success":false}"#),
+            ("200 OK", "application/json", r#"{"success":true}"#),
+        ]);
+        let options = PostOptions {
+            max_retries: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            post_with(&url, options).await.unwrap_err(),
+            ApiError::Unauthorized
+        );
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&ApiError::ServerError(502)));
+        assert!(is_transient(&ApiError::Network("timeout".to_string())));
+        assert!(!is_transient(&ApiError::Unauthorized));
+        assert!(!is_transient(&ApiError::BlockedByChallenge));
+        assert!(!is_transient(&ApiError::RateLimited {
+            retry_after: Some(60)
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_post_challenge_page() {
+        let page = "<!DOCTYPE html><html><head><title>Just a moment...</title></head></html>";
+        let url = serve_once_as("403 Forbidden", "text/html; charset=UTF-8", page);
+        let err = post_to(&url).await.unwrap_err();
+        assert_eq!(err, ApiError::BlockedByChallenge);
+        assert!(err.to_string().starts_with("blocked_by_challenge: "));
+        assert!(!err.to_string().contains("<html"));
+    }
 }
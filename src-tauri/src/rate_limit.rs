@@ -0,0 +1,109 @@
+// rate_limit.rs
+// Responsibility: Token bucket shared by every Rolimons API call, so many running ads and
+// the inventory fan-out are smoothed into a steady request rate instead of bursting into 429s.
+
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_REQUESTS_PER_MINUTE: u64 = 60;
+/// Most requests that may go out back to back after an idle period.
+const MAX_BURST: f64 = 5.0;
+
+/// Requests per minute, adjustable at runtime. Zero disables the limiter.
+static REQUESTS_PER_MINUTE: AtomicU64 = AtomicU64::new(DEFAULT_REQUESTS_PER_MINUTE);
+
+pub fn requests_per_minute() -> u64 {
+    REQUESTS_PER_MINUTE.load(Ordering::Relaxed)
+}
+
+pub fn set_requests_per_minute(n: u64) {
+    REQUESTS_PER_MINUTE.store(n, Ordering::Relaxed);
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(now: Instant) -> Self {
+        Bucket {
+            tokens: MAX_BURST,
+            last_refill: now,
+        }
+    }
+
+    /// Take a token if one is available, otherwise return how long until the next one is.
+    fn try_take(&mut self, now: Instant, per_minute: u64) -> Result<(), Duration> {
+        let per_sec = per_minute as f64 / 60.0;
+        let capacity = MAX_BURST.min(per_minute as f64).max(1.0);
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / per_sec))
+        }
+    }
+}
+
+static BUCKET: Lazy<Mutex<Bucket>> = Lazy::new(|| Mutex::new(Bucket::new(Instant::now())));
+
+/// Wait for a permit before sending a Rolimons request.
+pub async fn acquire() {
+    loop {
+        let per_minute = requests_per_minute();
+        if per_minute == 0 {
+            return;
+        }
+        let wait = match BUCKET.lock().unwrap().try_take(Instant::now(), per_minute) {
+            Ok(()) => return,
+            Err(wait) => wait,
+        };
+        log::debug!("rate_limit: waiting {:?} for a request permit", wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_paces() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(start);
+        for _ in 0..5 {
+            assert!(bucket.try_take(start, 60).is_ok());
+        }
+        let wait = bucket.try_take(start, 60).unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+        // one token per second at 60/min
+        assert!(bucket.try_take(start + Duration::from_secs(1), 60).is_ok());
+        assert!(bucket.try_take(start + Duration::from_secs(1), 60).is_err());
+    }
+
+    #[test]
+    fn test_bucket_never_exceeds_capacity() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(start);
+        let later = start + Duration::from_secs(3600);
+        let taken = (0..20)
+            .filter(|_| bucket.try_take(later, 60).is_ok())
+            .count();
+        assert_eq!(taken, 5);
+
+        // a slow limit caps the burst at its own rate
+        let mut slow = Bucket::new(start);
+        assert!(slow.try_take(later, 2).is_ok());
+        assert!(slow.try_take(later, 2).is_ok());
+        assert!(slow.try_take(later, 2).is_err());
+    }
+}
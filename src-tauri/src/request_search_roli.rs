@@ -1,9 +1,18 @@
 // catalog.rs
 // Responsibility: Fetch Rolimons item details and provide ItemInfo type.
 
-use anyhow::{anyhow, Result};
-use reqwest::header::USER_AGENT;
+use super::thumbnails::ThumbSize;
+use crate::api_error::{check_status, ApiError};
+use crate::http;
+use crate::rate_limit;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+type Result<T> = std::result::Result<T, ApiError>;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ItemInfo {
@@ -14,227 +23,784 @@ pub struct ItemInfo {
     pub value: u64,
     // data URL (e.g. data:image/webp;base64,...) or remote URL for the item's thumbnail
     pub thumbnail: Option<String>,
+    // Rolimons demand / trend ratings (0 = terrible .. 4 = amazing), None when unassigned
+    #[serde(default)]
+    pub demand: Option<i8>,
+    #[serde(default)]
+    pub trend: Option<i8>,
+    #[serde(default)]
+    pub projected: bool,
+    #[serde(default)]
+    pub rare: bool,
+    // Rolimons has no value for the item; `value` then holds its RAP (0 under ValueOnly)
+    #[serde(default)]
+    pub value_unknown: bool,
 }
 
-/// Fetches Rolimons item details from their public item API, maps indices to fields,
-/// sorts by RAP descending and returns a page of items plus total count.
-pub async fn fetch_item_details(
-    page: usize,
-    per_page: usize,
-    search: Option<String>,
-) -> Result<(Vec<ItemInfo>, usize)> {
-    let fetch_start = std::time::Instant::now();
-    eprintln!("fetch_item_details: starting (page={}, per_page={}, search={:?})", page, per_page, search);
-    // The public Rolimons item details endpoint (v2)
-    let url = "https://api.rolimons.com/items/v2/itemdetails";
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+/// Optional catalog filters applied after the name/abbreviation search. All set
+/// fields must match (AND semantics).
+#[derive(Clone, Debug, Default)]
+pub struct ItemFilter {
+    pub min_value: Option<u64>,
+    pub max_value: Option<u64>,
+    /// Items without a demand rating are excluded when this is set.
+    pub min_demand: Option<i8>,
+}
 
-    let resp = client
-        .get(url)
-        .header(USER_AGENT, "rolimons-fetcher/1.0")
-        .send()
-        .await?;
+impl ItemFilter {
+    pub fn matches(&self, item: &ItemInfo) -> bool {
+        if self.min_value.is_some_and(|min| item.value < min) {
+            return false;
+        }
+        if self.max_value.is_some_and(|max| item.value > max) {
+            return false;
+        }
+        if let Some(min) = self.min_demand {
+            if item.demand.is_none_or(|d| d < min) {
+                return false;
+            }
+        }
+        true
+    }
+}
 
-    if !resp.status().is_success() {
-        return Err(anyhow!("Failed to fetch item details: {}", resp.status()));
+/// Catalog sort key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemSort {
+    #[default]
+    Value,
+    Rap,
+    Name,
+    Id,
+}
+
+/// Which figure catalog results rank by and show as `value`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueSource {
+    /// Rolimons value, or RAP for items without one.
+    #[default]
+    ValueOrRap,
+    RapOnly,
+    /// Rolimons value only. Items without one are kept, shown as 0 with `value_unknown` set.
+    ValueOnly,
+}
+
+impl ValueSource {
+    fn value_of(self, item: &ItemInfo) -> u64 {
+        match self {
+            ValueSource::ValueOrRap => item.value,
+            ValueSource::RapOnly => item.rap,
+            ValueSource::ValueOnly if item.value_unknown => 0,
+            ValueSource::ValueOnly => item.value,
+        }
     }
 
-    let body = resp.text().await.unwrap_or_default();
-    let root: serde_json::Value = serde_json::from_str(&body)?;
+    /// Rewrite `item.value` to the figure this source shows.
+    fn apply(self, item: &mut ItemInfo) {
+        item.value = self.value_of(item);
+    }
+}
 
-    // Extract items object
-    let items_value = match root.get("items") {
-        Some(v) => v,
-        None => return Ok((Vec::new(), 0)),
-    };
-    let items_map = match items_value {
-        serde_json::Value::Object(m) => m,
-        _ => return Ok((Vec::new(), 0)),
-    };
+/// How the catalog search string is compared against items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// Name or abbreviation contains the search, case-insensitively.
+    #[default]
+    Contains,
+    /// Abbreviation equals the search, case-insensitively.
+    ExactAbbrev,
+    /// Name equals the search, case-insensitively.
+    ExactName,
+    /// The search is a catalog id.
+    ById,
+}
 
-    let mut items: Vec<ItemInfo> = Vec::with_capacity(items_map.len());
+impl MatchMode {
+    /// Whether `item` matches `query`, which must already be trimmed and lowercased.
+    fn matches(self, query: &str, item: &ItemInfo) -> bool {
+        let abbrev = item.abbreviation.as_ref().map(|a| a.to_lowercase());
+        match self {
+            MatchMode::Contains => {
+                item.name.to_lowercase().contains(query)
+                    || abbrev.is_some_and(|a| a.contains(query))
+            }
+            MatchMode::ExactAbbrev => abbrev.is_some_and(|a| a == query),
+            MatchMode::ExactName => item.name.to_lowercase() == query,
+            MatchMode::ById => query.parse::<u64>().is_ok_and(|id| id == item.id),
+        }
+    }
+}
 
-    for (key, val) in items_map.iter() {
-        let id: u64 = match key.parse() {
-            Ok(v) => v,
-            Err(_) => continue,
+/// Sort items by `sort_by`, descending when `desc` is set. Value sorts compare the figure
+/// `value_source` picks. Ties fall back to id ascending so pages stay stable.
+fn sort_items(items: &mut [&ItemInfo], sort_by: ItemSort, desc: bool, value_source: ValueSource) {
+    items.sort_by(|a, b| {
+        let ord = match sort_by {
+            ItemSort::Value => value_source.value_of(a).cmp(&value_source.value_of(b)),
+            ItemSort::Rap => a.rap.cmp(&b.rap),
+            ItemSort::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            ItemSort::Id => a.id.cmp(&b.id),
         };
+        let ord = if desc { ord.reverse() } else { ord };
+        ord.then(a.id.cmp(&b.id))
+    });
+}
 
-        if let serde_json::Value::Array(arr) = val {
-            let name = arr
-                .get(0)
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_string();
-            let abbr = arr
-                .get(1)
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-                .filter(|s| !s.is_empty());
-            let rap = arr.get(2).and_then(|v| v.as_i64()).unwrap_or(0) as i64;
-            let value_raw = arr.get(3).and_then(|v| v.as_i64()).unwrap_or(-1) as i64;
-            let rap_u = if rap < 0 { 0 } else { rap as u64 };
-            let value_u = if value_raw < 0 {
-                rap_u
-            } else {
-                value_raw as u64
-            };
+/// Parsed Rolimons catalog keyed by item id, shared between callers.
+type ItemMap = Arc<HashMap<u64, ItemInfo>>;
+
+/// In-memory cache with TTL for the parsed catalog, so paging and repeated searches
+/// don't re-download and re-parse the whole itemdetails blob.
+static ITEM_CACHE: Lazy<RwLock<(Instant, ItemMap)>> = Lazy::new(|| {
+    RwLock::new((
+        Instant::now() - Duration::from_secs(3600),
+        Arc::new(HashMap::new()),
+    ))
+});
+const ITEM_TTL: Duration = Duration::from_secs(60 * 5); // 5 minutes
+
+/// Rating at `idx` (demand / trend). Rolimons uses -1 for "not assigned".
+fn rating_at(arr: &[serde_json::Value], idx: usize) -> Option<i8> {
+    arr.get(idx)
+        .and_then(|v| v.as_i64())
+        .filter(|v| (0..=i8::MAX as i64).contains(v))
+        .map(|v| v as i8)
+}
+
+/// Flag at `idx` (projected / rare). Rolimons uses 1 for set and -1 for unset.
+fn flag_at(arr: &[serde_json::Value], idx: usize) -> bool {
+    arr.get(idx).and_then(|v| v.as_i64()) == Some(1)
+}
 
-            let item = ItemInfo {
-                id,
-                name,
-                abbreviation: abbr,
-                rap: rap_u,
-                value: value_u,
-                thumbnail: None,
+/// Map one `itemdetails` array entry to an ItemInfo. Layout:
+/// [name, acronym, rap, value, default_value, demand, trend, projected, hyped, rare]
+fn parse_item_entry(id: u64, arr: &[serde_json::Value]) -> ItemInfo {
+    let name = arr
+        .first()
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let abbr = arr
+        .get(1)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .filter(|s| !s.is_empty());
+    let rap = arr.get(2).and_then(|v| v.as_i64()).unwrap_or(0);
+    let value_raw = arr.get(3).and_then(|v| v.as_i64()).unwrap_or(-1);
+    let rap_u = if rap < 0 { 0 } else { rap as u64 };
+    let value_u = if value_raw < 0 {
+        rap_u
+    } else {
+        value_raw as u64
+    };
+
+    ItemInfo {
+        id,
+        name,
+        abbreviation: abbr,
+        rap: rap_u,
+        value: value_u,
+        thumbnail: None,
+        demand: rating_at(arr, 5),
+        trend: rating_at(arr, 6),
+        projected: flag_at(arr, 7),
+        rare: flag_at(arr, 9),
+        value_unknown: value_raw < 0,
+    }
+}
+
+/// Return the parsed catalog, fetching it from Rolimons when the cache is stale
+/// or `force_refresh` is set.
+async fn load_item_map(force_refresh: bool) -> Result<ItemMap> {
+    if !force_refresh {
+        if let Ok(cache_guard) = ITEM_CACHE.read() {
+            let (ts, ref map) = &*cache_guard;
+            if ts.elapsed() < ITEM_TTL && !map.is_empty() {
+                log::debug!(
+                    "item details: cache hit ({} entries, age {:?})",
+                    map.len(),
+                    ts.elapsed()
+                );
+                return Ok(Arc::clone(map));
+            }
+        }
+    }
+
+    let start = Instant::now();
+    // The public Rolimons item details endpoint (v2)
+    let url = "https://api.rolimons.com/items/v2/itemdetails";
+    rate_limit::acquire().await;
+    let resp = http::client().get(url).send().await?;
+    let resp = check_status(resp).await?;
+
+    let body = resp.text().await.unwrap_or_default();
+    let root: serde_json::Value = serde_json::from_str(&body)?;
+
+    let mut map: HashMap<u64, ItemInfo> = HashMap::new();
+    if let Some(serde_json::Value::Object(items_map)) = root.get("items") {
+        map.reserve(items_map.len());
+        for (key, val) in items_map.iter() {
+            let id: u64 = match key.parse() {
+                Ok(v) => v,
+                Err(_) => continue,
             };
-            items.push(item);
+            if let serde_json::Value::Array(arr) = val {
+                map.insert(id, parse_item_entry(id, arr));
+            }
         }
     }
 
-    // Optional filtering by search (match name or abbreviation)
-    let filtered: Vec<ItemInfo> = if let Some(q) = search {
-        let ql = q.to_lowercase();
-        items
-            .into_iter()
-            .filter(|it| {
-                it.name.to_lowercase().contains(&ql)
-                    || it
-                        .abbreviation
-                        .as_ref()
-                        .map(|a| a.to_lowercase().contains(&ql))
-                        .unwrap_or(false)
-            })
-            .collect()
-    } else {
-        items
-    };
+    log::debug!(
+        "item details: fetched and parsed {} items in {:?}",
+        map.len(),
+        start.elapsed()
+    );
+    let map = Arc::new(map);
+    if let Ok(mut cache_guard) = ITEM_CACHE.write() {
+        *cache_guard = (Instant::now(), Arc::clone(&map));
+    }
+    Ok(map)
+}
 
-    // Sort by value desc (prefer higher value items first for requests)
-    let mut sorted = filtered;
-    sorted.sort_by(|a, b| b.value.cmp(&a.value));
+/// Drop the cached catalog. Returns the number of items removed.
+pub fn clear_item_cache() -> usize {
+    match ITEM_CACHE.write() {
+        Ok(mut cache_guard) => {
+            let n = cache_guard.1.len();
+            *cache_guard = (
+                Instant::now() - Duration::from_secs(3600),
+                Arc::new(HashMap::new()),
+            );
+            n
+        }
+        Err(_) => 0,
+    }
+}
+
+/// Re-download the catalog regardless of cache age. Returns the number of items.
+pub async fn refresh_item_cache() -> Result<usize> {
+    let map = load_item_map(true).await?;
+    Ok(map.len())
+}
+
+/// Returns Rolimons item details (cached for ITEM_TTL), filtered by search (compared
+/// according to `match_mode`) and `filter`, sorted by `sort_by` and paged, plus the
+/// filtered total count. `value_source` decides the figure value sorts use and that
+/// returned items carry as `value`.
+#[allow(clippy::too_many_arguments)]
+pub async fn fetch_item_details(
+    page: usize,
+    per_page: usize,
+    search: Option<String>,
+    match_mode: MatchMode,
+    filter: &ItemFilter,
+    sort_by: ItemSort,
+    sort_desc: bool,
+    value_source: ValueSource,
+) -> Result<(Vec<ItemInfo>, usize)> {
+    let fetch_start = std::time::Instant::now();
+    log::debug!(
+        "fetch_item_details: starting (page={}, per_page={}, search={:?})",
+        page,
+        per_page,
+        search
+    );
+    let item_map = load_item_map(false).await?;
+
+    // Optional filtering by search (name, abbreviation or id depending on match_mode)
+    let ql = search.map(|q| q.trim().to_lowercase());
+    let mut sorted: Vec<&ItemInfo> = item_map
+        .values()
+        .filter(|it| match &ql {
+            Some(ql) => match_mode.matches(ql, it),
+            None => true,
+        })
+        .filter(|it| filter.matches(it))
+        .collect();
+
+    // Sort before paging so each page is a slice of the full ordering
+    sort_items(&mut sorted, sort_by, sort_desc, value_source);
 
     let total = sorted.len();
     let start = page.saturating_sub(1) * per_page;
-    let end = std::cmp::min(start + per_page, total);
+    let end = std::cmp::min(start.saturating_add(per_page), total);
     let page_items = if start >= total {
         Vec::new()
     } else {
-        let mut page_slice: Vec<ItemInfo> = sorted[start..end].to_vec();
-        match super::thumbnails::fetch_thumbnails_map(&client).await {
+        let mut page_slice: Vec<ItemInfo> = sorted[start..end]
+            .iter()
+            .map(|it| {
+                let mut item = (*it).clone();
+                value_source.apply(&mut item);
+                item
+            })
+            .collect();
+        let page_ids: Vec<u64> = page_slice.iter().map(|it| it.id).collect();
+        match super::thumbnails::fetch_thumbnails_for(&page_ids, http::DEFAULT_TIMEOUT).await {
             Ok(map) => {
-                eprintln!("thumbnails: helper returned {} entries", map.len());
+                log::debug!("thumbnails: helper returned {} entries", map.len());
                 for it in page_slice.iter_mut() {
                     let key = it.id.to_string();
                     it.thumbnail = map.get(&key).cloned();
                 }
             }
             Err(e) => {
-                eprintln!("thumbnails: helper error: {}", e);
+                log::warn!("thumbnails: helper error: {}", e);
             }
         }
         page_slice
     };
 
-    eprintln!("fetch_item_details: returning {} items (total={}) in {:?}", page_items.len(), total, fetch_start.elapsed());
+    log::debug!(
+        "fetch_item_details: returning {} items (total={}) in {:?}",
+        page_items.len(),
+        total,
+        fetch_start.elapsed()
+    );
     Ok((page_items, total))
 }
 
-/// Fetch a small list of items by their catalog IDs. Returns the ItemInfo list (no paging).
-pub async fn fetch_items_by_ids(ids: Vec<u64>) -> Result<Vec<ItemInfo>> {
+/// Make sure the catalog cache is loaded, so a later lookup doesn't have to wait for it.
+pub async fn warm_item_cache() -> Result<()> {
+    load_item_map(false).await.map(|_| ())
+}
+
+/// Fetch a small list of items by their catalog IDs with thumbnails of the given size.
+/// Returns the ItemInfo list (no paging).
+pub async fn fetch_items_by_ids(ids: Vec<u64>, thumb_size: ThumbSize) -> Result<Vec<ItemInfo>> {
     let start = std::time::Instant::now();
-    eprintln!("fetch_items_by_ids: starting for {} ids", ids.len());
+    log::debug!("fetch_items_by_ids: starting for {} ids", ids.len());
     // Short-circuit empty
     if ids.is_empty() {
         return Ok(Vec::new());
     }
 
-    // Fetch the Rolimons itemdetails JSON once and pick only requested ids (v2)
-    let url = "https://api.rolimons.com/items/v2/itemdetails";
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+    // Load the catalog and the thumbnail map side by side; on a cold cache these are the
+    // two big downloads and neither needs the other.
+    let (item_map, thumbs) = tokio::join!(
+        load_item_map(false),
+        super::thumbnails::fetch_thumbnails_sized(&ids, thumb_size, http::DEFAULT_TIMEOUT)
+    );
+    let item_map = item_map?;
 
-    let resp = client
-        .get(url)
-        .header(USER_AGENT, "rolimons-fetcher/1.0")
-        .send()
-        .await?;
+    // Pick only requested ids out of the shared catalog cache
+    let mut out: Vec<ItemInfo> = ids
+        .into_iter()
+        .filter_map(|id| item_map.get(&id).cloned())
+        .collect();
 
-    if !resp.status().is_success() {
-        return Err(anyhow!("Failed to fetch item details: {}", resp.status()));
+    // attach thumbnails for requested ids
+    match thumbs {
+        Ok(map) => {
+            for it in out.iter_mut() {
+                let key = it.id.to_string();
+                it.thumbnail = map.get(&key).cloned();
+            }
+        }
+        Err(e) => {
+            log::warn!("thumbnails: helper error: {}", e);
+        }
     }
 
-    let body = resp.text().await.unwrap_or_default();
-    let root: serde_json::Value = serde_json::from_str(&body)?;
-    let items_value = match root.get("items") {
-        Some(v) => v,
-        None => return Ok(Vec::new()),
-    };
-    let items_map = match items_value {
-        serde_json::Value::Object(m) => m,
-        _ => return Ok(Vec::new()),
-    };
+    log::debug!(
+        "fetch_items_by_ids: returning {} items in {:?}",
+        out.len(),
+        start.elapsed()
+    );
+    Ok(out)
+}
 
-    let mut out: Vec<ItemInfo> = Vec::new();
-    for id in ids.into_iter() {
-        let key = id.to_string();
-        if let Some(val) = items_map.get(&key) {
-            if let serde_json::Value::Array(arr) = val {
-                let name = arr
-                    .get(0)
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .to_string();
-                let abbr = arr
-                    .get(1)
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .filter(|s| !s.is_empty());
-                let rap = arr.get(2).and_then(|v| v.as_i64()).unwrap_or(0) as i64;
-                let value_raw = arr.get(3).and_then(|v| v.as_i64()).unwrap_or(-1) as i64;
-                let rap_u = if rap < 0 { 0 } else { rap as u64 };
-                let value_u = if value_raw < 0 {
-                    rap_u
-                } else {
-                    value_raw as u64
-                };
-
-                out.push(ItemInfo {
-                    id,
-                    name,
-                    abbreviation: abbr,
-                    rap: rap_u,
-                    value: value_u,
-                    thumbnail: None,
-                });
+/// Totals for one side of a trade. Ids the catalog doesn't know are listed in `unknown_ids`
+/// and left out of the sums.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TradeSide {
+    pub items: Vec<ItemInfo>,
+    pub value: u64,
+    pub rap: u64,
+    pub unknown_ids: Vec<u64>,
+}
+
+/// Value and RAP on each side of a trade. Differences are request minus offer, so a positive
+/// number is a gain for whoever posts it; percents are relative to the offer and `None`
+/// when the offer side sums to 0.
+#[derive(Clone, Debug, Serialize)]
+pub struct TradeEvaluation {
+    pub offer: TradeSide,
+    pub request: TradeSide,
+    pub value_difference: i64,
+    pub value_percent: Option<f64>,
+    pub rap_difference: i64,
+    pub rap_percent: Option<f64>,
+    // tags carry no value, so they're only reported back
+    pub request_tags: Vec<String>,
+}
+
+fn trade_side(ids: &[u64], catalog: &HashMap<u64, ItemInfo>) -> TradeSide {
+    let mut side = TradeSide::default();
+    // an id listed twice is two copies of the item and counts twice
+    for id in ids {
+        match catalog.get(id) {
+            Some(item) => {
+                side.value += item.value;
+                side.rap += item.rap;
+                side.items.push(item.clone());
             }
+            None => side.unknown_ids.push(*id),
         }
     }
+    side
+}
 
-    // attach thumbnails for requested ids
-    match super::thumbnails::fetch_thumbnails_map(&client).await {
+fn percent_of(difference: i64, base: u64) -> Option<f64> {
+    (base > 0).then(|| difference as f64 / base as f64 * 100.0)
+}
+
+/// Sum both sides of a trade from already fetched catalog `items`.
+pub fn evaluate_trade(
+    offer_ids: &[u64],
+    request_ids: &[u64],
+    request_tags: Vec<String>,
+    items: &[ItemInfo],
+) -> TradeEvaluation {
+    let catalog: HashMap<u64, ItemInfo> = items.iter().map(|it| (it.id, it.clone())).collect();
+    let offer = trade_side(offer_ids, &catalog);
+    let request = trade_side(request_ids, &catalog);
+    let value_difference = request.value as i64 - offer.value as i64;
+    let rap_difference = request.rap as i64 - offer.rap as i64;
+    TradeEvaluation {
+        value_percent: percent_of(value_difference, offer.value),
+        rap_percent: percent_of(rap_difference, offer.rap),
+        offer,
+        request,
+        value_difference,
+        rap_difference,
+        request_tags,
+    }
+}
+
+/// Look up both sides of a trade in the catalog and total them (see `evaluate_trade`).
+pub async fn fetch_trade_evaluation(
+    offer_ids: Vec<u64>,
+    request_ids: Vec<u64>,
+    request_tags: Vec<String>,
+) -> Result<TradeEvaluation> {
+    let mut ids: Vec<u64> = offer_ids.iter().chain(&request_ids).copied().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let items = fetch_items_by_ids(ids, ThumbSize::Small).await?;
+    Ok(evaluate_trade(
+        &offer_ids,
+        &request_ids,
+        request_tags,
+        &items,
+    ))
+}
+
+/// Rating used to rank the trending list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendingMetric {
+    #[default]
+    Demand,
+    Trend,
+}
+
+impl TrendingMetric {
+    fn rating(self, item: &ItemInfo) -> Option<i8> {
+        match self {
+            TrendingMetric::Demand => item.demand,
+            TrendingMetric::Trend => item.trend,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            TrendingMetric::Demand => TrendingMetric::Trend,
+            TrendingMetric::Trend => TrendingMetric::Demand,
+        }
+    }
+}
+
+/// Top `limit` rated items by `metric`, highest first. Unrated items are left out; ties go to
+/// the other rating, then value, then id so the list stays stable.
+fn rank_trending(
+    item_map: &HashMap<u64, ItemInfo>,
+    metric: TrendingMetric,
+    limit: usize,
+) -> Vec<ItemInfo> {
+    let tiebreak = metric.other();
+    let mut rated: Vec<&ItemInfo> = item_map
+        .values()
+        .filter(|it| metric.rating(it).is_some())
+        .collect();
+    rated.sort_by(|a, b| {
+        metric
+            .rating(b)
+            .cmp(&metric.rating(a))
+            .then(tiebreak.rating(b).cmp(&tiebreak.rating(a)))
+            .then(b.value.cmp(&a.value))
+            .then(a.id.cmp(&b.id))
+    });
+    rated.into_iter().take(limit).cloned().collect()
+}
+
+/// Highest-rated items by demand or trend from the cached catalog, with thumbnails attached.
+pub async fn fetch_trending_items(metric: TrendingMetric, limit: usize) -> Result<Vec<ItemInfo>> {
+    let item_map = load_item_map(false).await?;
+    let mut out = rank_trending(&item_map, metric, limit);
+    if out.is_empty() {
+        return Ok(out);
+    }
+
+    let ids: Vec<u64> = out.iter().map(|it| it.id).collect();
+    match super::thumbnails::fetch_thumbnails_for(&ids, http::DEFAULT_TIMEOUT).await {
         Ok(map) => {
             for it in out.iter_mut() {
-                let key = it.id.to_string();
-                it.thumbnail = map.get(&key).cloned();
+                it.thumbnail = map.get(&it.id.to_string()).cloned();
             }
         }
         Err(e) => {
-            eprintln!("thumbnails: helper error: {}", e);
+            log::warn!("thumbnails: helper error: {}", e);
         }
     }
-
-    eprintln!("fetch_items_by_ids: returning {} items in {:?}", out.len(), start.elapsed());
     Ok(out)
 }
 
+/// An item whose value moved between two catalog fetches.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CatalogChange {
+    pub id: u64,
+    pub name: String,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub change_percent: f64,
+}
+
+/// How catalog changes are ordered, largest first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeSort {
+    /// Size of the percent change.
+    #[default]
+    Percent,
+    /// Size of the change in value.
+    Absolute,
+}
+
+/// Catalog values (id -> value) from the last `fetch_catalog_changes`. `taken_at` is unix seconds.
+#[derive(Serialize, Deserialize)]
+struct CatalogSnapshot {
+    taken_at: u64,
+    values: HashMap<u64, u64>,
+}
+
+fn catalog_snapshot_path() -> Option<PathBuf> {
+    let mut dir = dirs::data_local_dir()?;
+    dir.push("roli-trade-ad-automation");
+    std::fs::create_dir_all(&dir).ok()?;
+    dir.push("catalog_snapshot.json");
+    Some(dir)
+}
+
+fn read_catalog_snapshot(path: &std::path::Path) -> Option<CatalogSnapshot> {
+    if !path.exists() {
+        return None;
+    }
+    crate::atomic_file::read_with_backup(path, |raw| Ok(serde_json::from_str(raw)?))
+        .map_err(|e| log::warn!("catalog changes: failed to read snapshot: {}", e))
+        .ok()
+}
+
+/// Items in both `old` and `items` whose value differs, sorted by `sort_by` (ties by id).
+/// Items that were valued at 0 before have no meaningful percent and are skipped, as are items
+/// without a Rolimons value, whose `value` is only their RAP.
+fn diff_catalog(
+    old: &HashMap<u64, u64>,
+    items: &HashMap<u64, ItemInfo>,
+    sort_by: ChangeSort,
+) -> Vec<CatalogChange> {
+    let mut changes: Vec<CatalogChange> = items
+        .values()
+        .filter(|item| !item.value_unknown)
+        .filter_map(|item| {
+            let old_value = *old.get(&item.id)?;
+            if old_value == 0 || old_value == item.value {
+                return None;
+            }
+            Some(CatalogChange {
+                id: item.id,
+                name: item.name.clone(),
+                old_value,
+                new_value: item.value,
+                change_percent: (item.value as f64 - old_value as f64) / old_value as f64 * 100.0,
+            })
+        })
+        .collect();
+    changes.sort_by(|a, b| {
+        let ord = match sort_by {
+            ChangeSort::Percent => b.change_percent.abs().total_cmp(&a.change_percent.abs()),
+            ChangeSort::Absolute => b
+                .new_value
+                .abs_diff(b.old_value)
+                .cmp(&a.new_value.abs_diff(a.old_value)),
+        };
+        ord.then(a.id.cmp(&b.id))
+    });
+    changes
+}
+
+/// Refetch the catalog and compare its values with the snapshot from the previous call, then
+/// replace the snapshot. Returns up to `limit` changes, the total number of changes, and
+/// whether there was a snapshot to compare against (the first call only records one).
+pub async fn fetch_catalog_changes(
+    limit: usize,
+    sort_by: ChangeSort,
+) -> Result<(Vec<CatalogChange>, usize, bool)> {
+    let item_map = load_item_map(true).await?;
+    let path = catalog_snapshot_path();
+    let previous = path.as_deref().and_then(read_catalog_snapshot);
+
+    let (mut changes, had_snapshot) = match &previous {
+        Some(snapshot) => (diff_catalog(&snapshot.values, &item_map, sort_by), true),
+        None => (Vec::new(), false),
+    };
+    let total = changes.len();
+    changes.truncate(limit);
+
+    let snapshot = CatalogSnapshot {
+        taken_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        // unvalued items carry their RAP; leave them out so gaining a value isn't a "change"
+        values: item_map
+            .values()
+            .filter(|it| !it.value_unknown)
+            .map(|it| (it.id, it.value))
+            .collect(),
+    };
+    match (path, serde_json::to_string(&snapshot)) {
+        (Some(path), Ok(raw)) => {
+            if let Err(e) = crate::atomic_file::write_atomic(&path, raw) {
+                log::warn!("catalog changes: failed to write snapshot: {}", e);
+            }
+        }
+        (None, _) => log::warn!("catalog changes: no data directory for the snapshot"),
+        (_, Err(e)) => log::warn!("catalog changes: failed to serialize snapshot: {}", e),
+    }
+    log::debug!(
+        "catalog changes: {} items changed since the last snapshot ({} returned)",
+        total,
+        changes.len()
+    );
+    Ok((changes, total, had_snapshot))
+}
+
+/// An item a query could refer to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ItemCandidate {
+    pub id: u64,
+    pub name: String,
+    pub abbreviation: Option<String>,
+}
+
+impl From<&ItemInfo> for ItemCandidate {
+    fn from(item: &ItemInfo) -> Self {
+        ItemCandidate {
+            id: item.id,
+            name: item.name.clone(),
+            abbreviation: item.abbreviation.clone(),
+        }
+    }
+}
+
+/// What an offer/request query (id, abbreviation or name) resolved to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ItemResolution {
+    Resolved {
+        query: String,
+        item: ItemCandidate,
+    },
+    /// Several items share the abbreviation or name; the user has to pick one.
+    Ambiguous {
+        query: String,
+        candidates: Vec<ItemCandidate>,
+    },
+    Unknown {
+        query: String,
+    },
+}
+
+/// Resolve one query against the catalog: a catalog id first, then an exact abbreviation,
+/// then an exact name, all case-insensitive.
+fn resolve_query(item_map: &HashMap<u64, ItemInfo>, query: &str) -> ItemResolution {
+    let q = query.trim().to_lowercase();
+    for mode in [
+        MatchMode::ById,
+        MatchMode::ExactAbbrev,
+        MatchMode::ExactName,
+    ] {
+        let mut matches: Vec<&ItemInfo> = item_map
+            .values()
+            .filter(|it| mode.matches(&q, it))
+            .collect();
+        matches.sort_by_key(|it| it.id);
+        match matches.as_slice() {
+            [] => continue,
+            [item] => {
+                return ItemResolution::Resolved {
+                    query: query.to_string(),
+                    item: ItemCandidate::from(*item),
+                }
+            }
+            _ => {
+                return ItemResolution::Ambiguous {
+                    query: query.to_string(),
+                    candidates: matches.into_iter().map(ItemCandidate::from).collect(),
+                }
+            }
+        }
+    }
+    ItemResolution::Unknown {
+        query: query.to_string(),
+    }
+}
+
+/// Resolve offer/request queries ("CF", "Red Baseball Cap", "1028606") to catalog ids using
+/// the cached catalog. One resolution per query, in order.
+pub async fn resolve_items(queries: &[String]) -> Result<Vec<ItemResolution>> {
+    let item_map = load_item_map(false).await?;
+    Ok(queries
+        .iter()
+        .map(|q| resolve_query(&item_map, q))
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// A catalog item with the given figures and every optional field unset.
+    fn item(id: u64, name: &str, rap: u64, value: u64) -> ItemInfo {
+        ItemInfo {
+            id,
+            name: name.to_string(),
+            abbreviation: None,
+            rap,
+            value,
+            thumbnail: None,
+            demand: None,
+            trend: None,
+            projected: false,
+            rare: false,
+            value_unknown: false,
+        }
+    }
+
     #[test]
     fn test_item_info_creation() {
         let item = ItemInfo {
@@ -244,6 +810,11 @@ mod tests {
             rap: 1441,
             value: 1441,
             thumbnail: None,
+            demand: None,
+            trend: None,
+            projected: false,
+            rare: false,
+            value_unknown: false,
         };
 
         assert_eq!(item.id, 1028606);
@@ -263,6 +834,11 @@ mod tests {
             rap: 11045,
             value: 11045,
             thumbnail: None,
+            demand: None,
+            trend: None,
+            projected: false,
+            rare: false,
+            value_unknown: false,
         };
 
         assert_eq!(item.abbreviation, None);
@@ -279,17 +855,296 @@ mod tests {
             rap: 479116,
             value: 470000,
             thumbnail: Some(thumbnail_url.clone()),
+            demand: None,
+            trend: None,
+            projected: false,
+            rare: false,
+            value_unknown: false,
         };
 
         assert_eq!(item.thumbnail, Some(thumbnail_url));
         assert_eq!(item.rap, 479116);
     }
 
+    #[test]
+    fn test_parse_item_entry() {
+        let arr: Vec<serde_json::Value> =
+            serde_json::from_str(r#"["Red Baseball Cap", "RBC", 1441, -1]"#).unwrap();
+        let item = parse_item_entry(1028606, &arr);
+        assert_eq!(item.name, "Red Baseball Cap");
+        assert_eq!(item.abbreviation, Some("RBC".to_string()));
+        assert_eq!(item.rap, 1441);
+        // missing value (-1) falls back to rap
+        assert_eq!(item.value, 1441);
+
+        assert_eq!(item.demand, None);
+        assert!(!item.projected);
+
+        let arr: Vec<serde_json::Value> = serde_json::from_str(
+            r#"["Dominus Empyreus", "", 25000000, 30000000, 30000000, 4, 2, -1, -1, 1]"#,
+        )
+        .unwrap();
+        let item = parse_item_entry(21070012, &arr);
+        assert_eq!(item.abbreviation, None);
+        assert_eq!(item.value, 30000000);
+        assert_eq!(item.demand, Some(4));
+        assert_eq!(item.trend, Some(2));
+        assert!(!item.projected);
+        assert!(item.rare);
+    }
+
+    #[test]
+    fn test_item_info_deserializes_without_new_fields() {
+        let item: ItemInfo = serde_json::from_str(
+            r#"{"id":1,"name":"Old","abbreviation":null,"rap":10,"value":10,"thumbnail":null}"#,
+        )
+        .unwrap();
+        assert_eq!(item.demand, None);
+        assert_eq!(item.trend, None);
+        assert!(!item.projected);
+        assert!(!item.rare);
+    }
+
+    #[test]
+    fn test_item_filter() {
+        let mut item = ItemInfo {
+            demand: Some(2),
+            ..item(1, "Filtered", 900, 1000)
+        };
+
+        assert!(ItemFilter::default().matches(&item));
+        let range = ItemFilter {
+            min_value: Some(500),
+            max_value: Some(1000),
+            min_demand: None,
+        };
+        assert!(range.matches(&item));
+        assert!(!ItemFilter {
+            min_value: Some(1001),
+            ..Default::default()
+        }
+        .matches(&item));
+        assert!(!ItemFilter {
+            max_value: Some(999),
+            ..Default::default()
+        }
+        .matches(&item));
+
+        let demand = ItemFilter {
+            min_demand: Some(3),
+            ..Default::default()
+        };
+        assert!(!demand.matches(&item));
+        item.demand = Some(3);
+        assert!(demand.matches(&item));
+        item.demand = None;
+        assert!(!demand.matches(&item));
+    }
+
+    #[test]
+    fn test_match_mode() {
+        let item = ItemInfo {
+            abbreviation: Some("VH".to_string()),
+            ..item(1365767, "Valkyrie Helm", 0, 0)
+        };
+        assert!(MatchMode::Contains.matches("valk", &item));
+        assert!(MatchMode::Contains.matches("v", &item));
+        assert!(MatchMode::ExactAbbrev.matches("vh", &item));
+        assert!(!MatchMode::ExactAbbrev.matches("v", &item));
+        assert!(MatchMode::ExactName.matches("valkyrie helm", &item));
+        assert!(!MatchMode::ExactName.matches("valkyrie", &item));
+        assert!(MatchMode::ById.matches("1365767", &item));
+        assert!(!MatchMode::ById.matches("136576", &item));
+        assert!(!MatchMode::ById.matches("vh", &item));
+    }
+
+    #[test]
+    fn test_sort_items() {
+        let a = item(3, "bravo", 100, 500);
+        let b = item(1, "Alpha", 300, 500);
+        let c = item(2, "charlie", 200, 900);
+
+        let ids = |sort_by: ItemSort, desc: bool| {
+            let mut items = vec![&a, &b, &c];
+            sort_items(&mut items, sort_by, desc, ValueSource::ValueOrRap);
+            items.iter().map(|it| it.id).collect::<Vec<_>>()
+        };
+
+        // equal values keep id order
+        assert_eq!(ids(ItemSort::Value, true), vec![2, 1, 3]);
+        assert_eq!(ids(ItemSort::Value, false), vec![1, 3, 2]);
+        assert_eq!(ids(ItemSort::Rap, true), vec![1, 2, 3]);
+        assert_eq!(ids(ItemSort::Name, false), vec![1, 3, 2]);
+        assert_eq!(ids(ItemSort::Id, true), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_value_source() {
+        let arr: Vec<serde_json::Value> =
+            serde_json::from_str(r#"["Red Baseball Cap", "RBC", 1441, -1]"#).unwrap();
+        let unvalued = parse_item_entry(1028606, &arr);
+        assert!(unvalued.value_unknown);
+        let arr: Vec<serde_json::Value> =
+            serde_json::from_str(r#"["Clockwork Fedora", "CF", 900, 2000]"#).unwrap();
+        let valued = parse_item_entry(20, &arr);
+        assert!(!valued.value_unknown);
+
+        assert_eq!(ValueSource::ValueOrRap.value_of(&unvalued), 1441);
+        assert_eq!(ValueSource::ValueOnly.value_of(&unvalued), 0);
+        assert_eq!(ValueSource::ValueOnly.value_of(&valued), 2000);
+        assert_eq!(ValueSource::RapOnly.value_of(&valued), 900);
+
+        let ids = |source: ValueSource| {
+            let mut items = vec![&unvalued, &valued];
+            sort_items(&mut items, ItemSort::Value, true, source);
+            items.iter().map(|it| it.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(ValueSource::ValueOrRap), vec![20, 1028606]);
+        assert_eq!(ids(ValueSource::RapOnly), vec![1028606, 20]);
+
+        let mut shown = unvalued.clone();
+        ValueSource::ValueOnly.apply(&mut shown);
+        assert_eq!(shown.value, 0);
+        assert!(shown.value_unknown);
+    }
+
     #[tokio::test]
     async fn test_fetch_items_by_ids_empty() {
-        let result = fetch_items_by_ids(vec![]).await;
+        let result = fetch_items_by_ids(vec![], ThumbSize::Small).await;
         assert!(result.is_ok());
         let items = result.unwrap();
         assert_eq!(items.len(), 0);
     }
+
+    #[test]
+    fn test_resolve_query() {
+        let make = |id: u64, name: &str, abbr: Option<&str>| ItemInfo {
+            abbreviation: abbr.map(str::to_string),
+            ..item(id, name, 0, 0)
+        };
+        let map: HashMap<u64, ItemInfo> = [
+            make(1028606, "Red Baseball Cap", Some("RBC")),
+            make(20, "Clockwork Fedora", Some("CF")),
+            make(21, "Chill Fedora", Some("CF")),
+            make(30, "Domino Crown", None),
+        ]
+        .into_iter()
+        .map(|it| (it.id, it))
+        .collect();
+
+        let resolved_id = |q: &str| match resolve_query(&map, q) {
+            ItemResolution::Resolved { item, .. } => Some(item.id),
+            _ => None,
+        };
+        assert_eq!(resolved_id("rbc"), Some(1028606));
+        assert_eq!(resolved_id(" Domino Crown "), Some(30));
+        assert_eq!(resolved_id("1028606"), Some(1028606));
+
+        match resolve_query(&map, "CF") {
+            ItemResolution::Ambiguous { candidates, .. } => {
+                let ids: Vec<u64> = candidates.iter().map(|c| c.id).collect();
+                assert_eq!(ids, vec![20, 21]);
+            }
+            other => panic!("expected ambiguous, got {:?}", other),
+        }
+        assert_eq!(
+            resolve_query(&map, "nope"),
+            ItemResolution::Unknown {
+                query: "nope".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_evaluate_trade() {
+        let make = |id: u64, rap: u64, value: u64| item(id, &format!("Item {}", id), rap, value);
+        let items = vec![make(1, 900, 1000), make(2, 400, 500), make(3, 2000, 2500)];
+        let eval = evaluate_trade(&[1, 2, 2], &[3, 99], vec!["any".to_string()], &items);
+        assert_eq!(eval.offer.value, 2000);
+        assert_eq!(eval.offer.rap, 1700);
+        assert_eq!(eval.offer.items.len(), 3);
+        assert_eq!(eval.request.value, 2500);
+        assert_eq!(eval.request.unknown_ids, vec![99]);
+        assert_eq!(eval.value_difference, 500);
+        assert_eq!(eval.value_percent, Some(25.0));
+        assert_eq!(eval.rap_difference, 300);
+        assert_eq!(eval.request_tags, vec!["any"]);
+
+        // tags only: nothing to compare against
+        let eval = evaluate_trade(&[], &[], vec!["any".to_string()], &items);
+        assert_eq!(eval.value_difference, 0);
+        assert_eq!(eval.value_percent, None);
+    }
+
+    #[test]
+    fn test_diff_catalog() {
+        let make = |id: u64, value: u64| item(id, &format!("Item {}", id), 0, value);
+        let items: HashMap<u64, ItemInfo> = [
+            make(1, 1100),   // +10%, +100
+            make(2, 50_000), // -50%, -50000
+            make(3, 300),    // unchanged
+            make(4, 999),    // new since the snapshot
+            make(5, 40),     // was 0 before
+            ItemInfo {
+                value_unknown: true,
+                ..make(7, 5_000) // no Rolimons value; 5000 is its RAP
+            },
+        ]
+        .into_iter()
+        .map(|it| (it.id, it))
+        .collect();
+        let old: HashMap<u64, u64> = [
+            (1, 1000),
+            (2, 100_000),
+            (3, 300),
+            (5, 0),
+            (6, 10),
+            (7, 2_000),
+        ]
+        .into_iter()
+        .collect();
+
+        let changes = diff_catalog(&old, &items, ChangeSort::Percent);
+        let ids: Vec<u64> = changes.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(changes[0].change_percent, -50.0);
+        assert_eq!(changes[1].old_value, 1000);
+        assert_eq!(changes[1].new_value, 1100);
+        assert!((changes[1].change_percent - 10.0).abs() < 1e-9);
+
+        let by_size: Vec<u64> = diff_catalog(&old, &items, ChangeSort::Absolute)
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(by_size, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_rank_trending() {
+        let make = |id: u64, value: u64, demand: Option<i8>, trend: Option<i8>| ItemInfo {
+            demand,
+            trend,
+            ..item(id, &format!("Item {}", id), 0, value)
+        };
+        let map: HashMap<u64, ItemInfo> = [
+            make(1, 100, Some(4), Some(1)),
+            make(2, 500, Some(4), Some(1)),
+            make(3, 900, Some(2), Some(4)),
+            make(4, 50, Some(4), Some(3)),
+            make(5, 9999, None, None),
+        ]
+        .into_iter()
+        .map(|it| (it.id, it))
+        .collect();
+
+        let ids = |metric, limit| -> Vec<u64> {
+            rank_trending(&map, metric, limit)
+                .iter()
+                .map(|it| it.id)
+                .collect()
+        };
+        assert_eq!(ids(TrendingMetric::Demand, 10), vec![4, 2, 1, 3]);
+        assert_eq!(ids(TrendingMetric::Trend, 2), vec![3, 4]);
+        assert!(ids(TrendingMetric::Demand, 0).is_empty());
+    }
 }
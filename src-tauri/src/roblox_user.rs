@@ -1,16 +1,17 @@
 // roblox_user.rs
 // Responsibility: Interface with Roblox user search and details endpoints.
 
+use crate::http;
 use anyhow::{anyhow, Result};
 use once_cell::sync::Lazy;
-use rand::Rng;
-use reqwest::header::USER_AGENT;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::RwLock;
 use std::time::Duration;
 use std::time::Instant;
-use tokio::time::sleep;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RobloxUser {
@@ -33,22 +34,215 @@ pub struct UserSearchResponse {
     pub data: Vec<RobloxUser>,
 }
 
+/// Treat an explicit `null` like a missing field and use the type's default.
+fn null_as_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// Roblox leaves out or nulls fields for deleted and banned accounts, so everything but the
+// id falls back to a default rather than failing the whole lookup.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserDetails {
     pub id: u64,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub name: String,
-    #[serde(rename = "displayName")]
+    #[serde(rename = "displayName", default, deserialize_with = "null_as_default")]
     pub display_name: String,
+    // The batch endpoint only returns id/name/displayName/hasVerifiedBadge
+    #[serde(default, deserialize_with = "null_as_default")]
     pub description: String,
-    pub created: String,
-    #[serde(rename = "isBanned")]
+    #[serde(default)]
+    pub created: Option<String>,
+    #[serde(rename = "isBanned", default, deserialize_with = "null_as_default")]
     pub is_banned: bool,
-    #[serde(rename = "hasVerifiedBadge")]
+    #[serde(
+        rename = "hasVerifiedBadge",
+        default,
+        deserialize_with = "null_as_default"
+    )]
     pub has_verified_badge: bool,
-    #[serde(rename = "externalAppDisplayName")]
+    #[serde(rename = "externalAppDisplayName", default)]
     pub external_app_display_name: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct UserDetailsBatchResponse {
+    data: Vec<UserDetails>,
+}
+
+/// Maximum ids (or usernames) accepted by a single batch POST.
+const USERS_BATCH_SIZE: usize = 100;
+
+/// An exact username match returned by `/v1/usernames/users`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResolvedUser {
+    #[serde(rename = "requestedUsername")]
+    pub requested_username: String,
+    pub id: u64,
+    pub name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    #[serde(rename = "hasVerifiedBadge", default)]
+    pub has_verified_badge: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolvedUsersResponse {
+    data: Vec<ResolvedUser>,
+}
+
+struct CachedSearch {
+    fetched: Instant,
+    // entries seeded from disk keep the longer disk TTL
+    ttl: Duration,
+    response: UserSearchResponse,
+}
+
+/// A search result as persisted in user_search_cache.json.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct DiskSearchEntry {
+    fetched_unix: u64,
+    response: UserSearchResponse,
+}
+
+const SEARCH_TTL: Duration = Duration::from_secs(60); // cache for 60s
+/// How long persisted searches stay usable, including across restarts.
+const DISK_SEARCH_TTL: Duration = Duration::from_secs(10 * 60);
+/// Most searches kept on disk; the oldest are dropped first.
+const MAX_DISK_ENTRIES: usize = 200;
+
+/// Whether searches are also persisted to disk. On by default.
+static DISK_CACHE_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn disk_cache_enabled() -> bool {
+    DISK_CACHE_ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_disk_cache_enabled(enabled: bool) {
+    DISK_CACHE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn get_disk_cache_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or_else(|| anyhow!("Failed to get config directory"))?;
+    let app_dir = config_dir.join("roli-trade-ad-automation");
+    std::fs::create_dir_all(&app_dir)?;
+    Ok(app_dir.join("user_search_cache.json"))
+}
+
+fn load_disk_entries() -> Result<HashMap<String, DiskSearchEntry>> {
+    let path = get_disk_cache_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    crate::atomic_file::read_with_backup(&path, |raw| Ok(serde_json::from_str(raw)?))
+}
+
+fn save_disk_entries(entries: &HashMap<String, DiskSearchEntry>) -> Result<()> {
+    let path = get_disk_cache_path()?;
+    crate::atomic_file::write_atomic(&path, serde_json::to_string(entries)?)
+}
+
+/// Drop expired entries, then the oldest ones beyond MAX_DISK_ENTRIES.
+fn prune_disk_entries(entries: &mut HashMap<String, DiskSearchEntry>, now_unix: u64) {
+    entries.retain(|_, e| now_unix.saturating_sub(e.fetched_unix) < DISK_SEARCH_TTL.as_secs());
+    if entries.len() > MAX_DISK_ENTRIES {
+        let mut by_age: Vec<(String, u64)> = entries
+            .iter()
+            .map(|(k, e)| (k.clone(), e.fetched_unix))
+            .collect();
+        by_age.sort_by_key(|(_, fetched)| *fetched);
+        let excess = entries.len() - MAX_DISK_ENTRIES;
+        for (key, _) in by_age.into_iter().take(excess) {
+            entries.remove(&key);
+        }
+    }
+}
+
+/// Add one search to the disk cache.
+fn persist_search(key: &str, response: &UserSearchResponse) {
+    let now = unix_now();
+    let mut entries = load_disk_entries().unwrap_or_else(|e| {
+        log::warn!("roblox_user: failed to read search cache: {}", e);
+        HashMap::new()
+    });
+    entries.insert(
+        key.to_string(),
+        DiskSearchEntry {
+            fetched_unix: now,
+            response: response.clone(),
+        },
+    );
+    prune_disk_entries(&mut entries, now);
+    if let Err(e) = save_disk_entries(&entries) {
+        log::warn!("roblox_user: failed to write search cache: {}", e);
+    }
+}
+
+/// Turn unexpired disk entries into in-memory ones, keeping their real age.
+fn seed_from_disk(
+    mut entries: HashMap<String, DiskSearchEntry>,
+    now_unix: u64,
+) -> HashMap<String, CachedSearch> {
+    prune_disk_entries(&mut entries, now_unix);
+    let now = Instant::now();
+    entries
+        .into_iter()
+        .map(|(key, e)| {
+            let age = Duration::from_secs(now_unix.saturating_sub(e.fetched_unix));
+            let cached = CachedSearch {
+                fetched: now.checked_sub(age).unwrap_or(now),
+                ttl: DISK_SEARCH_TTL,
+                response: e.response,
+            };
+            (key, cached)
+        })
+        .collect()
+}
+
+// Simple in-memory cache to reduce Roblox API calls and avoid 429 rate limits.
+// Keyed by normalized lowercase keyword and optional limit (as string). Seeded from the
+// disk cache on first use, so recent searches survive a restart.
+static SEARCH_CACHE: Lazy<RwLock<HashMap<String, CachedSearch>>> = Lazy::new(|| {
+    if !disk_cache_enabled() {
+        return RwLock::new(HashMap::new());
+    }
+    let seeded = match load_disk_entries() {
+        Ok(entries) => seed_from_disk(entries, unix_now()),
+        Err(e) => {
+            log::warn!("roblox_user: failed to load search cache: {}", e);
+            HashMap::new()
+        }
+    };
+    log::debug!("roblox_user: seeded {} searches from disk", seeded.len());
+    RwLock::new(seeded)
+});
+
+/// Drop all cached user searches, in memory and on disk. Returns the number of entries removed.
+pub fn clear_cache() -> usize {
+    if let Err(e) = save_disk_entries(&HashMap::new()) {
+        log::warn!("roblox_user: failed to clear search cache file: {}", e);
+    }
+    match SEARCH_CACHE.write() {
+        Ok(mut cache) => {
+            let n = cache.len();
+            cache.clear();
+            n
+        }
+        Err(_) => 0,
+    }
+}
+
 /// Search for Roblox users by keyword.
 /// Returns up to `limit` results (default 10).
 pub async fn search_users(keyword: &str, limit: Option<u32>) -> Result<UserSearchResponse> {
@@ -56,22 +250,16 @@ pub async fn search_users(keyword: &str, limit: Option<u32>) -> Result<UserSearc
         return Err(anyhow!("Keyword must be at least 3 characters"));
     }
 
-    // Simple in-memory cache to reduce Roblox API calls and avoid 429 rate limits.
-    // Keyed by normalized lowercase keyword and optional limit (as string).
-    static SEARCH_CACHE: Lazy<RwLock<HashMap<String, (Instant, UserSearchResponse)>>> =
-        Lazy::new(|| RwLock::new(HashMap::new()));
-    const SEARCH_TTL: Duration = Duration::from_secs(60); // cache for 60s
-
     let norm_key = format!("{}::{}", keyword.to_lowercase(), limit.unwrap_or(10));
     if let Ok(cache) = SEARCH_CACHE.read() {
-        if let Some((ts, resp)) = cache.get(&norm_key) {
-            if ts.elapsed() < SEARCH_TTL {
-                eprintln!(
+        if let Some(cached) = cache.get(&norm_key) {
+            if cached.fetched.elapsed() < cached.ttl {
+                log::debug!(
                     "roblox_user: cache hit for '{}' (age {:?})",
                     norm_key,
-                    ts.elapsed()
+                    cached.fetched.elapsed()
                 );
-                return Ok(resp.clone());
+                return Ok(cached.response.clone());
             }
         }
     }
@@ -83,98 +271,55 @@ pub async fn search_users(keyword: &str, limit: Option<u32>) -> Result<UserSearc
         limit
     );
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-
-    // Retry loop with exponential backoff and jitter to handle 429 rate limits.
-    let mut attempt: u32 = 0;
-    let max_attempts: u32 = 4;
-    loop {
-        attempt += 1;
-        let resp = client
-            .get(&url)
-            .header(USER_AGENT, "roblox-user-search/1.0")
-            .send()
-            .await?;
+    let resp = http::send_with_backoff(|| http::client().get(&url)).await?;
 
-        if resp.status().as_u16() == 429 {
-            // Respect Retry-After header if present, otherwise exponential backoff with jitter
-            if attempt >= max_attempts {
-                // If we have a cached response, return it instead of failing immediately.
-                if let Ok(cache) = SEARCH_CACHE.read() {
-                    if let Some((_, cached)) = cache.get(&norm_key) {
-                        eprintln!(
-                            "roblox_user: 429 exhausted; returning cached response for {}",
-                            norm_key
-                        );
-                        return Ok(cached.clone());
-                    }
-                }
-
-                return Err(anyhow!(
-                    "Too many requests (429) from Roblox API; please try again later"
-                ));
-            }
-            let retry_after = resp
-                .headers()
-                .get("retry-after")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|s| s.parse::<u64>().ok());
-
-            if let Some(secs) = retry_after {
-                eprintln!(
-                    "roblox_user: 429 received; retrying after {}s (Retry-After header)",
-                    secs
-                );
-                sleep(Duration::from_secs(secs)).await;
-            } else {
-                // exponential backoff: base 1s * 2^(attempt-1) plus jitter up to 500ms
-                let exp = std::cmp::min(attempt.saturating_sub(1), 4) as u32; // cap exponent to avoid huge waits
-                let base = 1u64.checked_shl(exp).unwrap_or(16); // 1 << exp
-                let jitter_ms: u64 = {
-                    let mut r = rand::thread_rng();
-                    r.gen_range(0..500)
-                };
-                let wait = Duration::from_millis(base * 1000 + jitter_ms);
-                eprintln!(
-                    "roblox_user: 429 received; retrying after {:?} (attempt {}/{})",
-                    wait, attempt, max_attempts
+    if resp.status().as_u16() == 429 {
+        // If we have a cached response, return it instead of failing immediately.
+        if let Ok(cache) = SEARCH_CACHE.read() {
+            if let Some(cached) = cache.get(&norm_key) {
+                log::warn!(
+                    "roblox_user: 429 exhausted; returning cached response for {}",
+                    norm_key
                 );
-                sleep(wait).await;
+                return Ok(cached.response.clone());
             }
-
-            continue;
         }
 
-        if !resp.status().is_success() {
-            return Err(anyhow!("Failed to search users: {}", resp.status()));
-        }
+        return Err(anyhow!(
+            "Too many requests (429) from Roblox API; please try again later"
+        ));
+    }
 
-        let body = resp.text().await?;
-        let result: UserSearchResponse = serde_json::from_str(&body)?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("Failed to search users: {}", resp.status()));
+    }
 
-        // Store in cache
-        if let Ok(mut cache) = SEARCH_CACHE.write() {
-            cache.insert(norm_key.clone(), (Instant::now(), result.clone()));
-        }
+    let body = resp.text().await?;
+    let result: UserSearchResponse = serde_json::from_str(&body)?;
 
-        return Ok(result);
+    // Store in cache
+    if let Ok(mut cache) = SEARCH_CACHE.write() {
+        cache.insert(
+            norm_key.clone(),
+            CachedSearch {
+                fetched: Instant::now(),
+                ttl: SEARCH_TTL,
+                response: result.clone(),
+            },
+        );
     }
+    if disk_cache_enabled() {
+        persist_search(&norm_key, &result);
+    }
+
+    Ok(result)
 }
 
 /// Fetch detailed information for a specific Roblox user by ID.
 pub async fn get_user_details(user_id: u64) -> Result<UserDetails> {
     let url = format!("https://users.roblox.com/v1/users/{}", user_id);
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-    let resp = client
-        .get(&url)
-        .header(USER_AGENT, "roblox-user-details/1.0")
-        .send()
-        .await?;
+    let resp = http::client().get(&url).send().await?;
 
     if !resp.status().is_success() {
         return Err(anyhow!("Failed to fetch user details: {}", resp.status()));
@@ -185,6 +330,95 @@ pub async fn get_user_details(user_id: u64) -> Result<UserDetails> {
     Ok(details)
 }
 
+/// Fetch basic details for many users at once via the batch `/v1/users` endpoint.
+/// Ids are sent in chunks of 100. The batch endpoint does not return `description`,
+/// `created` or `isBanned`, so those fields are left at their defaults.
+pub async fn get_users_details(ids: Vec<u64>) -> Result<Vec<UserDetails>> {
+    let mut ids = ids;
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let client = http::client();
+
+    let mut out = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(USERS_BATCH_SIZE) {
+        let payload = serde_json::json!({
+            "userIds": chunk,
+            "excludeBannedUsers": false,
+        });
+        let resp = http::send_with_backoff(|| {
+            client
+                .post("https://users.roblox.com/v1/users")
+                .json(&payload)
+        })
+        .await?;
+
+        if resp.status().as_u16() == 429 {
+            return Err(anyhow!(
+                "Too many requests (429) from Roblox API; please try again later"
+            ));
+        }
+        if !resp.status().is_success() {
+            return Err(anyhow!("Failed to fetch user details: {}", resp.status()));
+        }
+
+        let body = resp.text().await?;
+        let batch: UserDetailsBatchResponse = serde_json::from_str(&body)?;
+        out.extend(batch.data);
+    }
+
+    Ok(out)
+}
+
+/// Resolve exact usernames to user ids. Usernames that don't exist are simply
+/// missing from the result; matching is case-insensitive on Roblox's side.
+pub async fn resolve_usernames(usernames: Vec<String>) -> Result<Vec<ResolvedUser>> {
+    let mut names: Vec<String> = usernames
+        .into_iter()
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .collect();
+    names.sort_by_key(|n| n.to_lowercase());
+    names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let client = http::client();
+
+    let mut out = Vec::with_capacity(names.len());
+    for chunk in names.chunks(USERS_BATCH_SIZE) {
+        let payload = serde_json::json!({
+            "usernames": chunk,
+            "excludeBannedUsers": false,
+        });
+        let resp = http::send_with_backoff(|| {
+            client
+                .post("https://users.roblox.com/v1/usernames/users")
+                .json(&payload)
+        })
+        .await?;
+
+        if resp.status().as_u16() == 429 {
+            return Err(anyhow!(
+                "Too many requests (429) from Roblox API; please try again later"
+            ));
+        }
+        if !resp.status().is_success() {
+            return Err(anyhow!("Failed to resolve usernames: {}", resp.status()));
+        }
+
+        let body = resp.text().await?;
+        let resolved: ResolvedUsersResponse = serde_json::from_str(&body)?;
+        out.extend(resolved.data);
+    }
+
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,4 +428,70 @@ mod tests {
         let result = search_users("test", Some(5)).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_batch_response_parses_without_profile_fields() {
+        let body = r#"{"data":[{"hasVerifiedBadge":false,"id":1,"name":"Roblox","displayName":"Roblox"}]}"#;
+        let batch: UserDetailsBatchResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(batch.data.len(), 1);
+        assert_eq!(batch.data[0].id, 1);
+        assert!(batch.data[0].description.is_empty());
+        assert!(!batch.data[0].is_banned);
+    }
+
+    #[test]
+    fn test_user_details_parse_banned_account() {
+        let body = r#"{"id":123,"name":"Deleted","displayName":null,"description":null,"created":null,"isBanned":true}"#;
+        let details: UserDetails = serde_json::from_str(body).unwrap();
+        assert_eq!(details.id, 123);
+        assert_eq!(details.name, "Deleted");
+        assert!(details.display_name.is_empty());
+        assert!(details.description.is_empty());
+        assert_eq!(details.created, None);
+        assert!(details.is_banned);
+        assert!(!details.has_verified_badge);
+
+        let minimal: UserDetails = serde_json::from_str(r#"{"id":5}"#).unwrap();
+        assert!(minimal.name.is_empty());
+        assert_eq!(minimal.external_app_display_name, None);
+    }
+
+    #[test]
+    fn test_resolved_users_parse() {
+        let body = r#"{"data":[{"requestedUsername":"roblox","hasVerifiedBadge":true,"id":1,"name":"Roblox","displayName":"Roblox"}]}"#;
+        let resolved: ResolvedUsersResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(resolved.data[0].requested_username, "roblox");
+        assert_eq!(resolved.data[0].id, 1);
+    }
+
+    #[test]
+    fn test_disk_entries_prune_and_seed() {
+        let entry = |fetched_unix: u64| DiskSearchEntry {
+            fetched_unix,
+            response: UserSearchResponse {
+                previous_page_cursor: None,
+                next_page_cursor: None,
+                data: Vec::new(),
+            },
+        };
+        let now = 1_000_000;
+        let mut entries: HashMap<String, DiskSearchEntry> = HashMap::new();
+        entries.insert("fresh::10".to_string(), entry(now - 30));
+        entries.insert("stale::10".to_string(), entry(now - 11 * 60));
+
+        let seeded = seed_from_disk(entries.clone(), now);
+        assert_eq!(seeded.len(), 1);
+        let fresh = &seeded["fresh::10"];
+        // still usable under the disk TTL even though it's older than SEARCH_TTL would allow
+        assert_eq!(fresh.ttl, DISK_SEARCH_TTL);
+        assert!(fresh.fetched.elapsed() >= Duration::from_secs(30));
+
+        for i in 0..(MAX_DISK_ENTRIES as u64 + 5) {
+            entries.insert(format!("k{}::10", i), entry(now - 100 + i / 10));
+        }
+        prune_disk_entries(&mut entries, now);
+        assert_eq!(entries.len(), MAX_DISK_ENTRIES);
+        assert!(!entries.contains_key("stale::10"));
+        assert!(entries.contains_key("fresh::10"));
+    }
 }
@@ -1,4 +1,6 @@
-use reqwest::header::USER_AGENT;
+use crate::api_error::{check_status, ApiError};
+use crate::http;
+use crate::rate_limit;
 use serde_json::Value;
 
 /// Search players via Rolimons players API.
@@ -19,25 +21,16 @@ pub async fn search_players_with_thumbnails(
         encoded
     );
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| e.to_string())?;
-    let resp = client
-        .get(&url)
-        .header(USER_AGENT, "rolimons-players-search/1.0")
-        .send()
+    rate_limit::acquire().await;
+    let resp = http::send_with_backoff(|| http::client().get(&url))
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| ApiError::from(e).to_string())?;
+    let resp = check_status(resp).await.map_err(|e| e.to_string())?;
 
-    if !resp.status().is_success() {
-        return Err(format!(
-            "Rolimons players search HTTP error: {}",
-            resp.status()
-        ));
-    }
-
-    let body: Value = resp.json().await.map_err(|e| e.to_string())?;
+    let body: Value = resp
+        .json()
+        .await
+        .map_err(|e| ApiError::from(e).to_string())?;
 
     // Extract players array ([[id, name], ...])
     let players_arr = match body.get("players") {
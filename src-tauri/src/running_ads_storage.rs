@@ -0,0 +1,70 @@
+// running_ads_storage.rs
+// Persist the set of running ads so they can be restarted after an app restart.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunningAdEntry {
+    pub id: String,
+    // interval the ad was started with when it overrode the stored one
+    pub interval_override: Option<u64>,
+}
+
+/// Runner-wide settings. `None` fields use the runner's defaults.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunnerSettings {
+    pub max_concurrent_ads: Option<usize>,
+    // random wait range [min, max] in seconds between ads restarted at launch
+    #[serde(default)]
+    pub restore_stagger_secs: Option<(u64, u64)>,
+}
+
+fn get_app_file_path(name: &str) -> Result<PathBuf> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Failed to get config directory"))?;
+    let app_dir = config_dir.join("roli-trade-ad-automation");
+    fs::create_dir_all(&app_dir)?;
+    Ok(app_dir.join(name))
+}
+
+fn get_running_ads_file_path() -> Result<PathBuf> {
+    get_app_file_path("running_ads.json")
+}
+
+fn get_runner_settings_file_path() -> Result<PathBuf> {
+    get_app_file_path("runner_settings.json")
+}
+
+pub fn load_runner_settings() -> Result<RunnerSettings> {
+    let path = get_runner_settings_file_path()?;
+    if !path.exists() {
+        return Ok(RunnerSettings::default());
+    }
+    crate::atomic_file::read_with_backup(&path, |raw| Ok(serde_json::from_str(raw)?))
+}
+
+pub fn save_runner_settings(settings: &RunnerSettings) -> Result<()> {
+    let path = get_runner_settings_file_path()?;
+    let raw = serde_json::to_string_pretty(settings)?;
+    crate::atomic_file::write_atomic(&path, raw)
+}
+
+pub fn load_running_ads() -> Result<Vec<RunningAdEntry>> {
+    let path = get_running_ads_file_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let entries: Vec<RunningAdEntry> =
+        crate::atomic_file::read_with_backup(&path, |raw| Ok(serde_json::from_str(raw)?))?;
+    Ok(entries)
+}
+
+pub fn save_running_ads(entries: &[RunningAdEntry]) -> Result<()> {
+    let path = get_running_ads_file_path()?;
+    let raw = serde_json::to_string_pretty(entries)?;
+    crate::atomic_file::write_atomic(&path, raw)?;
+    Ok(())
+}
@@ -0,0 +1,242 @@
+// secret_store.rs
+// Responsibility: Encrypt secrets (the roli_verification cookie) before they are written to disk.
+// On Windows the value is protected with DPAPI so it is bound to the current user account.
+// Other platforms fall back to AES-256-GCM with a random key kept in the app data directory.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+const DPAPI_PREFIX: &str = "dpapi:";
+const AES_PREFIX: &str = "aesgcm:";
+
+/// Whether a stored value was produced by `encrypt` (as opposed to a legacy plaintext token).
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(DPAPI_PREFIX) || stored.starts_with(AES_PREFIX)
+}
+
+/// Encrypt a secret into a prefixed, base64-encoded string suitable for JSON storage.
+pub fn encrypt(plain: &str) -> Result<String> {
+    #[cfg(windows)]
+    {
+        let blob = dpapi_protect(plain.as_bytes())?;
+        Ok(format!("{}{}", DPAPI_PREFIX, STANDARD.encode(blob)))
+    }
+    #[cfg(not(windows))]
+    {
+        let blob = aes_encrypt(plain.as_bytes())?;
+        Ok(format!("{}{}", AES_PREFIX, STANDARD.encode(blob)))
+    }
+}
+
+/// `encrypt`, except that a value which is already encrypted is kept as is. Loaders leave
+/// a token they couldn't decrypt in its stored form, so saving must not wrap it again.
+pub fn encrypt_if_plain(value: &str) -> Result<String> {
+    if is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+    encrypt(value)
+}
+
+/// Decrypt a value produced by `encrypt`. Legacy plaintext values are returned unchanged
+/// so callers can migrate them by re-saving.
+pub fn decrypt(stored: &str) -> Result<String> {
+    let plain = if let Some(b64) = stored.strip_prefix(DPAPI_PREFIX) {
+        let blob = STANDARD.decode(b64)?;
+        #[cfg(windows)]
+        {
+            dpapi_unprotect(&blob)?
+        }
+        #[cfg(not(windows))]
+        {
+            let _ = blob;
+            return Err(anyhow!(
+                "DPAPI-protected secrets can only be read on Windows"
+            ));
+        }
+    } else if let Some(b64) = stored.strip_prefix(AES_PREFIX) {
+        aes_decrypt(&STANDARD.decode(b64)?)?
+    } else {
+        return Ok(stored.to_string());
+    };
+    Ok(String::from_utf8(plain)?)
+}
+
+/// Encrypt bytes with DPAPI so only the current Windows user can decrypt them.
+#[cfg(windows)]
+fn dpapi_protect(data: &[u8]) -> Result<Vec<u8>> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::HLOCAL;
+    use windows::Win32::Security::Cryptography::{
+        CryptProtectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+    use windows::Win32::System::Memory::LocalFree;
+
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        let ok = CryptProtectData(
+            &input,
+            PCWSTR::null(),
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        );
+        if !ok.as_bool() {
+            return Err(anyhow!("CryptProtectData failed"));
+        }
+        let out = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(output.pbData as isize));
+        Ok(out)
+    }
+}
+
+/// Decrypt a DPAPI blob (ours, or one produced by another app for the same user such as Chrome).
+#[cfg(windows)]
+pub(crate) fn dpapi_unprotect(data: &[u8]) -> Result<Vec<u8>> {
+    use windows::Win32::Foundation::HLOCAL;
+    use windows::Win32::Security::Cryptography::{
+        CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+    use windows::Win32::System::Memory::LocalFree;
+
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        let ok = CryptUnprotectData(
+            &input,
+            None,
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        );
+        if !ok.as_bool() {
+            return Err(anyhow!("CryptUnprotectData failed"));
+        }
+        let out = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        let _ = LocalFree(HLOCAL(output.pbData as isize));
+        Ok(out)
+    }
+}
+
+/// Load (or create on first use) the 32-byte key used by the non-Windows fallback.
+fn local_key() -> Result<Vec<u8>> {
+    let mut dir = dirs::data_local_dir().ok_or_else(|| anyhow!("Failed to get data directory"))?;
+    dir.push("roli-trade-ad-automation");
+    std::fs::create_dir_all(&dir)?;
+    load_or_create_key(&dir.join("secret.key"))
+}
+
+/// Read the key at `path`, generating one only when the file doesn't exist. A file that is
+/// there but unreadable or malformed is an error: replacing it would make every token
+/// encrypted with the old key unreadable.
+fn load_or_create_key(path: &std::path::Path) -> Result<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(existing) if existing.len() == 32 => return Ok(existing),
+        Ok(existing) => {
+            return Err(anyhow!(
+                "{} is malformed ({} bytes, expected 32); not replacing it",
+                path.display(),
+                existing.len()
+            ));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    }
+    let mut key = vec![0u8; 32];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut key);
+    std::fs::write(path, &key)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(key)
+}
+
+/// AES-256-GCM encrypt, returning nonce || ciphertext.
+#[cfg_attr(windows, allow(dead_code))]
+fn aes_encrypt(data: &[u8]) -> Result<Vec<u8>> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+
+    let cipher = Aes256Gcm::new_from_slice(&local_key()?).map_err(|e| anyhow!("{}", e))?;
+    let mut nonce = [0u8; 12];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut nonce);
+    let ct = cipher
+        .encrypt(&Nonce::from(nonce), data)
+        .map_err(|e| anyhow!("encrypt failed: {}", e))?;
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&ct);
+    Ok(out)
+}
+
+/// Reverse of `aes_encrypt`.
+fn aes_decrypt(data: &[u8]) -> Result<Vec<u8>> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Nonce};
+
+    if data.len() < 12 {
+        return Err(anyhow!("encrypted secret is too short"));
+    }
+    let (nonce, ct) = data.split_at(12);
+    let nonce: [u8; 12] = nonce.try_into()?;
+    let cipher = Aes256Gcm::new_from_slice(&local_key()?).map_err(|e| anyhow!("{}", e))?;
+    cipher
+        .decrypt(&Nonce::from(nonce), ct)
+        .map_err(|e| anyhow!("decrypt failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let token = "_|WARNING:-DO-NOT-SHARE-THIS.--sample-token";
+        let stored = encrypt(token).unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains(token));
+        assert_eq!(decrypt(&stored).unwrap(), token);
+    }
+
+    #[test]
+    fn test_encrypt_if_plain_keeps_stored_value() {
+        let stored = encrypt("token").unwrap();
+        assert_eq!(encrypt_if_plain(&stored).unwrap(), stored);
+        assert!(is_encrypted(&encrypt_if_plain("token").unwrap()));
+    }
+
+    #[test]
+    fn test_load_or_create_key() {
+        let dir = std::env::temp_dir().join(format!("secret-key-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secret.key");
+        let _ = std::fs::remove_file(&path);
+
+        let key = load_or_create_key(&path).unwrap();
+        assert_eq!(key.len(), 32);
+        assert_eq!(load_or_create_key(&path).unwrap(), key);
+
+        std::fs::write(&path, b"truncated").unwrap();
+        assert!(load_or_create_key(&path).is_err());
+        // the malformed file is left for the user to restore, not overwritten
+        assert_eq!(std::fs::read(&path).unwrap(), b"truncated");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plaintext_passthrough() {
+        assert!(!is_encrypted("legacy-token"));
+        assert_eq!(decrypt("legacy-token").unwrap(), "legacy-token");
+    }
+}
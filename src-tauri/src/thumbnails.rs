@@ -1,74 +1,326 @@
+use crate::http;
+use crate::rate_limit;
 use once_cell::sync::Lazy;
-use reqwest::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::RwLock;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Which Rolimons thumbnail map to use. Small is what list views show; large is for
+/// detail views and is a much bigger download, so it is only fetched when asked for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbSize {
+    #[default]
+    Small,
+    Large,
+}
+
+impl ThumbSize {
+    const ALL: [ThumbSize; 2] = [ThumbSize::Small, ThumbSize::Large];
+
+    fn url(self) -> &'static str {
+        match self {
+            ThumbSize::Small => "https://api.rolimons.com/itemthumbs/v1/thumbssm",
+            ThumbSize::Large => "https://api.rolimons.com/itemthumbs/v1/thumbslg",
+        }
+    }
+
+    fn disk_cache_name(self) -> &'static str {
+        match self {
+            // keeps the pre-size filename so an existing cache is still picked up
+            ThumbSize::Small => "thumbs_cache.json",
+            ThumbSize::Large => "thumbs_cache_lg.json",
+        }
+    }
+
+    /// This size's in-memory map.
+    fn cache(self) -> &'static RwLock<(Instant, HashMap<String, String>)> {
+        match self {
+            ThumbSize::Small => &THUMB_CACHE_SM,
+            ThumbSize::Large => &THUMB_CACHE_LG,
+        }
+    }
+
+    /// Set while a background refresh of this size is running.
+    fn refreshing(self) -> &'static AtomicBool {
+        match self {
+            ThumbSize::Small => &REFRESHING_SM,
+            ThumbSize::Large => &REFRESHING_LG,
+        }
+    }
+}
 
 /// Fetch thumbnails for specific item IDs only (lazy loading).
 /// This avoids fetching the entire thumbnail map when only a few thumbnails are needed.
-pub async fn fetch_thumbnails_for_ids_cmd(ids: Vec<u64>) -> Result<HashMap<String, String>, String> {
+pub async fn fetch_thumbnails_for_ids_cmd(
+    ids: Vec<u64>,
+    size: ThumbSize,
+) -> Result<HashMap<String, String>, String> {
     let start = Instant::now();
-    eprintln!("fetch_thumbnails_for_ids_cmd: fetching {} thumbnails", ids.len());
+    log::debug!(
+        "fetch_thumbnails_for_ids_cmd: fetching {} thumbnails",
+        ids.len()
+    );
     if ids.is_empty() {
         return Ok(HashMap::new());
     }
 
-    // Build a client with timeout
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(8))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    // For now, fetch the full map and filter to requested IDs.
+    // The full map is cached; only the requested entries are copied out.
     // TODO: if Rolimons provides a batch endpoint, use that instead.
-    let full_map = fetch_thumbnails_map(&client)
+    let result = fetch_thumbnails_sized(&ids, size, Duration::from_secs(8))
         .await
         .map_err(|e| e.to_string())?;
 
-    let mut result = HashMap::new();
-    for id in ids {
-        let key = id.to_string();
-        if let Some(url) = full_map.get(&key) {
-            result.insert(key, url.clone());
+    log::debug!(
+        "fetch_thumbnails_for_ids_cmd: returning {} thumbnails in {:?}",
+        result.len(),
+        start.elapsed()
+    );
+    Ok(result)
+}
+
+/// Simple in-memory caches with TTL, one thumbnails map per size.
+static THUMB_CACHE_SM: Lazy<RwLock<(Instant, HashMap<String, String>)>> =
+    Lazy::new(|| RwLock::new((Instant::now() - Duration::from_secs(3600), HashMap::new())));
+static THUMB_CACHE_LG: Lazy<RwLock<(Instant, HashMap<String, String>)>> =
+    Lazy::new(|| RwLock::new((Instant::now() - Duration::from_secs(3600), HashMap::new())));
+/// Cache TTL in seconds, adjustable at runtime and shared by all sizes. Zero means always refetch.
+static THUMB_TTL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_THUMB_TTL_SECS);
+pub const DEFAULT_THUMB_TTL_SECS: u64 = 60 * 10; // 10 minutes
+
+pub fn cache_ttl_secs() -> u64 {
+    THUMB_TTL_SECS.load(Ordering::Relaxed)
+}
+
+pub fn set_cache_ttl_secs(secs: u64) {
+    THUMB_TTL_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Whether a cached map of this age can still be served under the current TTL.
+fn is_fresh(age: Duration) -> bool {
+    let ttl = cache_ttl_secs();
+    // A zero TTL disables caching outright rather than relying on `age < 0`
+    if ttl == 0 {
+        return false;
+    }
+    age < Duration::from_secs(ttl)
+}
+
+/// Set while a background refresh (started after serving the disk copy) is running.
+static REFRESHING_SM: AtomicBool = AtomicBool::new(false);
+static REFRESHING_LG: AtomicBool = AtomicBool::new(false);
+
+/// On-disk copy of the thumbnails map so a fresh launch can render without waiting
+/// for the full download. `fetched_at` is unix seconds.
+#[derive(Serialize, Deserialize)]
+struct DiskThumbCache<M> {
+    fetched_at: u64,
+    items: M,
+}
+
+fn disk_cache_path(size: ThumbSize) -> Option<PathBuf> {
+    let mut dir = dirs::data_local_dir()?;
+    dir.push("roli-trade-ad-automation");
+    std::fs::create_dir_all(&dir).ok()?;
+    dir.push(size.disk_cache_name());
+    Some(dir)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Read the disk cache, returning its age and map.
+fn read_disk_cache(path: &Path) -> Option<(Duration, HashMap<String, String>)> {
+    if !path.exists() {
+        return None;
+    }
+    let cache: DiskThumbCache<HashMap<String, String>> =
+        match crate::atomic_file::read_with_backup(path, |raw| Ok(serde_json::from_str(raw)?)) {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("thumbnails: failed to read disk cache: {}", e);
+                return None;
+            }
+        };
+    let age = Duration::from_secs(unix_now().saturating_sub(cache.fetched_at));
+    Some((age, cache.items))
+}
+
+fn write_disk_cache(path: &Path, map: &HashMap<String, String>) {
+    let cache = DiskThumbCache {
+        fetched_at: unix_now(),
+        items: map,
+    };
+    match serde_json::to_string(&cache) {
+        Ok(raw) => {
+            if let Err(e) = crate::atomic_file::write_atomic(path, raw) {
+                log::warn!("thumbnails: failed to write disk cache: {}", e);
+            }
         }
+        Err(e) => log::warn!("thumbnails: failed to serialize disk cache: {}", e),
     }
+}
 
-    eprintln!("fetch_thumbnails_for_ids_cmd: returning {} thumbnails in {:?}", result.len(), start.elapsed());
-    Ok(result)
+/// Store a freshly downloaded map in memory and on disk.
+fn store_thumbnails(size: ThumbSize, map: HashMap<String, String>) {
+    if map.is_empty() {
+        return;
+    }
+    if let Some(path) = disk_cache_path(size) {
+        write_disk_cache(&path, &map);
+    }
+    if let Ok(mut cache_guard) = size.cache().write() {
+        *cache_guard = (Instant::now(), map);
+    }
 }
 
-/// Simple in-memory cache with TTL for thumbnails map.
-static THUMB_CACHE: Lazy<RwLock<(Instant, HashMap<String, String>)>> =
-    Lazy::new(|| RwLock::new((Instant::now() - Duration::from_secs(3600), HashMap::new())));
-const THUMB_TTL: Duration = Duration::from_secs(60 * 10); // 10 minutes
+/// Seed `size`'s cache from last session's disk copy if it is younger than the TTL.
+fn load_from_disk(size: ThumbSize) -> bool {
+    let Some((age, map)) = disk_cache_path(size).and_then(|p| read_disk_cache(&p)) else {
+        return false;
+    };
+    if !is_fresh(age) || map.is_empty() {
+        return false;
+    }
+    log::debug!(
+        "thumbnails: loaded {} {:?} entries from disk cache (age {:?})",
+        map.len(),
+        size,
+        age
+    );
+    if let Ok(mut cache_guard) = size.cache().write() {
+        let ts = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+        *cache_guard = (ts, map);
+    }
+    true
+}
+
+/// Drop the in-memory thumbnails maps of every size and their disk copies so the next
+/// lookup refetches. Returns the number of entries removed from memory.
+pub fn clear_cache() -> usize {
+    ThumbSize::ALL
+        .into_iter()
+        .map(|size| {
+            if let Some(path) = disk_cache_path(size) {
+                let _ = std::fs::remove_file(path);
+            }
+            match size.cache().write() {
+                Ok(mut cache_guard) => {
+                    let n = cache_guard.1.len();
+                    *cache_guard = (Instant::now() - Duration::from_secs(3600), HashMap::new());
+                    n
+                }
+                Err(_) => 0,
+            }
+        })
+        .sum()
+}
 
-/// Fetch Rolimons small thumbnails map and return a HashMap mapping item ID string -> data URL
-/// The function accepts a reqwest client reference to reuse connections. Results are cached for THUMB_TTL.
-pub async fn fetch_thumbnails_map(
-    client: &reqwest::Client,
+/// Re-download the map in the background after serving the disk copy.
+fn spawn_background_refresh(size: ThumbSize) {
+    if size.refreshing().swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        match download_thumbnails(size, Duration::from_secs(30)).await {
+            Ok(map) => store_thumbnails(size, map),
+            Err(e) => log::warn!("thumbnails: background refresh failed: {}", e),
+        }
+        size.refreshing().store(false, Ordering::SeqCst);
+    });
+}
+
+/// Look up small thumbnails for `ids`, returning item ID string -> data URL for the ids that
+/// have one. Only the requested entries are copied out of the cached map, so per-page lookups
+/// stay cheap.
+pub async fn fetch_thumbnails_for(
+    ids: &[u64],
+    timeout: Duration,
 ) -> Result<HashMap<String, String>, reqwest::Error> {
+    fetch_thumbnails_sized(ids, ThumbSize::Small, timeout).await
+}
+
+/// Like `fetch_thumbnails_for`, from the map of the given size.
+pub async fn fetch_thumbnails_sized(
+    ids: &[u64],
+    size: ThumbSize,
+    timeout: Duration,
+) -> Result<HashMap<String, String>, reqwest::Error> {
+    ensure_thumbnails_cached(size, timeout).await?;
+
+    let mut result = HashMap::with_capacity(ids.len());
+    if let Ok(cache_guard) = size.cache().read() {
+        let (_, ref map) = &*cache_guard;
+        for id in ids {
+            let key = id.to_string();
+            if let Some(url) = map.get(&key) {
+                result.insert(key, url.clone());
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Make sure `size`'s cache holds a fresh copy of that Rolimons thumbnails map
+/// (item ID string -> data URL), fetching it if it is older than the TTL.
+/// On the first call of a session the disk copy is used when fresh enough and a
+/// refresh is started in the background.
+/// `timeout` applies to the download if one is needed.
+async fn ensure_thumbnails_cached(
+    size: ThumbSize,
+    timeout: Duration,
+) -> Result<(), reqwest::Error> {
     // Check cache first
-    if let Ok(cache_guard) = THUMB_CACHE.read() {
+    let mut memory_empty = true;
+    if let Ok(cache_guard) = size.cache().read() {
         let (ts, ref map) = &*cache_guard;
-        if ts.elapsed() < THUMB_TTL && !map.is_empty() {
-            eprintln!(
-                "thumbnails: cache hit ({} entries, age {:?})",
+        if is_fresh(ts.elapsed()) && !map.is_empty() {
+            log::debug!(
+                "thumbnails: {:?} cache hit ({} entries, age {:?})",
+                size,
                 map.len(),
                 ts.elapsed()
             );
-            return Ok(map.clone());
+            return Ok(());
         }
+        memory_empty = map.is_empty();
     }
 
+    if memory_empty && load_from_disk(size) {
+        spawn_background_refresh(size);
+        return Ok(());
+    }
+
+    let map = download_thumbnails(size, timeout).await?;
+    store_thumbnails(size, map);
+    Ok(())
+}
+
+/// Download and parse the Rolimons thumbnails map of the given size.
+async fn download_thumbnails(
+    size: ThumbSize,
+    timeout: Duration,
+) -> Result<HashMap<String, String>, reqwest::Error> {
     let start = Instant::now();
-    eprintln!("thumbnails: cache miss, fetching full thumbnail map...");
+    log::debug!(
+        "thumbnails: {:?} cache miss, fetching full thumbnail map...",
+        size
+    );
     // fetch fresh
     let mut map: HashMap<String, String> = HashMap::new();
 
-    let resp = client
-        .get("https://api.rolimons.com/itemthumbs/v1/thumbssm")
-        .header(USER_AGENT, "rolimons-thumbs-fetcher/1.0")
+    rate_limit::acquire().await;
+    let resp = http::client()
+        .get(size.url())
+        .timeout(timeout)
         .send()
         .await?;
 
@@ -81,7 +333,7 @@ pub async fn fetch_thumbnails_map(
                 // Prefer the nested `items` object when present.
                 if let Some(items_val) = obj.remove("items") {
                     if let Value::Object(items_map) = items_val {
-                        eprintln!("thumbnails: items_map length {}", items_map.len());
+                        log::debug!("thumbnails: items_map length {}", items_map.len());
                         let mut seen = 0usize;
                         for (k, v) in items_map.into_iter() {
                             match v {
@@ -100,14 +352,14 @@ pub async fn fetch_thumbnails_map(
                                 other => {
                                     // Log a sample of unexpected value types to help debugging.
                                     if seen < 3 {
-                                        eprintln!("thumbnails: items_map[{}] = {}", k, other);
+                                        log::debug!("thumbnails: items_map[{}] = {}", k, other);
                                         seen += 1;
                                     }
                                 }
                             }
                         }
                     } else {
-                        eprintln!("thumbnails: 'items' field present but not an object");
+                        log::warn!("thumbnails: 'items' field present but not an object");
                     }
                 } else {
                     // Fallback: top-level object might itself be the map of id->string
@@ -125,10 +377,10 @@ pub async fn fetch_thumbnails_map(
                         }
                     }
                 }
-                eprintln!("thumbnails: fetched {} entries", map.len());
+                log::debug!("thumbnails: fetched {} entries", map.len());
             }
-            Ok(_) => eprintln!("thumbnails: unexpected JSON shape from thumbs endpoint"),
-            Err(e) => eprintln!("thumbnails: JSON parse error: {}", e),
+            Ok(_) => log::warn!("thumbnails: unexpected JSON shape from thumbs endpoint"),
+            Err(e) => log::error!("thumbnails: JSON parse error: {}", e),
         }
 
         // If we fetched zero entries, print a small preview of the response body to help
@@ -140,20 +392,80 @@ pub async fn fetch_thumbnails_map(
             } else {
                 text.clone()
             };
-            eprintln!("thumbnails: response preview (truncated): {}", preview);
+            log::warn!("thumbnails: response preview (truncated): {}", preview);
         }
     } else {
-        eprintln!(
+        log::error!(
             "thumbnails: HTTP error {} when fetching thumbnails",
             resp.status()
         );
     }
 
-    eprintln!("thumbnails: fetched and parsed {} thumbnails in {:?}", map.len(), start.elapsed());
-    // update cache
-    if let Ok(mut cache_guard) = THUMB_CACHE.write() {
-        *cache_guard = (Instant::now(), map.clone());
+    log::debug!(
+        "thumbnails: fetched and parsed {} thumbnails in {:?}",
+        map.len(),
+        start.elapsed()
+    );
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_cache_roundtrip() {
+        let path = std::env::temp_dir().join("roli-thumbs-cache-test.json");
+        let mut map = HashMap::new();
+        map.insert(
+            "1028606".to_string(),
+            "data:image/webp;base64,AAAA".to_string(),
+        );
+
+        write_disk_cache(&path, &map);
+        let (age, loaded) = read_disk_cache(&path).unwrap();
+        assert!(age < Duration::from_secs(DEFAULT_THUMB_TTL_SECS));
+        assert_eq!(loaded, map);
+
+        let _ = std::fs::remove_file(&path);
+        assert!(read_disk_cache(&path).is_none());
+    }
+
+    #[test]
+    fn test_zero_ttl_is_never_fresh() {
+        assert!(is_fresh(Duration::from_secs(0)));
+        set_cache_ttl_secs(0);
+        assert!(!is_fresh(Duration::from_secs(0)));
+        set_cache_ttl_secs(DEFAULT_THUMB_TTL_SECS);
+        assert!(!is_fresh(Duration::from_secs(DEFAULT_THUMB_TTL_SECS)));
     }
 
-    Ok(map)
+    #[test]
+    fn test_sizes_have_separate_caches() {
+        let key = "thumb-size-test".to_string();
+        if let Ok(mut cache_guard) = ThumbSize::Large.cache().write() {
+            cache_guard
+                .1
+                .insert(key.clone(), "data:image/webp;base64,LG".to_string());
+        }
+        assert!(!ThumbSize::Small
+            .cache()
+            .read()
+            .unwrap()
+            .1
+            .contains_key(&key));
+        assert!(ThumbSize::Large
+            .cache()
+            .read()
+            .unwrap()
+            .1
+            .contains_key(&key));
+        ThumbSize::Large.cache().write().unwrap().1.remove(&key);
+
+        assert_ne!(ThumbSize::Small.url(), ThumbSize::Large.url());
+        assert_ne!(
+            ThumbSize::Small.disk_cache_name(),
+            ThumbSize::Large.disk_cache_name()
+        );
+    }
 }
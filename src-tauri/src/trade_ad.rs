@@ -8,17 +8,40 @@
 pub mod request_search_roli {
     include!("request_search_roli.rs");
 }
+pub use request_search_roli::clear_item_cache;
+pub use request_search_roli::fetch_catalog_changes;
 pub use request_search_roli::fetch_item_details;
 pub use request_search_roli::fetch_items_by_ids;
+pub use request_search_roli::fetch_trade_evaluation;
+pub use request_search_roli::fetch_trending_items;
+pub use request_search_roli::refresh_item_cache;
+pub use request_search_roli::resolve_items;
+pub use request_search_roli::warm_item_cache;
+pub use request_search_roli::ChangeSort;
+pub use request_search_roli::ItemFilter;
+pub use request_search_roli::ItemResolution;
+pub use request_search_roli::ItemSort;
+pub use request_search_roli::MatchMode;
+pub use request_search_roli::TradeEvaluation;
+pub use request_search_roli::TrendingMetric;
+pub use request_search_roli::ValueSource;
 
 // Include post-trade-ad.rs into a valid Rust module name `post_trade_ad`.
 pub mod post_trade_ad {
     include!("post_trade_ad.rs");
 }
+pub use post_trade_ad::get_player_trade_ads;
+pub use post_trade_ad::post_cooldown_remaining;
 pub use post_trade_ad::post_trade_ad_direct;
+pub use post_trade_ad::post_trade_ad_with_client;
+pub use post_trade_ad::preview_trade_ad;
+pub use post_trade_ad::validate_roli_verification;
+pub use post_trade_ad::PostOptions;
+pub use post_trade_ad::PostedTradeAd;
 
 // Include thumbnails helper module
 pub mod thumbnails {
     include!("thumbnails.rs");
 }
-// thumbnails helper available as `crate::trade_ad::thumbnails::fetch_thumbnails_map`
+// thumbnails helper available as `crate::trade_ad::thumbnails::fetch_thumbnails_for`
+pub use thumbnails::ThumbSize;
@@ -4,37 +4,190 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Mutex;
 
-/// In-memory cache: catalog_id -> (name, value)
-static VALUE_CACHE: Lazy<Mutex<HashMap<u64, (String, u64)>>> =
+/// Cached (name, value, rap) for one catalog item.
+type CacheEntry = (String, u64, u64);
+
+/// One user's cache: catalog_id -> (name, value, rap)
+type UserCache = HashMap<u64, CacheEntry>;
+
+/// In-memory cache keyed by user_id, so accounts with different inventories don't
+/// compare against each other. A user's map is seeded from disk the first time
+/// that user is seen in this process.
+static VALUE_CACHE: Lazy<Mutex<HashMap<String, UserCache>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// On-disk form of a cache entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedValue {
+    name: String,
+    value: u64,
+    /// Missing in caches written before RAP was tracked; defaults to `value`.
+    #[serde(default)]
+    rap: Option<u64>,
+}
+
+/// A reported change. A field that did not move past the threshold has
+/// `old == new`, so `value_changed()` / `rap_changed()` tell which one fired.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValueChange {
     pub catalog_id: u64,
     pub name: String,
     pub old_value: u64,
     pub new_value: u64,
+    pub old_rap: u64,
+    pub new_rap: u64,
     pub thumbnail: Option<String>,
 }
 
-/// Detect value changes by comparing fresh inventory against cache.
-/// If cache is empty, populate it and return no changes.
-pub fn detect_value_changes(enriched_items: &[serde_json::Value]) -> Vec<ValueChange> {
-    let mut cache = VALUE_CACHE.lock().unwrap();
+impl ValueChange {
+    pub fn value_changed(&self) -> bool {
+        self.old_value != self.new_value
+    }
+
+    pub fn rap_changed(&self) -> bool {
+        self.old_rap != self.new_rap
+    }
+}
+
+/// Directory holding one cache file per user.
+fn cache_dir() -> Option<PathBuf> {
+    #[cfg(test)]
+    {
+        Some(std::env::temp_dir().join("roli-value-cache-test"))
+    }
+    #[cfg(not(test))]
+    {
+        let mut dir = dirs::data_local_dir()?;
+        dir.push("roli-trade-ad-automation");
+        dir.push("value_cache");
+        Some(dir)
+    }
+}
+
+/// Per-user cache file. Only alphanumeric characters of the user id are kept in the name.
+fn cache_file_path(user_id: &str) -> Option<PathBuf> {
+    let mut dir = cache_dir()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    let safe: String = user_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if safe.is_empty() {
+        return None;
+    }
+    dir.push(format!("{}.json", safe));
+    Some(dir)
+}
+
+/// Read a user's cache from disk (empty if there is none yet).
+fn load_cache(user_id: &str) -> UserCache {
+    let mut cache = UserCache::new();
+    let Some(path) = cache_file_path(user_id) else {
+        return cache;
+    };
+    if !path.exists() {
+        return cache;
+    }
+    match crate::atomic_file::read_with_backup(&path, |raw| {
+        Ok(serde_json::from_str::<HashMap<u64, CachedValue>>(raw)?)
+    }) {
+        Ok(stored) => {
+            for (id, entry) in stored {
+                let rap = entry.rap.unwrap_or(entry.value);
+                cache.insert(id, (entry.name, entry.value, rap));
+            }
+        }
+        Err(e) => log::warn!("value_change_detector: failed to parse cache file: {}", e),
+    }
+    cache
+}
+
+/// Write a user's cache to disk so changes made while the app is closed are detected.
+fn save_cache(user_id: &str, cache: &UserCache) {
+    let Some(path) = cache_file_path(user_id) else {
+        return;
+    };
+    let stored: HashMap<u64, CachedValue> = cache
+        .iter()
+        .map(|(id, (name, value, rap))| {
+            (
+                *id,
+                CachedValue {
+                    name: name.clone(),
+                    value: *value,
+                    rap: Some(*rap),
+                },
+            )
+        })
+        .collect();
+    match serde_json::to_string(&stored) {
+        Ok(raw) => {
+            if let Err(e) = crate::atomic_file::write_atomic(&path, raw) {
+                log::warn!("value_change_detector: failed to write cache file: {}", e);
+            }
+        }
+        Err(e) => log::warn!("value_change_detector: failed to serialize cache: {}", e),
+    }
+}
+
+/// Whether moving from `old` to `new` is a large enough change to report.
+/// `threshold_percent` is compared against `abs(new - old) / old * 100`; a zero
+/// old value always counts so a newly valued item still notifies.
+pub fn exceeds_threshold(old: u64, new: u64, threshold_percent: f64) -> bool {
+    if old == new {
+        return false;
+    }
+    if old == 0 {
+        return true;
+    }
+    let diff = old.abs_diff(new) as f64;
+    diff / old as f64 * 100.0 > threshold_percent
+}
+
+/// Detect value changes by comparing `user_id`'s fresh inventory against their cache.
+/// The cache is persisted between runs; if it is empty (no cache file yet),
+/// populate it and return no changes.
+///
+/// Value and RAP are compared independently; an item is reported when either moves
+/// by more than `threshold_percent`. Smaller moves leave that field's cached
+/// baseline untouched, so slow drift is still reported once it adds up.
+pub fn detect_value_changes(
+    user_id: &str,
+    enriched_items: &[serde_json::Value],
+    threshold_percent: f64,
+) -> Vec<ValueChange> {
+    let mut caches = VALUE_CACHE.lock().unwrap();
+    let cache = caches
+        .entry(user_id.to_string())
+        .or_insert_with(|| load_cache(user_id));
+    let changes = compare_and_update(cache, enriched_items, threshold_percent);
+    save_cache(user_id, cache);
+    changes
+}
+
+fn compare_and_update(
+    cache: &mut UserCache,
+    enriched_items: &[serde_json::Value],
+    threshold_percent: f64,
+) -> Vec<ValueChange> {
     let mut changes = Vec::new();
 
     // If cache is empty, this is first load - populate cache and return empty
     if cache.is_empty() {
         for item in enriched_items {
             if let (Some(catalog_id), Some(name), Some(value)) = (
-                item.get("catalog_id")
-                    .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok()))),
+                item.get("catalog_id").and_then(|v| {
+                    v.as_u64()
+                        .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+                }),
                 item.get("name").and_then(|v| v.as_str()),
                 item.get("value").and_then(|v| v.as_u64()),
             ) {
-                cache.insert(catalog_id, (name.to_string(), value));
+                let rap = item.get("rap").and_then(|v| v.as_u64()).unwrap_or(value);
+                cache.insert(catalog_id, (name.to_string(), value, rap));
             }
         }
         return changes;
@@ -43,40 +196,74 @@ pub fn detect_value_changes(enriched_items: &[serde_json::Value]) -> Vec<ValueCh
     // Compare current values against cache
     for item in enriched_items {
         let catalog_id = match item.get("catalog_id") {
-            Some(v) => v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())),
+            Some(v) => v
+                .as_u64()
+                .or_else(|| v.as_str().and_then(|s| s.parse().ok())),
             None => None,
         };
         let name = item.get("name").and_then(|v| v.as_str());
         let current_value = item.get("value").and_then(|v| v.as_u64());
-        let thumbnail = item.get("thumbnail").and_then(|v| v.as_str()).map(String::from);
+        let current_rap = item.get("rap").and_then(|v| v.as_u64());
+        let thumbnail = item
+            .get("thumbnail")
+            .and_then(|v| v.as_str())
+            .map(String::from);
 
         if let (Some(cid), Some(n), Some(cur_val)) = (catalog_id, name, current_value) {
-            if let Some((_cached_name, cached_value)) = cache.get(&cid) {
-                if *cached_value != cur_val {
-                    changes.push(ValueChange {
-                        catalog_id: cid,
-                        name: n.to_string(),
-                        old_value: *cached_value,
-                        new_value: cur_val,
-                        thumbnail,
-                    });
-                }
+            let Some(&(_, cached_value, cached_rap)) = cache.get(&cid) else {
+                let rap = current_rap.unwrap_or(cur_val);
+                cache.insert(cid, (n.to_string(), cur_val, rap));
+                continue;
+            };
+            // Unchanged or too small: keep the last reported figure as the baseline
+            let new_value = if exceeds_threshold(cached_value, cur_val, threshold_percent) {
+                cur_val
+            } else {
+                cached_value
+            };
+            let new_rap = match current_rap {
+                Some(rap) if exceeds_threshold(cached_rap, rap, threshold_percent) => rap,
+                _ => cached_rap,
+            };
+            if new_value == cached_value && new_rap == cached_rap {
+                continue;
             }
-            // Update cache with current value
-            cache.insert(cid, (n.to_string(), cur_val));
+            changes.push(ValueChange {
+                catalog_id: cid,
+                name: n.to_string(),
+                old_value: cached_value,
+                new_value,
+                old_rap: cached_rap,
+                new_rap,
+                thumbnail,
+            });
+            cache.insert(cid, (n.to_string(), new_value, new_rap));
         }
     }
 
     changes
 }
 
-/// Clear the value cache (for testing purposes)
+/// Clear the in-memory value cache for every user (for testing purposes). The on-disk
+/// copies are left alone and are reloaded the next time each user is seen.
 #[allow(dead_code)]
 pub fn clear_cache() {
     let mut cache = VALUE_CACHE.lock().unwrap();
     cache.clear();
 }
 
+/// Forget every user's baseline, in memory and on disk. The next fetch for each user
+/// repopulates the cache without reporting changes. Returns the number of users cleared.
+pub fn reset_cache() -> usize {
+    let mut cache = VALUE_CACHE.lock().unwrap();
+    let n = cache.len();
+    cache.clear();
+    if let Some(dir) = cache_dir() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    n
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,10 +273,31 @@ mod tests {
     // Serialize test execution to avoid cache conflicts
     static TEST_LOCK: Mutex<()> = Mutex::new(());
 
+    const TEST_USER: &str = "1426170901";
+
+    /// Start `user` from an empty cache without reading anything left on disk.
+    fn reset(user: &str) {
+        clear_cache();
+        VALUE_CACHE
+            .lock()
+            .unwrap()
+            .insert(user.to_string(), UserCache::new());
+    }
+
+    /// Put `(name, value, rap)` for `id` in `user`'s cache, as if a previous fetch saw it.
+    fn seed(user: &str, id: u64, (name, value, rap): (&str, u64, u64)) {
+        VALUE_CACHE
+            .lock()
+            .unwrap()
+            .get_mut(user)
+            .unwrap()
+            .insert(id, (name.to_string(), value, rap));
+    }
+
     #[test]
     fn test_first_load_populates_cache() {
         let _guard = TEST_LOCK.lock().unwrap();
-        clear_cache();
+        reset(TEST_USER);
 
         let items = vec![
             json!({
@@ -105,25 +313,26 @@ mod tests {
             }),
         ];
 
-        let changes = detect_value_changes(&items);
+        let changes = detect_value_changes(TEST_USER, &items, 0.0);
 
         // First load should return no changes
         assert_eq!(changes.len(), 0);
 
         // Cache should be populated
-        let cache = VALUE_CACHE.lock().unwrap();
+        let caches = VALUE_CACHE.lock().unwrap();
+        let cache = &caches[TEST_USER];
         assert_eq!(cache.len(), 2);
-        assert_eq!(cache.get(&1001), Some(&("Valkyrie Helm".to_string(), 5000000)));
+        assert_eq!(
+            cache.get(&1001),
+            Some(&("Valkyrie Helm".to_string(), 5000000, 5000000))
+        );
     }
 
     #[test]
     fn test_value_change_detected() {
         let _guard = TEST_LOCK.lock().unwrap();
-        clear_cache();
-        {
-            let mut cache = VALUE_CACHE.lock().unwrap();
-            cache.insert(2001, ("Test Item".to_string(), 1000000));
-        }
+        reset(TEST_USER);
+        seed(TEST_USER, 2001, ("Test Item", 1000000, 1000000));
 
         let items = vec![json!({
             "catalog_id": 2001,
@@ -132,24 +341,24 @@ mod tests {
             "thumbnail": "http://example.com/test.png"
         })];
 
-        let changes = detect_value_changes(&items);
+        let changes = detect_value_changes(TEST_USER, &items, 0.0);
 
         assert_eq!(changes.len(), 1);
         assert_eq!(changes[0].catalog_id, 2001);
         assert_eq!(changes[0].name, "Test Item");
         assert_eq!(changes[0].old_value, 1000000);
         assert_eq!(changes[0].new_value, 2000000);
-        assert_eq!(changes[0].thumbnail, Some("http://example.com/test.png".to_string()));
+        assert_eq!(
+            changes[0].thumbnail,
+            Some("http://example.com/test.png".to_string())
+        );
     }
 
     #[test]
     fn test_no_change_returns_empty() {
         let _guard = TEST_LOCK.lock().unwrap();
-        clear_cache();
-        {
-            let mut cache = VALUE_CACHE.lock().unwrap();
-            cache.insert(3001, ("Stable Item".to_string(), 500000));
-        }
+        reset(TEST_USER);
+        seed(TEST_USER, 3001, ("Stable Item", 500000, 500000));
 
         let items = vec![json!({
             "catalog_id": 3001,
@@ -157,14 +366,14 @@ mod tests {
             "value": 500000,
         })];
 
-        let changes = detect_value_changes(&items);
+        let changes = detect_value_changes(TEST_USER, &items, 0.0);
         assert_eq!(changes.len(), 0);
     }
 
     #[test]
     fn test_catalog_id_as_string() {
         let _guard = TEST_LOCK.lock().unwrap();
-        clear_cache();
+        reset(TEST_USER);
 
         let items = vec![json!({
             "catalog_id": "4001",
@@ -172,18 +381,142 @@ mod tests {
             "value": 750000,
         })];
 
-        let changes = detect_value_changes(&items);
+        let changes = detect_value_changes(TEST_USER, &items, 0.0);
         assert_eq!(changes.len(), 0);
 
-        let cache = VALUE_CACHE.lock().unwrap();
+        let caches = VALUE_CACHE.lock().unwrap();
+        let cache = &caches[TEST_USER];
         assert!(cache.contains_key(&4001));
     }
 
     #[test]
-    fn test_integration_full_notification_flow() {
+    fn test_cache_survives_reload() {
         let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+
+        let items = vec![json!({
+            "catalog_id": 6001,
+            "name": "Persisted Item",
+            "value": 100000,
+        })];
+        // first load populates and writes the cache file
+        assert_eq!(detect_value_changes(TEST_USER, &items, 0.0).len(), 0);
+
+        // simulate an app restart: empty memory, reload from disk on next access
         clear_cache();
 
+        let changed = vec![json!({
+            "catalog_id": 6001,
+            "name": "Persisted Item",
+            "value": 120000,
+        })];
+        let changes = detect_value_changes(TEST_USER, &changed, 0.0);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_value, 100000);
+        assert_eq!(changes[0].new_value, 120000);
+    }
+
+    #[test]
+    fn test_exceeds_threshold() {
+        assert!(exceeds_threshold(1000, 1100, 5.0));
+        assert!(exceeds_threshold(1000, 900, 5.0));
+        assert!(!exceeds_threshold(1000, 1001, 5.0));
+        assert!(!exceeds_threshold(1000, 1050, 5.0));
+        assert!(!exceeds_threshold(1000, 1000, 0.0));
+        assert!(exceeds_threshold(0, 1, 50.0));
+    }
+
+    #[test]
+    fn test_change_below_threshold_not_reported() {
+        let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+        seed(TEST_USER, 7001, ("Wiggly Item", 1000000, 1000000));
+
+        let items = vec![json!({
+            "catalog_id": 7001,
+            "name": "Wiggly Item",
+            "value": 1001000,
+        })];
+        assert_eq!(detect_value_changes(TEST_USER, &items, 5.0).len(), 0);
+
+        // small moves accumulate against the last reported value
+        let items = vec![json!({
+            "catalog_id": 7001,
+            "name": "Wiggly Item",
+            "value": 1060000,
+        })];
+        let changes = detect_value_changes(TEST_USER, &items, 5.0);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_value, 1000000);
+        assert_eq!(changes[0].new_value, 1060000);
+    }
+
+    #[test]
+    fn test_change_from_zero_always_reported() {
+        let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+        seed(TEST_USER, 8001, ("Newly Valued", 0, 0));
+
+        let items = vec![json!({
+            "catalog_id": 8001,
+            "name": "Newly Valued",
+            "value": 10,
+        })];
+        let changes = detect_value_changes(TEST_USER, &items, 100.0);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_value, 0);
+    }
+
+    #[test]
+    fn test_rap_change_detected_without_value_change() {
+        let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+        seed(TEST_USER, 9001, ("RAP Mover", 50000, 40000));
+
+        let items = vec![json!({
+            "catalog_id": 9001,
+            "name": "RAP Mover",
+            "value": 50000,
+            "rap": 48000,
+        })];
+        let changes = detect_value_changes(TEST_USER, &items, 5.0);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].value_changed());
+        assert!(changes[0].rap_changed());
+        assert_eq!(changes[0].old_rap, 40000);
+        assert_eq!(changes[0].new_rap, 48000);
+    }
+
+    #[test]
+    fn test_legacy_cache_entry_defaults_rap_to_value() {
+        let entry: CachedValue =
+            serde_json::from_str(r#"{"name":"Old Entry","value":1234}"#).unwrap();
+        assert_eq!(entry.rap, None);
+
+        let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+        let path = cache_file_path(TEST_USER).unwrap();
+        std::fs::write(&path, r#"{"10001":{"name":"Old Entry","value":1234}}"#).unwrap();
+        clear_cache();
+
+        let items = vec![json!({
+            "catalog_id": 10001,
+            "name": "Old Entry",
+            "value": 1234,
+            "rap": 1234,
+        })];
+        assert_eq!(detect_value_changes(TEST_USER, &items, 0.0).len(), 0);
+        assert_eq!(
+            VALUE_CACHE.lock().unwrap()[TEST_USER].get(&10001),
+            Some(&("Old Entry".to_string(), 1234, 1234))
+        );
+    }
+
+    #[test]
+    fn test_integration_full_notification_flow() {
+        let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+
         // Simulate first inventory load
         let first_load = vec![
             json!({
@@ -199,8 +532,12 @@ mod tests {
             }),
         ];
 
-        let changes = detect_value_changes(&first_load);
-        assert_eq!(changes.len(), 0, "First load should not produce any changes");
+        let changes = detect_value_changes(TEST_USER, &first_load, 0.0);
+        assert_eq!(
+            changes.len(),
+            0,
+            "First load should not produce any changes"
+        );
 
         // Simulate second load with value changes
         let second_load = vec![
@@ -217,7 +554,7 @@ mod tests {
             }),
         ];
 
-        let changes = detect_value_changes(&second_load);
+        let changes = detect_value_changes(TEST_USER, &second_load, 0.0);
         assert_eq!(changes.len(), 2, "Should detect both value changes");
 
         // Verify first change (decrease)
@@ -225,7 +562,10 @@ mod tests {
         assert_eq!(changes[0].name, "Valkyrie Helm");
         assert_eq!(changes[0].old_value, 7000000);
         assert_eq!(changes[0].new_value, 6000000);
-        assert_eq!(changes[0].thumbnail, Some("http://example.com/valkyrie.png".to_string()));
+        assert_eq!(
+            changes[0].thumbnail,
+            Some("http://example.com/valkyrie.png".to_string())
+        );
 
         // Verify second change (increase)
         assert_eq!(changes[1].catalog_id, 5002);
@@ -247,7 +587,54 @@ mod tests {
             }),
         ];
 
-        let changes = detect_value_changes(&third_load);
-        assert_eq!(changes.len(), 0, "No changes should be detected when values are stable");
+        let changes = detect_value_changes(TEST_USER, &third_load, 0.0);
+        assert_eq!(
+            changes.len(),
+            0,
+            "No changes should be detected when values are stable"
+        );
+    }
+
+    #[test]
+    fn test_users_have_separate_caches() {
+        let _guard = TEST_LOCK.lock().unwrap();
+        let alt_user = "9876543210";
+        reset(TEST_USER);
+        VALUE_CACHE
+            .lock()
+            .unwrap()
+            .insert(alt_user.to_string(), UserCache::new());
+
+        // Same catalog id, different values per account
+        let mine = vec![json!({ "catalog_id": 11001, "name": "Shared Item", "value": 1000 })];
+        let theirs = vec![json!({ "catalog_id": 11001, "name": "Shared Item", "value": 5000 })];
+        assert_eq!(detect_value_changes(TEST_USER, &mine, 0.0).len(), 0);
+        assert_eq!(detect_value_changes(alt_user, &theirs, 0.0).len(), 0);
+
+        // Switching back and forth between accounts is not a change
+        assert_eq!(detect_value_changes(TEST_USER, &mine, 0.0).len(), 0);
+        assert_eq!(detect_value_changes(alt_user, &theirs, 0.0).len(), 0);
+
+        // A real change for one user is only reported for that user
+        let mine_changed =
+            vec![json!({ "catalog_id": 11001, "name": "Shared Item", "value": 1200 })];
+        let changes = detect_value_changes(TEST_USER, &mine_changed, 0.0);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_value, 1000);
+        assert_eq!(detect_value_changes(alt_user, &theirs, 0.0).len(), 0);
+    }
+
+    #[test]
+    fn test_reset_cache_forgets_baselines() {
+        let _guard = TEST_LOCK.lock().unwrap();
+        reset(TEST_USER);
+
+        let items = vec![json!({ "catalog_id": 12001, "name": "Reset Item", "value": 1000 })];
+        assert_eq!(detect_value_changes(TEST_USER, &items, 0.0).len(), 0);
+        assert_eq!(reset_cache(), 1);
+
+        // After a reset the next fetch is treated as a first load, even across a reload
+        let changed = vec![json!({ "catalog_id": 12001, "name": "Reset Item", "value": 2000 })];
+        assert_eq!(detect_value_changes(TEST_USER, &changed, 0.0).len(), 0);
     }
 }
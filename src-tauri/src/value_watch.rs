@@ -0,0 +1,144 @@
+// value_watch.rs
+// Responsibility: Background polling of a user's own inventory so value-change notifications
+// fire even when the inventory tab isn't open. One watcher per user, cancelled like ad runners.
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+/// Shortest allowed polling interval; inventories and values don't move faster than this.
+pub const MIN_WATCH_INTERVAL_MINS: u64 = 5;
+
+struct WatchHandle {
+    cancel: oneshot::Sender<()>,
+    // unique id of the spawned task, so an exiting task never removes a newer watcher
+    watch_id: u64,
+    player_id: u64,
+    interval_minutes: u64,
+}
+
+// map: user_id -> handle of its watcher task
+static WATCHERS: Lazy<Mutex<HashMap<String, WatchHandle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static WATCH_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ValueWatch {
+    pub user_id: String,
+    pub player_id: u64,
+    pub interval_minutes: u64,
+}
+
+/// Start polling `player_id`'s inventory every `interval_minutes` for `user_id`. An existing
+/// watcher for the same user is replaced, so there is never more than one.
+pub fn start_value_watch(
+    app: AppHandle,
+    user_id: String,
+    player_id: u64,
+    interval_minutes: u64,
+) -> Result<()> {
+    if interval_minutes < MIN_WATCH_INTERVAL_MINS {
+        return Err(anyhow!(
+            "Interval must be at least {} minutes",
+            MIN_WATCH_INTERVAL_MINS
+        ));
+    }
+
+    let (tx, mut rx) = oneshot::channel::<()>();
+    let my_id = WATCH_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let previous = WATCHERS.lock().unwrap().insert(
+        user_id.clone(),
+        WatchHandle {
+            cancel: tx,
+            watch_id: my_id,
+            player_id,
+            interval_minutes,
+        },
+    );
+    if let Some(old) = previous {
+        let _ = old.cancel.send(());
+        log::info!(
+            "value_watch: replaced existing watcher for user {}",
+            user_id
+        );
+    }
+    log::info!(
+        "value_watch: watching player {} for user {} every {} min",
+        player_id,
+        user_id,
+        interval_minutes
+    );
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            // skip the fetch entirely while notifications are off; nothing would be shown
+            match crate::notification_settings::get_notification_enabled(&user_id) {
+                Ok(true) => {
+                    if let Err(e) = crate::enrich_inventory(
+                        &app,
+                        player_id,
+                        Some(user_id.clone()),
+                        Some(true),
+                        None,
+                    )
+                    .await
+                    {
+                        log::warn!("value_watch: refresh for user {} failed: {}", user_id, e);
+                    }
+                }
+                Ok(false) => {
+                    log::debug!(
+                        "value_watch: notifications disabled for user {}, skipping",
+                        user_id
+                    );
+                }
+                Err(e) => log::warn!("value_watch: failed to read settings: {}", e),
+            }
+
+            let sleep = tokio::time::sleep(std::time::Duration::from_secs(interval_minutes * 60));
+            tokio::select! {
+                _ = &mut rx => break,
+                _ = sleep => continue,
+            }
+        }
+
+        let mut guard = WATCHERS.lock().unwrap();
+        if guard.get(&user_id).is_some_and(|h| h.watch_id == my_id) {
+            guard.remove(&user_id);
+        }
+        log::debug!("value_watch: task for user {} exiting", user_id);
+    });
+
+    Ok(())
+}
+
+/// Stop the user's watcher. Returns whether one was running.
+pub fn stop_value_watch(user_id: &str) -> bool {
+    match WATCHERS.lock().unwrap().remove(user_id) {
+        Some(handle) => {
+            let _ = handle.cancel.send(());
+            log::info!("value_watch: stopped watcher for user {}", user_id);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn list_value_watches() -> Vec<ValueWatch> {
+    WATCHERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(user_id, h)| ValueWatch {
+            user_id: user_id.clone(),
+            player_id: h.player_id,
+            interval_minutes: h.interval_minutes,
+        })
+        .collect()
+}
@@ -1,7 +1,28 @@
 // verification.rs
 // Responsibility: Generate random verification codes for user authentication.
 
+use anyhow::{anyhow, Result};
 use rand::seq::SliceRandom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long an issued code stays valid by default.
+pub const DEFAULT_CODE_TTL_SECS: u64 = 15 * 60;
+static CODE_TTL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_CODE_TTL_SECS);
+
+// the one code currently handed out (normalized) and when it was issued; issuing a new
+// code replaces it, so older codes stop working
+static ISSUED_CODE: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// State of a code presented for verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeStatus {
+    Valid,
+    Expired,
+    /// Not the code most recently issued by this app session.
+    Unknown,
+}
 
 const WORDS: &[&str] = &[
     "apple",
@@ -56,15 +77,105 @@ const WORDS: &[&str] = &[
 
 /// Generate a random verification code with 5-10 words.
 pub fn generate_verification_code() -> String {
-    let mut rng = rand::thread_rng();
-    let word_count = rand::Rng::gen_range(&mut rng, 5..=10);
+    let word_count = rand::Rng::gen_range(&mut rand::thread_rng(), 5..=10);
+    // the built-in list always has enough words for 10
+    generate_verification_code_with(word_count, None).unwrap_or_default()
+}
+
+/// Generate a code of `word_count` distinct words, picked from `words` when given
+/// (blank entries and duplicates are ignored) or from the built-in list otherwise.
+pub fn generate_verification_code_with(
+    word_count: usize,
+    words: Option<Vec<String>>,
+) -> Result<String> {
+    let pool: Vec<String> = match words {
+        Some(custom) => {
+            let mut pool: Vec<String> = Vec::new();
+            for w in custom {
+                let w = w.trim().to_string();
+                if w.chars().any(char::is_whitespace) {
+                    return Err(anyhow!("Words must not contain spaces: {:?}", w));
+                }
+                if !w.is_empty() && !pool.contains(&w) {
+                    pool.push(w);
+                }
+            }
+            pool
+        }
+        None => WORDS.iter().map(|w| w.to_string()).collect(),
+    };
+
+    if word_count == 0 {
+        return Err(anyhow!("A verification code needs at least one word"));
+    }
+    if word_count > pool.len() {
+        return Err(anyhow!(
+            "Cannot pick {} distinct words from a list of {}",
+            word_count,
+            pool.len()
+        ));
+    }
 
-    let selected: Vec<&str> = WORDS
+    let mut rng = rand::thread_rng();
+    let selected: Vec<&str> = pool
         .choose_multiple(&mut rng, word_count)
-        .copied()
+        .map(String::as_str)
         .collect();
 
-    selected.join(" ")
+    Ok(selected.join(" "))
+}
+
+/// Lowercase and collapse every run of whitespace (including newlines) to a single space.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether a profile description contains the verification code, ignoring case,
+/// surrounding whitespace and line breaks inside the code.
+pub fn description_contains_code(description: &str, code: &str) -> bool {
+    let code = normalize(code);
+    !code.is_empty() && normalize(description).contains(&code)
+}
+
+pub fn code_ttl_secs() -> u64 {
+    CODE_TTL_SECS.load(Ordering::Relaxed)
+}
+
+pub fn set_code_ttl_secs(secs: u64) {
+    CODE_TTL_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Remember `code` as the active code, invalidating any previously issued one.
+pub fn issue_code(code: &str) {
+    *ISSUED_CODE.lock().unwrap() = Some((normalize(code), Instant::now()));
+}
+
+/// Forget the active code, e.g. once it has been used successfully.
+pub fn consume_code() {
+    *ISSUED_CODE.lock().unwrap() = None;
+}
+
+fn classify(issued: Option<(&str, Duration)>, code: &str, ttl: Duration) -> CodeStatus {
+    match issued {
+        Some((issued_code, age)) if issued_code == normalize(code) => {
+            if age > ttl {
+                CodeStatus::Expired
+            } else {
+                CodeStatus::Valid
+            }
+        }
+        _ => CodeStatus::Unknown,
+    }
+}
+
+/// Check `code` against the active code and the configured expiry window.
+pub fn issued_code_status(code: &str) -> CodeStatus {
+    let guard = ISSUED_CODE.lock().unwrap();
+    let issued = guard.as_ref().map(|(c, at)| (c.as_str(), at.elapsed()));
+    classify(issued, code, Duration::from_secs(code_ttl_secs()))
 }
 
 #[cfg(test)]
@@ -77,4 +188,57 @@ mod tests {
         let words: Vec<&str> = code.split_whitespace().collect();
         assert!(words.len() >= 5 && words.len() <= 10);
     }
+
+    #[test]
+    fn test_word_count_bounds() {
+        assert!(generate_verification_code_with(0, None).is_err());
+        assert!(generate_verification_code_with(WORDS.len() + 1, None).is_err());
+        let code = generate_verification_code_with(WORDS.len(), None).unwrap();
+        assert_eq!(code.split_whitespace().count(), WORDS.len());
+    }
+
+    #[test]
+    fn test_custom_word_list() {
+        let words = vec![
+            "alpha".to_string(),
+            "beta".to_string(),
+            " gamma ".to_string(),
+            "".to_string(),
+            "alpha".to_string(),
+        ];
+        let code = generate_verification_code_with(3, Some(words.clone())).unwrap();
+        let mut picked: Vec<&str> = code.split_whitespace().collect();
+        picked.sort();
+        assert_eq!(picked, vec!["alpha", "beta", "gamma"]);
+        // only three distinct non-blank words are available
+        assert!(generate_verification_code_with(4, Some(words)).is_err());
+        assert!(generate_verification_code_with(1, Some(vec!["two words".to_string()])).is_err());
+    }
+
+    #[test]
+    fn test_code_expiry() {
+        let ttl = Duration::from_secs(900);
+        let fresh = Some(("apple banana", Duration::from_secs(60)));
+        let stale = Some(("apple banana", Duration::from_secs(901)));
+        assert_eq!(classify(fresh, " Apple  banana", ttl), CodeStatus::Valid);
+        assert_eq!(classify(stale, "apple banana", ttl), CodeStatus::Expired);
+        assert_eq!(classify(fresh, "cherry", ttl), CodeStatus::Unknown);
+        assert_eq!(classify(None, "apple banana", ttl), CodeStatus::Unknown);
+    }
+
+    #[test]
+    fn test_description_contains_code() {
+        let code = "apple Banana cherry";
+        assert!(description_contains_code(
+            "hi! apple banana cherry :)",
+            code
+        ));
+        assert!(description_contains_code("APPLE BANANA CHERRY", code));
+        assert!(description_contains_code(
+            "bio\napple  banana\r\n cherry\n",
+            "  apple banana cherry \n"
+        ));
+        assert!(!description_contains_code("apple cherry banana", code));
+        assert!(!description_contains_code("anything", "   "));
+    }
 }
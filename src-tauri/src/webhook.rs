@@ -0,0 +1,156 @@
+// webhook.rs
+// Responsibility: Push value-change and post events to a Discord webhook
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::http;
+use crate::value_change_detector::ValueChange;
+
+/// Embed colours (decimal RGB) used by Discord.
+const COLOR_INCREASE: u32 = 0x2ecc71;
+const COLOR_DECREASE: u32 = 0xe74c3c;
+const COLOR_INFO: u32 = 0x3498db;
+
+/// Whether `url` looks like a Discord webhook URL.
+pub fn is_discord_webhook_url(url: &str) -> bool {
+    [
+        "https://discord.com/api/webhooks/",
+        "https://discordapp.com/api/webhooks/",
+        "https://ptb.discord.com/api/webhooks/",
+        "https://canary.discord.com/api/webhooks/",
+    ]
+    .iter()
+    .any(|prefix| url.starts_with(prefix))
+}
+
+/// Build the embed for a value change. Only figures listed in `show_value` /
+/// `show_rap` are included.
+fn value_change_embed(
+    change: &ValueChange,
+    title: &str,
+    show_value: bool,
+    show_rap: bool,
+) -> Value {
+    let mut fields = Vec::new();
+    if show_value {
+        fields.push(
+            json!({ "name": "Old Value", "value": change.old_value.to_string(), "inline": true }),
+        );
+        fields.push(
+            json!({ "name": "New Value", "value": change.new_value.to_string(), "inline": true }),
+        );
+    }
+    if show_rap {
+        fields.push(
+            json!({ "name": "Old RAP", "value": change.old_rap.to_string(), "inline": true }),
+        );
+        fields.push(
+            json!({ "name": "New RAP", "value": change.new_rap.to_string(), "inline": true }),
+        );
+    }
+
+    let increased = if show_value {
+        change.new_value > change.old_value
+    } else {
+        change.new_rap > change.old_rap
+    };
+
+    let mut embed = json!({
+        "title": title,
+        "description": change.name,
+        "url": format!("https://www.rolimons.com/item/{}", change.catalog_id),
+        "color": if increased { COLOR_INCREASE } else { COLOR_DECREASE },
+        "fields": fields,
+    });
+    // Thumbnails are usually inlined data URLs, which Discord can't display
+    if let Some(thumb) = change
+        .thumbnail
+        .as_deref()
+        .filter(|t| t.starts_with("http"))
+    {
+        embed["thumbnail"] = json!({ "url": thumb });
+    }
+    embed
+}
+
+async fn send_embed(url: &str, embed: Value) -> Result<()> {
+    let resp = http::client()
+        .post(url)
+        .json(&json!({ "embeds": [embed] }))
+        .send()
+        .await?;
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(anyhow!("Discord webhook returned {}: {}", status, body));
+    }
+    Ok(())
+}
+
+/// Post a value-change embed to the webhook.
+pub async fn send_value_change(
+    url: &str,
+    change: &ValueChange,
+    title: &str,
+    show_value: bool,
+    show_rap: bool,
+) -> Result<()> {
+    send_embed(url, value_change_embed(change, title, show_value, show_rap)).await
+}
+
+/// Post a "trade ad posted" embed to the webhook.
+pub async fn send_post_success(url: &str, ad_name: &str, count: u64) -> Result<()> {
+    let embed = json!({
+        "title": "Trade Ad Posted",
+        "description": ad_name,
+        "color": COLOR_INFO,
+        "fields": [{ "name": "Posts this session", "value": count.to_string(), "inline": true }],
+    });
+    send_embed(url, embed).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_change() -> ValueChange {
+        ValueChange {
+            catalog_id: 1365767,
+            name: "Valkyrie Helm".to_string(),
+            old_value: 100000,
+            new_value: 90000,
+            old_rap: 80000,
+            new_rap: 80000,
+            thumbnail: Some("data:image/png;base64,AAAA".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_webhook_url_validation() {
+        assert!(is_discord_webhook_url(
+            "https://discord.com/api/webhooks/1/abc"
+        ));
+        assert!(is_discord_webhook_url(
+            "https://discordapp.com/api/webhooks/1/abc"
+        ));
+        assert!(!is_discord_webhook_url(
+            "http://discord.com/api/webhooks/1/abc"
+        ));
+        assert!(!is_discord_webhook_url(
+            "https://example.com/api/webhooks/1/abc"
+        ));
+    }
+
+    #[test]
+    fn test_value_change_embed() {
+        let embed = value_change_embed(&sample_change(), "Item Value Changed", true, false);
+        assert_eq!(embed["description"], "Valkyrie Helm");
+        assert_eq!(embed["color"], COLOR_DECREASE);
+        assert_eq!(embed["fields"].as_array().unwrap().len(), 2);
+        assert_eq!(embed["fields"][1]["value"], "90000");
+        // data URLs are dropped
+        assert!(embed.get("thumbnail").is_none());
+    }
+}